@@ -0,0 +1,120 @@
+//! Command line definitions of the `floq` binary, kept apart from `main` so the
+//! mapping of commands to pipeline stages can be tested.
+
+use clap::{ArgAction, Parser, Subcommand};
+use floq::config::{Params, PipelineConfig, StageConfig};
+use serde_json::json;
+use std::path::PathBuf;
+
+#[derive(Parser)]
+#[command(name = "floq", version, about = "Runs floq stream processing pipelines")]
+pub struct Cli {
+    /// Log more: -v for info, -vv for debug, -vvv for trace
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Only log errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Runtime worker threads, by default one per slot plus one per CPU
+    #[arg(long, global = true)]
+    pub worker_threads: Option<usize>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a pipeline from a YAML or TOML config file
+    Run {
+        config: PathBuf,
+    },
+    /// Print the messages of a built-in source to stdout
+    Tail {
+        #[command(subcommand)]
+        source: TailSource,
+    },
+    /// Check a config file without running it
+    Validate {
+        config: PathBuf,
+    },
+    /// Print the stages of a config file and the types passed between them
+    Graph {
+        config: PathBuf,
+        /// Print the graph in Graphviz DOT format
+        #[arg(long)]
+        dot: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TailSource {
+    /// Bluesky firehose
+    Bluesky,
+    /// Public Mastodon timeline of a server
+    Mastodon {
+        server_url: String,
+        /// Environment variable holding the access token
+        #[arg(long)]
+        access_token_env: Option<String>,
+    },
+    /// Text messages of a WebSocket server
+    Websocket {
+        url: String,
+    },
+    /// MQTT over WebSocket
+    Mqtt {
+        url: String,
+        #[arg(long, default_value = "#")]
+        topic: String,
+        #[arg(long, default_value = "floq")]
+        client_id: String,
+    },
+    /// Lines of a file
+    File {
+        path: PathBuf,
+        /// Keep emitting lines appended to the file
+        #[arg(short, long)]
+        follow: bool,
+    },
+}
+
+impl TailSource {
+    /// The source stage reading from this source
+    pub fn stage(&self) -> StageConfig {
+        let (kind, params) = match self {
+            TailSource::Bluesky => ("bluesky", json!({})),
+            TailSource::Mastodon { server_url, access_token_env } => {
+                let mut params = json!({ "server_url": server_url });
+                if let Some(var) = access_token_env {
+                    params["access_token_env"] = json!(var);
+                }
+                ("mastodon", params)
+            }
+            TailSource::Websocket { url } => ("websocket", json!({ "url": url })),
+            TailSource::Mqtt { url, topic, client_id } => {
+                ("mqtt_websocket", json!({ "url": url, "topic": topic, "client_id": client_id }))
+            }
+            TailSource::File { path, follow: false } => ("file", json!({ "path": path })),
+            TailSource::File { path, follow: true } => ("tail_file", json!({ "path": path })),
+        };
+        stage(kind, params)
+    }
+
+    /// Pipeline printing the messages of this source
+    pub fn config(&self) -> PipelineConfig {
+        PipelineConfig {
+            stages: vec![self.stage(), stage("printer", json!({}))],
+        }
+    }
+}
+
+fn stage(kind: &str, params: serde_json::Value) -> StageConfig {
+    StageConfig {
+        kind: kind.to_string(),
+        slots: 1,
+        params: params.as_object().cloned().unwrap_or_else(Params::new),
+    }
+}
@@ -0,0 +1,149 @@
+use clap::Parser;
+use floq::config::{ComponentRegistry, PipelineConfig, ValueType};
+use floq_cli::{Cli, Command};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+use tracing::{error, info, warn, Level};
+
+// How long the pipeline may take to drain after Ctrl-C stopped its sources
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long to wait for slots to finish once the pipeline completed or failed
+// to drain; slots blocked waiting for input never yield, so the runtime cannot
+// wait for them indefinitely
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Exit code of a process stopped by SIGINT
+const INTERRUPTED: u8 = 130;
+
+fn init_tracing(cli: &Cli) {
+    let level = match (cli.quiet, cli.verbose) {
+        (true, _) => Level::ERROR,
+        (false, 0) => Level::WARN,
+        (false, 1) => Level::INFO,
+        (false, 2) => Level::DEBUG,
+        (false, _) => Level::TRACE,
+    };
+
+    // Logs go to stderr so stdout only carries pipeline output
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+fn load(path: &Path) -> Result<PipelineConfig, ExitCode> {
+    PipelineConfig::from_file(path).map_err(|e| {
+        eprintln!("error: {}", e);
+        ExitCode::FAILURE
+    })
+}
+
+fn validate(registry: &ComponentRegistry, path: &Path) -> Result<ExitCode, ExitCode> {
+    let config = load(path)?;
+    match registry.validate(&config) {
+        Ok(_) => {
+            println!("{}: ok, {} stages", path.display(), config.stages.len());
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            eprintln!("error: {}: {}", path.display(), e);
+            Err(ExitCode::FAILURE)
+        }
+    }
+}
+
+fn graph(registry: &ComponentRegistry, path: &Path, dot: bool) -> Result<ExitCode, ExitCode> {
+    let config = load(path)?;
+    let outputs = registry.validate(&config).map_err(|e| {
+        eprintln!("error: {}: {}", path.display(), e);
+        ExitCode::FAILURE
+    })?;
+    let inputs = std::iter::once(ValueType::None).chain(outputs.iter().copied());
+
+    if dot {
+        println!("digraph pipeline {{");
+        println!("    rankdir=LR;");
+        for (index, stage) in config.stages.iter().enumerate() {
+            let slots = if stage.slots > 1 { format!("\\n{} slots", stage.slots) } else { String::new() };
+            println!("    s{} [label=\"{}{}\"];", index, stage.kind, slots);
+        }
+        for (index, output) in outputs.iter().enumerate().take(outputs.len() - 1) {
+            println!("    s{} -> s{} [label=\"{}\"];", index, index + 1, output);
+        }
+        println!("}}");
+    } else {
+        for ((index, stage), (input, output)) in config.stages.iter().enumerate().zip(inputs.zip(outputs.iter())) {
+            let name = if stage.slots > 1 { format!("{} x{}", stage.kind, stage.slots) } else { stage.kind.clone() };
+            println!("[{}] {:<24} {} -> {}", index, name, input, output);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run(registry: &ComponentRegistry, config: PipelineConfig, worker_threads: Option<usize>) -> Result<ExitCode, ExitCode> {
+    // Every slot occupies a worker thread while it waits for input
+    let slots: usize = config.stages.iter().map(|stage| stage.slots).sum();
+    let cpus = std::thread::available_parallelism().map(|cpus| cpus.get()).unwrap_or(1);
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(worker_threads.unwrap_or(slots + cpus))
+        .enable_all()
+        .build()
+        .map_err(|e| {
+            eprintln!("error: failed to start runtime: {}", e);
+            ExitCode::FAILURE
+        })?;
+
+    let code = runtime.block_on(async {
+        let pipeline = registry.build(&config).map_err(|e| {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        })?;
+
+        info!("Running pipeline with {} stages", config.stages.len());
+        let running = pipeline.run();
+        tokio::pin!(running);
+        tokio::select! {
+            _ = &mut running => {
+                info!("Pipeline completed");
+                Ok(ExitCode::SUCCESS)
+            }
+            result = tokio::signal::ctrl_c() => {
+                if let Err(e) = result {
+                    error!("Failed to listen for Ctrl-C: {}", e);
+                }
+                // Stopping the sources lets the other stages finish what they
+                // received, so sinks flush and commit their files
+                info!("Interrupted, stopping sources and draining the pipeline");
+                pipeline.stop_sources();
+                tokio::select! {
+                    result = tokio::time::timeout(DRAIN_TIMEOUT, &mut running) => match result {
+                        Ok(()) => info!("Pipeline drained"),
+                        Err(_) => warn!("Pipeline did not drain within {:?}, stopping it", DRAIN_TIMEOUT),
+                    },
+                    _ = tokio::signal::ctrl_c() => warn!("Interrupted again, stopping without draining"),
+                }
+                Ok(ExitCode::from(INTERRUPTED))
+            }
+        }
+    });
+
+    runtime.shutdown_timeout(SHUTDOWN_TIMEOUT);
+    code
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    init_tracing(&cli);
+
+    let registry = ComponentRegistry::default();
+    let result = match &cli.command {
+        Command::Run { config } => load(config).and_then(|config| run(&registry, config, cli.worker_threads)),
+        Command::Tail { source } => run(&registry, source.config(), cli.worker_threads),
+        Command::Validate { config } => validate(&registry, config),
+        Command::Graph { config, dot } => graph(&registry, config, *dot),
+    };
+
+    result.unwrap_or_else(|code| code)
+}
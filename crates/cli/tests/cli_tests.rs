@@ -0,0 +1,168 @@
+use clap::Parser;
+use floq_cli::{Cli, Command};
+use serde_json::json;
+use std::path::PathBuf;
+use std::process::Output;
+
+fn floq(args: &[&str]) -> Output {
+    std::process::Command::new(env!("CARGO_BIN_EXE_floq"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn write_temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("floq_cli_{}_{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_validate_exit_codes() {
+    let input = write_temp_file("validate.txt", "one\n");
+    let valid = write_temp_file("valid.yaml", &format!(
+        "stages:\n  - type: file\n    path: {}\n  - type: lowercase\n  - type: printer\n",
+        input.display()
+    ));
+    let invalid = write_temp_file("invalid.yaml", &format!(
+        "stages:\n  - type: file\n    path: {}\n  - type: window\n    count: 2\n  - type: printer\n",
+        input.display()
+    ));
+
+    let output = floq(&["validate", valid.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("ok, 3 stages"));
+
+    // A batch cannot be printed without joining it first
+    let output = floq(&["validate", invalid.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("stages[2] (printer): expects text as input"));
+
+    let output = floq(&["validate", "/nonexistent/floq.yaml"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("failed to read config"));
+
+    for path in [input, valid, invalid] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_graph() {
+    let config = write_temp_file("graph.yaml", "stages:\n  - type: bluesky\n  - type: lowercase\n    slots: 2\n  - type: printer\n");
+
+    let output = floq(&["graph", config.to_str().unwrap()]);
+    assert!(output.status.success());
+    let lines: Vec<Vec<String>> = stdout(&output).lines()
+        .map(|line| line.split_whitespace().map(str::to_string).collect())
+        .collect();
+    assert_eq!(lines, vec![
+        vec!["[0]", "bluesky", "nothing", "->", "text"],
+        vec!["[1]", "lowercase", "x2", "text", "->", "text"],
+        vec!["[2]", "printer", "text", "->", "nothing"],
+    ]);
+
+    let output = floq(&["graph", "--dot", config.to_str().unwrap()]);
+    assert!(output.status.success());
+    let dot = stdout(&output);
+    assert!(dot.starts_with("digraph pipeline {"));
+    assert!(dot.contains("s1 [label=\"lowercase\\n2 slots\"];"));
+    assert!(dot.contains("s0 -> s1 [label=\"text\"];"));
+    assert!(dot.contains("s1 -> s2 [label=\"text\"];"));
+
+    std::fs::remove_file(config).unwrap();
+}
+
+// Parses `floq tail ...` and returns the source stage and its parameters
+fn tail_stage(args: &[&str]) -> (String, serde_json::Value) {
+    let cli = Cli::try_parse_from(["floq", "tail"].iter().chain(args)).unwrap();
+    let Command::Tail { source } = cli.command else {
+        panic!("expected the tail command");
+    };
+    let config = source.config();
+    assert_eq!(config.stages.len(), 2);
+    assert_eq!(config.stages[1].kind, "printer");
+    let stage = &config.stages[0];
+    (stage.kind.clone(), serde_json::Value::Object(stage.params.clone()))
+}
+
+#[test]
+fn test_tail_sources_map_to_stages() {
+    assert_eq!(tail_stage(&["bluesky"]), ("bluesky".to_string(), json!({})));
+    assert_eq!(
+        tail_stage(&["mastodon", "https://mastodon.social", "--access-token-env", "TOKEN"]),
+        ("mastodon".to_string(), json!({ "server_url": "https://mastodon.social", "access_token_env": "TOKEN" }))
+    );
+    assert_eq!(
+        tail_stage(&["websocket", "wss://example.com/feed"]),
+        ("websocket".to_string(), json!({ "url": "wss://example.com/feed" }))
+    );
+    assert_eq!(
+        tail_stage(&["mqtt", "ws://broker:8080", "--topic", "sensors/#"]),
+        ("mqtt_websocket".to_string(), json!({ "url": "ws://broker:8080", "topic": "sensors/#", "client_id": "floq" }))
+    );
+    assert_eq!(tail_stage(&["file", "posts.txt"]), ("file".to_string(), json!({ "path": "posts.txt" })));
+    assert_eq!(tail_stage(&["file", "posts.txt", "--follow"]), ("tail_file".to_string(), json!({ "path": "posts.txt" })));
+}
+
+#[test]
+fn test_tail_file_prints_lines() {
+    let input = write_temp_file("tail.txt", "one\ntwo\n");
+
+    let output = floq(&["tail", "file", input.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "one\ntwo\n");
+
+    std::fs::remove_file(input).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ctrl_c_drains_the_pipeline() {
+    let input = write_temp_file("interrupted.txt", "one\ntwo\n");
+    let output_dir = std::env::temp_dir().join(format!("floq_cli_{}_interrupted", std::process::id()));
+    let config = write_temp_file("interrupted.yaml", &format!(
+        "stages:\n  - type: tail_file\n    path: {}\n  - type: file_sink\n    path: {}\n",
+        input.display(), output_dir.display()
+    ));
+    let files = || -> Vec<String> {
+        std::fs::read_dir(&output_dir).map(|entries| {
+            entries.map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned()).collect()
+        }).unwrap_or_default()
+    };
+
+    let child = std::process::Command::new(env!("CARGO_BIN_EXE_floq"))
+        .args(["run", config.to_str().unwrap()])
+        .spawn()
+        .unwrap();
+    // The sink opens its file once the first line arrives
+    while files().is_empty() {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    let interrupted = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(interrupted.success());
+
+    // The tailing source never ends by itself, yet the sink commits its file
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(130));
+    let files = files();
+    assert_eq!(files.len(), 1);
+    assert!(!files[0].contains(".inprogress"), "left {} behind", files[0]);
+    assert_eq!(std::fs::read_to_string(output_dir.join(&files[0])).unwrap(), "one\ntwo\n");
+
+    std::fs::remove_dir_all(output_dir).unwrap();
+    for path in [input, config] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,166 @@
+//! Allocation and throughput benchmark for the built-in operators on a
+//! firehose-sized stream. Run with `cargo bench --bench operators`.
+//!
+//! Each case runs twice. The "before" run clones the payload at every stage, as
+//! the operators did before they moved payloads, and the "after" run uses the
+//! operators as they are: a `Map | Filter | Map` chain should then allocate
+//! roughly once per message (in the source), and fan-out with `Arc<str>`
+//! payloads should not allocate per output slot the way `String` does.
+
+use floq::functions::{Filter, Map};
+use floq::pipeline::{ComponentContext, Message, PipelineComponent, PipelineTask};
+use floq::pipeline::channel::{Receiver, Sender};
+use floq::slots::Broadcaster;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+const MESSAGES: usize = 100_000;
+const POST: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+    incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation.";
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Emits `MESSAGES` posts created by `make`
+struct PostSource<T> {
+    make: fn() -> T,
+}
+
+impl<T: Send + Sync + 'static> PipelineComponent for PostSource<T> {
+    type Input = ();
+    type Output = T;
+
+    fn new() -> Self {
+        panic!("PostSource requires a payload constructor")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        for _ in 0..MESSAGES {
+            if output.send(Message::new((self.make)())).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// Drops everything it receives
+struct Discard<T> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Send + Sync + 'static> PipelineComponent for Discard<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        Discard { _phantom: PhantomData }
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        while input.recv().is_ok() {}
+    }
+}
+
+/// Allocations and allocated bytes per message
+struct Usage {
+    allocations: f64,
+    bytes: f64,
+}
+
+async fn measure<F: std::future::Future<Output = ()>>(name: &str, pipeline: F) -> Usage {
+    let start = (ALLOCATIONS.load(Ordering::Relaxed), ALLOCATED_BYTES.load(Ordering::Relaxed));
+    let started = Instant::now();
+    pipeline.await;
+    let elapsed = started.elapsed();
+
+    let usage = Usage {
+        allocations: (ALLOCATIONS.load(Ordering::Relaxed) - start.0) as f64 / MESSAGES as f64,
+        bytes: (ALLOCATED_BYTES.load(Ordering::Relaxed) - start.1) as f64 / MESSAGES as f64,
+    };
+    println!(
+        "{:<36} {:>8.2} allocs/msg {:>10.1} bytes/msg {:>10.0} msg/s",
+        name,
+        usage.allocations,
+        usage.bytes,
+        MESSAGES as f64 / elapsed.as_secs_f64(),
+    );
+    usage
+}
+
+fn compare(before: Usage, after: Usage) {
+    println!(
+        "{:<36} {:>8.2} allocs/msg {:>10.1} bytes/msg fewer ({:.0}% of the bytes)\n",
+        "  reduction",
+        before.allocations - after.allocations,
+        before.bytes - after.bytes,
+        100.0 * after.bytes / before.bytes,
+    );
+}
+
+/// Clones the payload at every stage, as the operators did before
+async fn map_filter_map_cloning() {
+    let pipeline = PipelineTask::new(PostSource { make: || POST.to_string() })
+        | PipelineTask::new(Map::new(|post: String| post.clone()))
+        | PipelineTask::new(Map::new(|post: String| post.clone()))
+        | PipelineTask::new(Map::new(|post: String| post.clone()))
+        | PipelineTask::new(Discard::<String>::new());
+    pipeline.run().await;
+}
+
+async fn map_filter_map() {
+    let pipeline = PipelineTask::new(PostSource { make: || POST.to_string() })
+        | PipelineTask::new(Map::new(|post: String| post))
+        | PipelineTask::new(Filter::with_lambda(|post| !post.is_empty()))
+        | PipelineTask::new(Map::new(|post: String| post))
+        | PipelineTask::new(Discard::<String>::new());
+    pipeline.run().await;
+}
+
+/// Each output slot gets its own copy of the post
+async fn fan_out_string() {
+    let pipeline = PipelineTask::new(PostSource { make: || POST.to_string() })
+        | PipelineTask::new(Broadcaster::new())
+        | PipelineTask::with_slots(Discard::<String>::new(), 4);
+    pipeline.run().await;
+}
+
+/// Each post is allocated once in the source and shared by the output slots
+async fn fan_out_arc() {
+    let pipeline = PipelineTask::new(PostSource::<Arc<str>> { make: || Arc::from(POST) })
+        | PipelineTask::new(Broadcaster::new())
+        | PipelineTask::with_slots(Discard::<Arc<str>>::new(), 4);
+    pipeline.run().await;
+}
+
+#[tokio::main(flavor = "multi_thread", worker_threads = 4)]
+async fn main() {
+    println!("{} messages of {} bytes\n", MESSAGES, POST.len());
+
+    let before = measure("before: map | filter | map (clones)", map_filter_map_cloning()).await;
+    let after = measure("after: map | filter | map (moves)", map_filter_map()).await;
+    compare(before, after);
+
+    let before = measure("before: broadcast x4 (String)", fan_out_string()).await;
+    let after = measure("after: broadcast x4 (Arc<str>)", fan_out_arc()).await;
+    compare(before, after);
+}
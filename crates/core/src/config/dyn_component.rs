@@ -0,0 +1,183 @@
+use crate::pipeline::{PipelineComponent, StatelessComponent, ComponentContext};
+use crate::pipeline::channel::{self, Receiver, Sender};
+use crate::pipeline::fusion::fuse;
+use crate::functions::FlatMap;
+use super::value::{Payload, Value};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::{debug, error, warn};
+
+type BoxFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// Object safe counterpart of `PipelineComponent` for components over `Value`
+trait ErasedComponent: Send + Sync {
+    fn run<'a>(&'a self, input: Receiver<Value>, output: Sender<Value>, context: Arc<ComponentContext<Value, Value>>) -> BoxFuture<'a>;
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Value, Value>>>;
+}
+
+impl<C: PipelineComponent<Input = Value, Output = Value>> ErasedComponent for C {
+    fn run<'a>(&'a self, input: Receiver<Value>, output: Sender<Value>, context: Arc<ComponentContext<Value, Value>>) -> BoxFuture<'a> {
+        Box::pin(PipelineComponent::run(self, input, output, context))
+    }
+
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Value, Value>>> {
+        PipelineComponent::into_stateless(self)
+    }
+}
+
+/// The name of the stage a component runs in and the inputs it dropped
+struct Stage {
+    name: RwLock<String>,
+    dropped: AtomicUsize,
+}
+
+impl Stage {
+    fn new(name: &str) -> Arc<Self> {
+        Arc::new(Stage {
+            name: RwLock::new(name.to_string()),
+            dropped: AtomicUsize::new(0),
+        })
+    }
+
+    fn name(&self) -> String {
+        self.name.read().unwrap().clone()
+    }
+}
+
+/// A component whose concrete type is only known at runtime, so that stages
+/// built from a config file can be connected with `|` like any other. Clones
+/// share the component.
+#[derive(Clone)]
+pub struct DynComponent {
+    inner: Arc<dyn ErasedComponent>,
+    stage: Arc<Stage>,
+}
+
+impl DynComponent {
+    pub fn new<C: PipelineComponent<Input = Value, Output = Value>>(component: C) -> Self {
+        DynComponent {
+            inner: Arc::new(component),
+            stage: Stage::new(std::any::type_name::<C>()),
+        }
+    }
+
+    /// Wraps a component over regular payload types such as `String`, converting
+    /// its input from and its output to `Value`. Inputs that do not convert, e.g.
+    /// text that is not JSON for a component taking JSON, are logged, counted
+    /// in `dropped` and dropped.
+    pub fn adapt<C>(component: C) -> Self
+    where
+        C: PipelineComponent,
+        C::Input: Payload,
+        C::Output: Payload,
+    {
+        let stage = Stage::new(std::any::type_name::<C>());
+        DynComponent {
+            inner: Arc::new(Adapted { component, stage: stage.clone() }),
+            stage,
+        }
+    }
+
+    /// Names the component in logs, by default its type
+    pub fn with_name(self, name: impl Into<String>) -> Self {
+        *self.stage.name.write().unwrap() = name.into();
+        self
+    }
+
+    pub fn name(&self) -> String {
+        self.stage.name()
+    }
+
+    /// Number of inputs dropped because they could not be converted to the
+    /// input of the adapted component
+    pub fn dropped(&self) -> usize {
+        self.stage.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl PipelineComponent for DynComponent {
+    type Input = Value;
+    type Output = Value;
+
+    fn new() -> Self {
+        panic!("DynComponent requires a component. Use DynComponent::new() instead.")
+    }
+
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Self::Input, Self::Output>>> {
+        Arc::clone(&self.inner).into_stateless()
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        self.inner.run(input, output, context).await;
+        let dropped = self.dropped();
+        if dropped > 0 {
+            warn!("{} dropped {} inputs it could not convert", self.name(), dropped);
+        }
+    }
+}
+
+struct Adapted<C> {
+    component: C,
+    stage: Arc<Stage>,
+}
+
+impl<C> PipelineComponent for Adapted<C>
+where
+    C: PipelineComponent,
+    C::Input: Payload,
+    C::Output: Payload,
+{
+    type Input = Value;
+    type Output = Value;
+
+    fn new() -> Self {
+        Adapted {
+            component: C::new(),
+            stage: Stage::new(std::any::type_name::<C>()),
+        }
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("Adapted component starting");
+
+        // Inputs are converted inline as the component pulls them
+        let stage = self.stage.clone();
+        let convert = FlatMap::new(move |value: Value| {
+            let value_type = value.value_type();
+            let converted = C::Input::from_value(value);
+            if converted.is_none() {
+                stage.dropped.fetch_add(1, Ordering::Relaxed);
+                warn!("{} dropped {} input it could not convert", stage.name(), value_type);
+            }
+            converted
+        });
+        let input = fuse(Arc::new(convert), input);
+
+        // Outputs are converted by a forwarder, as senders cannot be wrapped
+        let (typed_sender, typed_receiver) = channel::channel::<C::Output>();
+        let forwarder = tokio::task::spawn_blocking(move || {
+            while let Ok(msg) = typed_receiver.recv() {
+                let (payload, msg) = msg.into_parts();
+                if let Some(value) = payload.into_value() {
+                    if let Err(e) = output.send(msg.with_new_payload(value)) {
+                        error!("Failed to send converted item: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let context = Arc::new(
+            ComponentContext::new(vec![typed_sender.clone()], vec![input.clone()])
+                .with_clock(context.clock.clone())
+        );
+        self.component.run(input, typed_sender, context).await;
+
+        if let Err(e) = forwarder.await {
+            error!("Forwarder failed: {:?}", e);
+        }
+        debug!("Adapted component completed");
+    }
+}
@@ -0,0 +1,118 @@
+pub mod value;
+pub mod dyn_component;
+pub mod registry;
+
+pub use value::{Value, ValueType, Payload};
+pub use dyn_component::DynComponent;
+pub use registry::{ComponentRegistry, Params, parse_params};
+
+use serde::Deserialize;
+use std::fmt;
+use std::path::Path;
+
+/// A pipeline described in a YAML or TOML file, e.g.
+///
+/// ```yaml
+/// stages:
+///   - type: file
+///     path: posts.txt
+///   - type: filter
+///     pattern: "(?i)rust"
+///   - type: printer
+/// ```
+///
+/// Every stage has a `type` naming a component in the `ComponentRegistry` and an
+/// optional number of `slots`; all other keys are parameters of the component.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PipelineConfig {
+    pub stages: Vec<StageConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StageConfig {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default = "default_slots")]
+    pub slots: usize,
+    #[serde(flatten)]
+    pub params: Params,
+}
+
+fn default_slots() -> usize {
+    1
+}
+
+impl PipelineConfig {
+    pub fn from_yaml(text: &str) -> Result<Self, ConfigError> {
+        serde_yaml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    /// Loads a config file, picking the format from its `.yaml`, `.yml` or `.toml` extension
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(format!("{}: {}", path.display(), e)))?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml") | Some("yml") => PipelineConfig::from_yaml(&text),
+            Some("toml") => PipelineConfig::from_toml(&text),
+            _ => Err(ConfigError::Parse(format!("{}: unknown config format, expected .yaml or .toml", path.display()))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// The config file could not be read
+    Io(String),
+    /// The config file is not valid YAML or TOML, or does not describe a pipeline
+    Parse(String),
+    /// The pipeline has no stages
+    Empty,
+    /// No component of this type is registered
+    UnknownComponent { stage: usize, kind: String },
+    /// The parameters of a stage were rejected by its component
+    InvalidParams { stage: usize, kind: String, message: String },
+    /// A stage cannot consume what the previous stage produces
+    TypeMismatch { stage: usize, kind: String, expected: ValueType, found: ValueType },
+    /// A stage has zero slots
+    NoSlots { stage: usize, kind: String },
+    /// A stage follows a sink, which produces nothing
+    AfterSink { stage: usize, kind: String },
+    /// A source is used anywhere but as the first stage
+    SourceNotFirst { stage: usize, kind: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(message) => write!(f, "failed to read config: {}", message),
+            ConfigError::Parse(message) => write!(f, "failed to parse config: {}", message),
+            ConfigError::Empty => write!(f, "pipeline has no stages"),
+            ConfigError::UnknownComponent { stage, kind } => {
+                write!(f, "stages[{}] ({}): unknown component type", stage, kind)
+            }
+            ConfigError::InvalidParams { stage, kind, message } => {
+                write!(f, "stages[{}] ({}): {}", stage, kind, message)
+            }
+            ConfigError::TypeMismatch { stage, kind, expected, found } => {
+                write!(f, "stages[{}] ({}): expects {} as input, but the previous stage produces {}", stage, kind, expected, found)
+            }
+            ConfigError::NoSlots { stage, kind } => {
+                write!(f, "stages[{}] ({}): requires at least one slot", stage, kind)
+            }
+            ConfigError::AfterSink { stage, kind } => {
+                write!(f, "stages[{}] ({}): follows a sink, which produces nothing", stage, kind)
+            }
+            ConfigError::SourceNotFirst { stage, kind } => {
+                write!(f, "stages[{}] ({}): is a source and can only be the first stage", stage, kind)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -0,0 +1,922 @@
+use crate::pipeline::{PipelineComponent, PipelineTask, Message};
+use crate::functions::{Filter, JsonPath, Map, ParseJson, SlidingWindow, Window};
+use crate::slots::{Broadcaster, LeastLoadedSplitter, Merger, RoundRobinSplitter};
+use crate::sources::{BlueskyFirehoseSource, Csv, DirectorySource, FileSource, HttpSource, JsonLines, SseSource, MastodonFirehoseSource, TailFileSource, WebSocketMqttSource, WebSocketSource};
+use crate::mqtt::{self, MqttSink, MqttSource};
+use crate::socket::{Framing, TcpSink, TcpSource, UdpSink, UdpSource};
+#[cfg(unix)]
+use crate::socket::{UnixSocketSink, UnixSocketSource};
+use crate::transformers::{FileSink, HttpSink, PrinterSink, SinkFormat, SlowClientPolicy, WebSocketServerSink};
+use crate::transformers::file_sink::validate_partitioning;
+use super::{ConfigError, DynComponent, PipelineConfig, Value, ValueType};
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::debug;
+
+/// Parameters of a stage, i.e. all keys of its config besides `type` and `slots`
+pub type Params = serde_json::Map<String, serde_json::Value>;
+
+type Factory = Arc<dyn Fn(&Params) -> Result<DynComponent, String> + Send + Sync>;
+
+struct Registration {
+    input: ValueType,
+    output: ValueType,
+    factory: Factory,
+}
+
+/// Deserializes the parameters of a stage into the struct a factory expects
+pub fn parse_params<P: DeserializeOwned>(params: &Params) -> Result<P, String> {
+    serde_json::from_value(serde_json::Value::Object(params.clone())).map_err(|e| e.to_string())
+}
+
+/// Maps the component types used in config files to factories building them.
+/// `ComponentRegistry::default()` contains the built-in components; applications
+/// can register their own next to them.
+pub struct ComponentRegistry {
+    components: HashMap<String, Registration>,
+}
+
+impl Default for ComponentRegistry {
+    fn default() -> Self {
+        ComponentRegistry::with_builtins()
+    }
+}
+
+impl ComponentRegistry {
+    /// Creates a registry without any components
+    pub fn new() -> Self {
+        ComponentRegistry {
+            components: HashMap::new(),
+        }
+    }
+
+    /// Registers a component type. `input` and `output` are what the component
+    /// consumes and produces, checked against the neighbouring stages.
+    pub fn register<F>(&mut self, kind: impl Into<String>, input: ValueType, output: ValueType, factory: F)
+    where
+        F: Fn(&Params) -> Result<DynComponent, String> + Send + Sync + 'static
+    {
+        self.components.insert(kind.into(), Registration {
+            input,
+            output,
+            factory: Arc::new(factory),
+        });
+    }
+
+    /// Registered component types in alphabetical order
+    pub fn kinds(&self) -> Vec<&str> {
+        let mut kinds: Vec<&str> = self.components.keys().map(|kind| kind.as_str()).collect();
+        kinds.sort();
+        kinds
+    }
+
+    /// Checks that all stages are registered, that each stage consumes what the
+    /// previous one produces and that the components accept their parameters.
+    /// Returns the type each stage produces.
+    pub fn validate(&self, config: &PipelineConfig) -> Result<Vec<ValueType>, ConfigError> {
+        let outputs = self.check_types(config)?;
+        self.create_components(config)?;
+        Ok(outputs)
+    }
+
+    /// Validates the config and connects its stages into a pipeline. All components
+    /// are created before any stage is connected, so an invalid parameter in a later
+    /// stage does not leave earlier stages running.
+    pub fn build(&self, config: &PipelineConfig) -> Result<PipelineTask<DynComponent>, ConfigError> {
+        self.check_types(config)?;
+        let components = self.create_components(config)?;
+
+        let mut tasks = config.stages.iter().zip(components).map(|(stage_config, component)| {
+            if stage_config.slots == 1 {
+                PipelineTask::new(component)
+            } else {
+                PipelineTask::with_slots(component, stage_config.slots)
+            }
+        });
+
+        let mut pipeline = tasks.next().unwrap();
+        for task in tasks {
+            pipeline = pipeline | task;
+        }
+        Ok(pipeline)
+    }
+
+    fn check_types(&self, config: &PipelineConfig) -> Result<Vec<ValueType>, ConfigError> {
+        if config.stages.is_empty() {
+            return Err(ConfigError::Empty);
+        }
+
+        let mut current = ValueType::None;
+        let mut outputs = Vec::with_capacity(config.stages.len());
+
+        for (stage, stage_config) in config.stages.iter().enumerate() {
+            let registration = self.registration(stage, &stage_config.kind)?;
+            if stage_config.slots == 0 {
+                return Err(ConfigError::NoSlots { stage, kind: stage_config.kind.clone() });
+            }
+
+            if stage > 0 && current == ValueType::None {
+                return Err(ConfigError::AfterSink { stage, kind: stage_config.kind.clone() });
+            }
+            if stage > 0 && registration.input == ValueType::None {
+                return Err(ConfigError::SourceNotFirst { stage, kind: stage_config.kind.clone() });
+            }
+
+            let compatible = match registration.input {
+                ValueType::Any => current != ValueType::None,
+                expected => expected == current,
+            };
+            if !compatible {
+                return Err(ConfigError::TypeMismatch {
+                    stage,
+                    kind: stage_config.kind.clone(),
+                    expected: registration.input,
+                    found: current,
+                });
+            }
+
+            if registration.output != ValueType::Any {
+                current = registration.output;
+            }
+            outputs.push(current);
+        }
+
+        Ok(outputs)
+    }
+
+    fn create_components(&self, config: &PipelineConfig) -> Result<Vec<DynComponent>, ConfigError> {
+        config.stages.iter().enumerate().map(|(stage, stage_config)| {
+            let registration = self.registration(stage, &stage_config.kind)?;
+            debug!("Creating stage {} ({}) with {} slots", stage, stage_config.kind, stage_config.slots);
+            (registration.factory)(&stage_config.params)
+                .map(|component| component.with_name(format!("stages[{}] ({})", stage, stage_config.kind)))
+                .map_err(|message| ConfigError::InvalidParams {
+                    stage,
+                    kind: stage_config.kind.clone(),
+                    message,
+                })
+        }).collect()
+    }
+
+    fn registration(&self, stage: usize, kind: &str) -> Result<&Registration, ConfigError> {
+        self.components.get(kind).ok_or_else(|| ConfigError::UnknownComponent {
+            stage,
+            kind: kind.to_string(),
+        })
+    }
+
+    pub fn with_builtins() -> Self {
+        let mut registry = ComponentRegistry::new();
+        registry.register_sources();
+        registry.register_functions();
+        registry.register_slots();
+        registry.register_sinks();
+        registry
+    }
+
+    fn register_sources(&mut self) {
+        self.register("file", ValueType::None, ValueType::Text, |params| {
+            let params: FileParams = parse_params(params)?;
+            let source = FileSource::new(params.path);
+            Ok(match params.format {
+                FileFormatParam::Lines => DynComponent::adapt(source),
+                FileFormatParam::Jsonl => DynComponent::adapt(source.with_format(JsonLines::<serde_json::Value>::new())),
+                FileFormatParam::Csv => DynComponent::adapt(source.with_format(Csv::<serde_json::Map<String, serde_json::Value>>::new())),
+            })
+        });
+
+        self.register("tail_file", ValueType::None, ValueType::Text, |params| {
+            let params: TailParams = parse_params(params)?;
+            let mut source = TailFileSource::new(params.path)
+                .with_poll_interval(Duration::from_millis(params.poll_interval_ms));
+            if let Some(offsets) = params.offsets {
+                source = source.with_offsets(offsets);
+            }
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                source = source.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            Ok(DynComponent::adapt(source))
+        });
+
+        self.register("directory", ValueType::None, ValueType::Text, |params| {
+            let params: DirectoryParams = parse_params(params)?;
+            glob::Pattern::new(&params.pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+            let mut source = DirectorySource::new(params.path, &params.pattern)
+                .with_poll_interval(Duration::from_millis(params.poll_interval_ms));
+            if let Some(offsets) = params.offsets {
+                source = source.with_offsets(offsets);
+            }
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                source = source.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            Ok(DynComponent::adapt(source))
+        });
+
+        self.register("http", ValueType::None, ValueType::Text, |params| {
+            let params: HttpParams = parse_params(params)?;
+            let mut source = HttpSource::<serde_json::Value>::bind(params.address).with_path(params.path);
+            match (params.token, params.token_env) {
+                (Some(token), _) => source = source.with_token(token),
+                (None, Some(var)) => source = source.with_token(std::env::var(&var).map_err(|_| format!("environment variable {} is not set", var))?),
+                (None, None) => {}
+            }
+            if let Some(max_body_size) = params.max_body_size {
+                source = source.with_max_body_size(max_body_size);
+            }
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                source = source.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            Ok(DynComponent::adapt(source))
+        });
+
+        self.register("sse", ValueType::None, ValueType::Text, |params| {
+            let params: SseParams = parse_params(params)?;
+            let mut source = SseSource::new(params.url);
+            for (name, value) in &params.headers {
+                source = source.with_header(name, value);
+            }
+            if let Some(id) = params.last_event_id {
+                source = source.with_last_event_id(id);
+            }
+            if let Some(retry) = params.retry_ms {
+                source = source.with_retry(Duration::from_millis(retry));
+            }
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                source = source.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            Ok(DynComponent::adapt(source))
+        });
+
+        self.register("mastodon", ValueType::None, ValueType::Text, |params| {
+            let params: MastodonParams = parse_params(params)?;
+            let access_token = match (params.access_token, params.access_token_env) {
+                (Some(token), _) => Some(token),
+                (None, Some(var)) => Some(std::env::var(&var).map_err(|_| format!("environment variable {} is not set", var))?),
+                (None, None) => None,
+            };
+            Ok(DynComponent::adapt(match access_token {
+                Some(token) => MastodonFirehoseSource::with_token(params.server_url, token),
+                None => MastodonFirehoseSource::new(params.server_url),
+            }))
+        });
+
+        self.register("bluesky", ValueType::None, ValueType::Text, |params| {
+            parse_params::<NoParams>(params)?;
+            Ok(DynComponent::adapt(BlueskyFirehoseSource::new()))
+        });
+
+        self.register("websocket", ValueType::None, ValueType::Text, |params| {
+            let params: UrlParams = parse_params(params)?;
+            Ok(DynComponent::adapt(WebSocketSource::new(params.url)))
+        });
+
+        self.register("mqtt_websocket", ValueType::None, ValueType::Text, |params| {
+            let params: MqttParams = parse_params(params)?;
+            Ok(DynComponent::adapt(WebSocketMqttSource::new(params.url, params.topic, params.client_id)))
+        });
+
+        self.register("mqtt", ValueType::None, ValueType::Text, |params| {
+            let params: MqttSourceParams = parse_params(params)?;
+            if params.topics.is_empty() {
+                return Err("at least one topic is required".to_string());
+            }
+            let qos = mqtt::qos(params.qos)?;
+            let mut source = MqttSource::new(&params.url, &params.client_id)
+                .with_clean_session(params.clean_session);
+            for topic in &params.topics {
+                source = source.with_topic(topic, qos);
+            }
+            if let Some((username, password)) = mqtt_credentials(params.username, params.password, params.password_env)? {
+                source = source.with_credentials(&username, &password);
+            }
+            if let Some(ca_file) = params.ca_file {
+                source = source.with_ca(read_ca(&ca_file)?);
+            }
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                source = source.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            Ok(DynComponent::adapt(source))
+        });
+
+        self.register("tcp", ValueType::None, ValueType::Text, |params| {
+            let params: StreamSourceParams = parse_params(params)?;
+            let mut source = TcpSource::bind(params.address).with_framing(params.framing.into());
+            if let Some(max_frame_size) = params.max_frame_size {
+                source = source.with_max_frame_size(max_frame_size);
+            }
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                source = source.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            Ok(DynComponent::adapt(source))
+        });
+
+        #[cfg(unix)]
+        self.register("unix_socket", ValueType::None, ValueType::Text, |params| {
+            let params: UnixSocketSourceParams = parse_params(params)?;
+            let mut source = UnixSocketSource::bind(params.path).with_framing(params.framing.into());
+            if let Some(max_frame_size) = params.max_frame_size {
+                source = source.with_max_frame_size(max_frame_size);
+            }
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                source = source.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            Ok(DynComponent::adapt(source))
+        });
+
+        self.register("udp", ValueType::None, ValueType::Text, |params| {
+            let params: UdpSourceParams = parse_params(params)?;
+            let mut source = UdpSource::bind(params.address);
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                source = source.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            Ok(DynComponent::adapt(source))
+        });
+    }
+
+    fn register_functions(&mut self) {
+        self.register("lowercase", ValueType::Text, ValueType::Text, |params| {
+            parse_params::<NoParams>(params)?;
+            Ok(text_map(|text| text.to_lowercase()))
+        });
+
+        self.register("uppercase", ValueType::Text, ValueType::Text, |params| {
+            parse_params::<NoParams>(params)?;
+            Ok(text_map(|text| text.to_uppercase()))
+        });
+
+        self.register("trim", ValueType::Text, ValueType::Text, |params| {
+            parse_params::<NoParams>(params)?;
+            Ok(text_map(|text| text.trim().to_string()))
+        });
+
+        self.register("strip_html", ValueType::Text, ValueType::Text, |params| {
+            parse_params::<NoParams>(params)?;
+            let tags = Regex::new(r"<[^>]*>").unwrap();
+            Ok(text_map(move |text| {
+                tags.replace_all(&text, " ").split_whitespace().collect::<Vec<_>>().join(" ")
+            }))
+        });
+
+        self.register("replace", ValueType::Text, ValueType::Text, |params| {
+            let params: ReplaceParams = parse_params(params)?;
+            let pattern = Regex::new(&params.pattern).map_err(|e| e.to_string())?;
+            Ok(text_map(move |text| pattern.replace_all(&text, params.replacement.as_str()).into_owned()))
+        });
+
+        self.register("filter", ValueType::Text, ValueType::Text, |params| {
+            let params: FilterParams = parse_params(params)?;
+            let pattern = Regex::new(&params.pattern).map_err(|e| e.to_string())?;
+            let filter = Filter::with_predicate(move |msg: &Message<Value>| {
+                msg.payload.as_text().is_some_and(|text| pattern.is_match(text))
+            });
+            Ok(DynComponent::new(if params.invert { !filter } else { filter }))
+        });
+
+        self.register("json", ValueType::Text, ValueType::Text, |params| {
+            let params: JsonParams = parse_params(params)?;
+            let mut parse = ParseJson::<serde_json::Value>::new();
+            if let Some(extract) = &params.extract {
+                JsonPath::parse(extract)?;
+                parse = parse.with_extract(extract);
+            }
+            for (name, path) in &params.fields {
+                JsonPath::parse(path)?;
+                parse = parse.with_field(name, path);
+            }
+            Ok(DynComponent::adapt(parse))
+        });
+
+        self.register("window", ValueType::Text, ValueType::Batch, |params| {
+            let params: WindowParams = parse_params(params)?;
+            match (params.count, params.duration_ms, params.slide_ms) {
+                (Some(count), None, None) if count > 0 => Ok(DynComponent::adapt(Window::<String>::with_count(count))),
+                (None, Some(duration), None) => Ok(DynComponent::adapt(Window::<String>::with_duration(Duration::from_millis(duration)))),
+                (None, Some(duration), Some(slide)) => {
+                    Ok(DynComponent::adapt(SlidingWindow::<String>::new(Duration::from_millis(duration), Duration::from_millis(slide))))
+                }
+                _ => Err("expected a positive count, a duration_ms, or a duration_ms with slide_ms".to_string()),
+            }
+        });
+
+        self.register("join", ValueType::Batch, ValueType::Text, |params| {
+            let params: JoinParams = parse_params(params)?;
+            Ok(DynComponent::new(Map::new(move |value: Value| match value {
+                Value::Batch(texts) => Value::Text(texts.join(&params.separator)),
+                text => text,
+            })))
+        });
+    }
+
+    fn register_slots(&mut self) {
+        self.register("round_robin", ValueType::Any, ValueType::Any, |params| {
+            parse_params::<NoParams>(params)?;
+            Ok(DynComponent::new(RoundRobinSplitter::<Value>::new()))
+        });
+
+        self.register("least_loaded", ValueType::Any, ValueType::Any, |params| {
+            parse_params::<NoParams>(params)?;
+            Ok(DynComponent::new(LeastLoadedSplitter::<Value>::new()))
+        });
+
+        self.register("broadcast", ValueType::Any, ValueType::Any, |params| {
+            parse_params::<NoParams>(params)?;
+            Ok(DynComponent::new(Broadcaster::<Value>::new()))
+        });
+
+        self.register("merger", ValueType::Any, ValueType::Any, |params| {
+            let params: MergerParams = parse_params(params)?;
+            let mut merger = Merger::<Value>::new().with_ordered(params.ordered);
+            if let Some(idle_timeout) = params.idle_timeout_ms {
+                merger = merger.with_idle_timeout(Duration::from_millis(idle_timeout));
+            }
+            if let Some(max_pending) = params.max_pending {
+                merger = merger.with_max_pending(max_pending);
+            }
+            Ok(DynComponent::new(merger))
+        });
+    }
+
+    fn register_sinks(&mut self) {
+        self.register("printer", ValueType::Text, ValueType::None, |params| {
+            let params: PrinterParams = parse_params(params)?;
+            Ok(DynComponent::adapt(PrinterSink::new(params.prefix)))
+        });
+
+        self.register("file_sink", ValueType::Text, ValueType::None, |params| {
+            let params: FileSinkParams = parse_params(params)?;
+            let mut sink = FileSink::<String>::new(params.path, params.format.into());
+            if let Some(max_bytes) = params.max_bytes {
+                sink = sink.with_max_bytes(max_bytes);
+            }
+            if let Some(max_duration) = params.max_duration_ms {
+                sink = sink.with_max_duration(Duration::from_millis(max_duration));
+            }
+            if let Some(partitioning) = params.partitioning {
+                validate_partitioning(&partitioning)?;
+                sink = sink.with_partitioning(partitioning);
+            }
+            Ok(DynComponent::adapt(sink))
+        });
+
+        self.register("mqtt_sink", ValueType::Text, ValueType::None, |params| {
+            let params: MqttSinkParams = parse_params(params)?;
+            let mut sink = MqttSink::<String>::new(&params.url, &params.client_id, &params.topic)
+                .with_qos(mqtt::qos(params.qos)?)
+                .with_retain(params.retain)
+                .with_clean_session(params.clean_session);
+            if let Some((username, password)) = mqtt_credentials(params.username, params.password, params.password_env)? {
+                sink = sink.with_credentials(&username, &password);
+            }
+            if let Some(ca_file) = params.ca_file {
+                sink = sink.with_ca(read_ca(&ca_file)?);
+            }
+            Ok(DynComponent::adapt(sink))
+        });
+
+        self.register("websocket_server", ValueType::Text, ValueType::None, |params| {
+            let params: WebSocketServerParams = parse_params(params)?;
+            let mut sink = WebSocketServerSink::<String>::bind(params.address)
+                .with_replay(params.replay)
+                .with_slow_clients(params.slow_clients.into());
+            if let Some(client_buffer) = params.client_buffer {
+                sink = sink.with_client_buffer(client_buffer);
+            }
+            Ok(DynComponent::adapt(sink))
+        });
+
+        self.register("http_sink", ValueType::Text, ValueType::None, |params| {
+            let params: HttpSinkParams = parse_params(params)?;
+            let mut sink = HttpSink::<serde_json::Value>::new(params.url);
+            for (name, value) in &params.headers {
+                sink = sink.with_header(name, value);
+            }
+            if let Some(batch_size) = params.batch_size {
+                if batch_size == 0 {
+                    return Err("batch_size must be at least 1".to_string());
+                }
+                sink = sink.with_batch_size(batch_size);
+            }
+            if let Some(linger) = params.linger_ms {
+                sink = sink.with_linger(Duration::from_millis(linger));
+            }
+            if let Some(concurrency) = params.concurrency {
+                if concurrency == 0 {
+                    return Err("concurrency must be at least 1".to_string());
+                }
+                sink = sink.with_concurrency(concurrency);
+            }
+            if let Some(max_retries) = params.max_retries {
+                sink = sink.with_max_retries(max_retries);
+            }
+            if params.backoff_ms.is_some() || params.max_backoff_ms.is_some() {
+                let initial = Duration::from_millis(params.backoff_ms.unwrap_or(200));
+                let max = Duration::from_millis(params.max_backoff_ms.unwrap_or(30_000));
+                sink = sink.with_backoff(initial, max);
+            }
+            if let Some(timeout) = params.timeout_ms {
+                sink = sink.with_timeout(Duration::from_millis(timeout));
+            }
+            Ok(DynComponent::adapt(sink))
+        });
+
+        self.register("tcp_sink", ValueType::Text, ValueType::None, |params| {
+            let params: StreamSinkParams = parse_params(params)?;
+            let mut sink = TcpSink::<String>::connect(params.address).with_framing(params.framing.into());
+            if let Some(max_backoff) = params.max_backoff_ms {
+                sink = sink.with_max_backoff(Duration::from_millis(max_backoff));
+            }
+            Ok(DynComponent::adapt(sink))
+        });
+
+        #[cfg(unix)]
+        self.register("unix_socket_sink", ValueType::Text, ValueType::None, |params| {
+            let params: UnixSocketSinkParams = parse_params(params)?;
+            let mut sink = UnixSocketSink::<String>::connect(params.path).with_framing(params.framing.into());
+            if let Some(max_backoff) = params.max_backoff_ms {
+                sink = sink.with_max_backoff(Duration::from_millis(max_backoff));
+            }
+            Ok(DynComponent::adapt(sink))
+        });
+
+        self.register("udp_sink", ValueType::Text, ValueType::None, |params| {
+            let params: UdpSinkParams = parse_params(params)?;
+            let mut sink = UdpSink::<String>::connect(params.address);
+            if let Some(max_backoff) = params.max_backoff_ms {
+                sink = sink.with_max_backoff(Duration::from_millis(max_backoff));
+            }
+            Ok(DynComponent::adapt(sink))
+        });
+    }
+}
+
+fn read_ca(path: &PathBuf) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))
+}
+
+fn text_map<F>(transform: F) -> DynComponent
+where
+    F: Fn(String) -> String + Send + Sync + 'static
+{
+    DynComponent::new(Map::new(move |value: Value| match value {
+        Value::Text(text) => Value::Text(transform(text)),
+        batch => batch,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoParams {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileParams {
+    path: PathBuf,
+    #[serde(default)]
+    format: FileFormatParam,
+}
+
+/// Formats of the `file` source; parsed records are emitted as JSON text
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum FileFormatParam {
+    #[default]
+    Lines,
+    Jsonl,
+    Csv,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TailParams {
+    path: PathBuf,
+    offsets: Option<PathBuf>,
+    #[serde(default = "default_poll_interval")]
+    poll_interval_ms: u64,
+    idle_timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DirectoryParams {
+    path: PathBuf,
+    pattern: String,
+    offsets: Option<PathBuf>,
+    #[serde(default = "default_poll_interval")]
+    poll_interval_ms: u64,
+    idle_timeout_ms: Option<u64>,
+}
+
+fn default_poll_interval() -> u64 {
+    250
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MastodonParams {
+    server_url: String,
+    access_token: Option<String>,
+    access_token_env: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UrlParams {
+    url: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MqttParams {
+    url: String,
+    topic: String,
+    client_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HttpParams {
+    address: String,
+    #[serde(default = "default_http_path")]
+    path: String,
+    token: Option<String>,
+    token_env: Option<String>,
+    max_body_size: Option<usize>,
+    idle_timeout_ms: Option<u64>,
+}
+
+fn default_http_path() -> String {
+    "/".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SseParams {
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    last_event_id: Option<String>,
+    retry_ms: Option<u64>,
+    idle_timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StreamSourceParams {
+    address: String,
+    #[serde(default)]
+    framing: FramingParam,
+    max_frame_size: Option<usize>,
+    idle_timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UnixSocketSourceParams {
+    path: PathBuf,
+    #[serde(default)]
+    framing: FramingParam,
+    max_frame_size: Option<usize>,
+    idle_timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UdpSourceParams {
+    address: String,
+    idle_timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+enum FramingParam {
+    #[default]
+    Lines,
+    LengthPrefixed,
+}
+
+impl From<FramingParam> for Framing {
+    fn from(framing: FramingParam) -> Self {
+        match framing {
+            FramingParam::Lines => Framing::Lines,
+            FramingParam::LengthPrefixed => Framing::LengthPrefixed,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MqttSourceParams {
+    url: String,
+    client_id: String,
+    topics: Vec<String>,
+    #[serde(default = "default_qos")]
+    qos: u8,
+    username: Option<String>,
+    password: Option<String>,
+    password_env: Option<String>,
+    #[serde(default = "default_clean_session")]
+    clean_session: bool,
+    ca_file: Option<PathBuf>,
+    idle_timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MqttSinkParams {
+    url: String,
+    client_id: String,
+    topic: String,
+    #[serde(default = "default_qos")]
+    qos: u8,
+    #[serde(default)]
+    retain: bool,
+    username: Option<String>,
+    password: Option<String>,
+    password_env: Option<String>,
+    #[serde(default = "default_clean_session")]
+    clean_session: bool,
+    ca_file: Option<PathBuf>,
+}
+
+/// The MQTT username and password, read from `password_env` if not given
+fn mqtt_credentials(username: Option<String>, password: Option<String>, password_env: Option<String>) -> Result<Option<(String, String)>, String> {
+    let Some(username) = username else {
+        return Ok(None);
+    };
+    let password = match (password, password_env) {
+        (Some(password), _) => password,
+        (None, Some(var)) => std::env::var(&var).map_err(|_| format!("environment variable {} is not set", var))?,
+        (None, None) => String::new(),
+    };
+    Ok(Some((username, password)))
+}
+
+fn default_qos() -> u8 {
+    1
+}
+
+fn default_clean_session() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ReplaceParams {
+    pattern: String,
+    #[serde(default)]
+    replacement: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FilterParams {
+    pattern: String,
+    #[serde(default)]
+    invert: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonParams {
+    extract: Option<String>,
+    #[serde(default)]
+    fields: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WindowParams {
+    count: Option<usize>,
+    duration_ms: Option<u64>,
+    slide_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JoinParams {
+    #[serde(default = "default_separator")]
+    separator: String,
+}
+
+fn default_separator() -> String {
+    "\n".to_string()
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MergerParams {
+    #[serde(default)]
+    ordered: bool,
+    idle_timeout_ms: Option<u64>,
+    max_pending: Option<usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileSinkParams {
+    path: PathBuf,
+    #[serde(default)]
+    format: SinkFormatParam,
+    max_bytes: Option<u64>,
+    max_duration_ms: Option<u64>,
+    partitioning: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum SinkFormatParam {
+    #[default]
+    Text,
+    Jsonl,
+    Csv,
+}
+
+impl From<SinkFormatParam> for SinkFormat {
+    fn from(format: SinkFormatParam) -> Self {
+        match format {
+            SinkFormatParam::Text => SinkFormat::Text,
+            SinkFormatParam::Jsonl => SinkFormat::JsonLines,
+            SinkFormatParam::Csv => SinkFormat::Csv,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WebSocketServerParams {
+    address: String,
+    #[serde(default)]
+    replay: usize,
+    client_buffer: Option<usize>,
+    #[serde(default)]
+    slow_clients: SlowClientsParam,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum SlowClientsParam {
+    #[default]
+    Drop,
+    Disconnect,
+}
+
+impl From<SlowClientsParam> for SlowClientPolicy {
+    fn from(policy: SlowClientsParam) -> Self {
+        match policy {
+            SlowClientsParam::Drop => SlowClientPolicy::DropMessages,
+            SlowClientsParam::Disconnect => SlowClientPolicy::Disconnect,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HttpSinkParams {
+    url: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    batch_size: Option<usize>,
+    linger_ms: Option<u64>,
+    concurrency: Option<usize>,
+    max_retries: Option<u32>,
+    backoff_ms: Option<u64>,
+    max_backoff_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StreamSinkParams {
+    address: String,
+    #[serde(default)]
+    framing: FramingParam,
+    max_backoff_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UnixSocketSinkParams {
+    path: PathBuf,
+    #[serde(default)]
+    framing: FramingParam,
+    max_backoff_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UdpSinkParams {
+    address: String,
+    max_backoff_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PrinterParams {
+    #[serde(default)]
+    prefix: String,
+}
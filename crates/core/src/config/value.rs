@@ -0,0 +1,133 @@
+use std::fmt;
+
+/// Payload flowing through pipelines built from a config file. Stages are
+/// connected at runtime, so their payloads share this one type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Batch(Vec<String>),
+}
+
+impl Value {
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::Text(_) => ValueType::Text,
+            Value::Batch(_) => ValueType::Batch,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            Value::Batch(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Text(text) => write!(f, "{}", text),
+            Value::Batch(texts) => write!(f, "[{}]", texts.join(", ")),
+        }
+    }
+}
+
+/// Type of the payloads a stage consumes or produces, checked when a config is
+/// validated. `None` is the input of sources and the output of sinks, `Any`
+/// stages pass through whatever the previous stage produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    None,
+    Text,
+    Batch,
+    Any,
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueType::None => write!(f, "nothing"),
+            ValueType::Text => write!(f, "text"),
+            ValueType::Batch => write!(f, "batch"),
+            ValueType::Any => write!(f, "any"),
+        }
+    }
+}
+
+/// Payload types of regular components that can be converted to and from `Value`,
+/// so the components can be used in config pipelines through `DynComponent::adapt`.
+pub trait Payload: Sized + Send + Sync + 'static {
+    const TYPE: ValueType;
+
+    fn from_value(value: Value) -> Option<Self>;
+    fn into_value(self) -> Option<Value>;
+}
+
+impl Payload for () {
+    const TYPE: ValueType = ValueType::None;
+
+    fn from_value(_value: Value) -> Option<Self> {
+        None
+    }
+
+    fn into_value(self) -> Option<Value> {
+        None
+    }
+}
+
+impl Payload for String {
+    const TYPE: ValueType = ValueType::Text;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Text(text) => Some(text),
+            Value::Batch(_) => None,
+        }
+    }
+
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Text(self))
+    }
+}
+
+impl Payload for Vec<String> {
+    const TYPE: ValueType = ValueType::Batch;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Batch(texts) => Some(texts),
+            Value::Text(_) => None,
+        }
+    }
+
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Batch(self))
+    }
+}
+
+/// Parsed JSON values and objects travel through config pipelines as compact
+/// JSON text
+impl Payload for serde_json::Value {
+    const TYPE: ValueType = ValueType::Text;
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_text().and_then(|text| serde_json::from_str(text).ok())
+    }
+
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Text(self.to_string()))
+    }
+}
+
+impl Payload for serde_json::Map<String, serde_json::Value> {
+    const TYPE: ValueType = ValueType::Text;
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_text().and_then(|text| serde_json::from_str(text).ok())
+    }
+
+    fn into_value(self) -> Option<Value> {
+        Some(Value::Text(serde_json::Value::Object(self).to_string()))
+    }
+}
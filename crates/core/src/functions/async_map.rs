@@ -0,0 +1,163 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::{Receiver, Sender};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::marker::PhantomData;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
+use tokio::task::JoinHandle;
+use tracing::{debug, error};
+
+type AsyncTransform<I, O> = Arc<dyn Fn(I) -> Pin<Box<dyn Future<Output = O> + Send>> + Send + Sync>;
+
+/// Applies an async transform, e.g. an HTTP enrichment call, to each item with at
+/// most `concurrency` calls in flight. With `ordered` results are emitted in input
+/// order, otherwise as soon as they complete.
+pub struct AsyncMap<I, O>
+where
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    transform: AsyncTransform<I, O>,
+    concurrency: usize,
+    ordered: bool,
+    _phantom: PhantomData<(I, O)>,
+}
+
+impl<I, O> Clone for AsyncMap<I, O>
+where
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            transform: self.transform.clone(),
+            concurrency: self.concurrency,
+            ordered: self.ordered,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, O> AsyncMap<I, O>
+where
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    pub fn new<F, Fut>(transform: F) -> Self
+    where
+        F: Fn(I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = O> + Send + 'static,
+    {
+        AsyncMap {
+            transform: Arc::new(move |item| Box::pin(transform(item))),
+            concurrency: 1,
+            ordered: true,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        assert!(concurrency > 0, "AsyncMap requires a concurrency of at least one");
+        self.concurrency = concurrency;
+        self
+    }
+
+    pub fn with_ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+}
+
+impl<I, O> PipelineComponent for AsyncMap<I, O>
+where
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    type Input = I;
+    type Output = O;
+
+    fn new() -> Self {
+        panic!("AsyncMap requires a transform function. Use AsyncMap::new() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("AsyncMap starting with concurrency {}", self.concurrency);
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+
+        // In ordered mode results are forwarded in input order by a separate task. The
+        // permit is released only once the result is forwarded, which bounds buffering.
+        let (pending_tx, mut pending_rx) = mpsc::unbounded_channel::<(JoinHandle<Message<O>>, OwnedSemaphorePermit)>();
+        let forwarder = if self.ordered {
+            let output = output.clone();
+            Some(tokio::spawn(async move {
+                while let Some((handle, permit)) = pending_rx.recv().await {
+                    match handle.await {
+                        Ok(msg) => {
+                            if let Err(e) = output.send(msg) {
+                                error!("Failed to send transformed item: {:?}", e);
+                                break;
+                            }
+                        }
+                        Err(e) => error!("AsyncMap transform failed: {:?}", e),
+                    }
+                    drop(permit);
+                }
+            }))
+        } else {
+            None
+        };
+
+        // Receiving blocks, so it runs apart from the transforms and the forwarder
+        let (transform, ordered) = (self.transform.clone(), self.ordered);
+        let runtime = tokio::runtime::Handle::current();
+        let permits = semaphore.clone();
+        let receiver = tokio::task::spawn_blocking(move || {
+            while let Ok(msg) = input.recv() {
+                debug!("AsyncMap received item");
+                let permit = match runtime.block_on(permits.clone().acquire_owned()) {
+                    Ok(permit) => permit,
+                    Err(e) => {
+                        error!("AsyncMap semaphore closed: {:?}", e);
+                        break;
+                    }
+                };
+
+                let (payload, metadata) = msg.into_parts();
+                let future = transform(payload);
+
+                if ordered {
+                    let handle = runtime.spawn(async move { metadata.with_new_payload(future.await) });
+                    if pending_tx.send((handle, permit)).is_err() {
+                        error!("AsyncMap forwarder stopped");
+                        break;
+                    }
+                } else {
+                    let output = output.clone();
+                    runtime.spawn(async move {
+                        let transformed = future.await;
+                        if let Err(e) = output.send(metadata.with_new_payload(transformed)) {
+                            error!("Failed to send transformed item: {:?}", e);
+                        }
+                        drop(permit);
+                    });
+                }
+            }
+        });
+        if let Err(e) = receiver.await {
+            error!("AsyncMap failed: {:?}", e);
+        }
+
+        // Wait for in-flight transforms before completing
+        if let Some(forwarder) = forwarder {
+            if let Err(e) = forwarder.await {
+                error!("AsyncMap forwarder failed: {:?}", e);
+            }
+        }
+        if let Err(e) = semaphore.acquire_many(self.concurrency as u32).await {
+            error!("AsyncMap semaphore closed: {:?}", e);
+        }
+
+        debug!("AsyncMap completed");
+    }
+}
@@ -1,71 +1,152 @@
-use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::{PipelineComponent, StatelessComponent, ComponentContext, Message};
 use crate::pipeline::channel::{Receiver, Sender};
+use std::ops::Not;
 use std::sync::Arc;
 use tracing::{debug, error};
 use regex::Regex;
 
-#[derive(Clone)]
-pub enum FilterCondition {
-    Regex(Regex),
-    Lambda(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+type Predicate<T> = Arc<dyn Fn(&Message<T>) -> bool + Send + Sync>;
+
+pub enum FilterCondition<T> {
+    Regex(Regex, fn(&T) -> &str),
+    Lambda(Predicate<T>),
+    And(Box<FilterCondition<T>>, Box<FilterCondition<T>>),
+    Or(Box<FilterCondition<T>>, Box<FilterCondition<T>>),
+    Not(Box<FilterCondition<T>>),
 }
 
-#[derive(Clone)]
-pub struct Filter {
-    condition: FilterCondition,
+// Manual Clone implementation that doesn't require T: Clone
+impl<T> Clone for FilterCondition<T> {
+    fn clone(&self) -> Self {
+        match self {
+            FilterCondition::Regex(pattern, text) => FilterCondition::Regex(pattern.clone(), *text),
+            FilterCondition::Lambda(f) => FilterCondition::Lambda(f.clone()),
+            FilterCondition::And(a, b) => FilterCondition::And(a.clone(), b.clone()),
+            FilterCondition::Or(a, b) => FilterCondition::Or(a.clone(), b.clone()),
+            FilterCondition::Not(a) => FilterCondition::Not(a.clone()),
+        }
+    }
+}
+
+impl<T> FilterCondition<T> {
+    pub fn matches(&self, msg: &Message<T>) -> bool {
+        match self {
+            FilterCondition::Regex(pattern, text) => pattern.is_match(text(&msg.payload)),
+            FilterCondition::Lambda(f) => f(msg),
+            FilterCondition::And(a, b) => a.matches(msg) && b.matches(msg),
+            FilterCondition::Or(a, b) => a.matches(msg) || b.matches(msg),
+            FilterCondition::Not(a) => !a.matches(msg),
+        }
+    }
 }
 
-impl Filter {
+pub struct Filter<T> {
+    condition: FilterCondition<T>,
+}
+
+impl<T> Clone for Filter<T> {
+    fn clone(&self) -> Self {
+        Filter {
+            condition: self.condition.clone(),
+        }
+    }
+}
+
+impl<T> Filter<T> {
+    /// Keeps messages for which the predicate returns true. The predicate sees the
+    /// whole message, so it can also filter on event time or source.
+    pub fn with_predicate<F>(f: F) -> Self
+    where
+        F: Fn(&Message<T>) -> bool + Send + Sync + 'static
+    {
+        Filter {
+            condition: FilterCondition::Lambda(Arc::new(f)),
+        }
+    }
+
+    pub fn with_condition(condition: FilterCondition<T>) -> Self {
+        Filter { condition }
+    }
+
+    /// Keeps messages matching both this filter and `other`
+    pub fn and(self, other: Filter<T>) -> Self {
+        Filter {
+            condition: FilterCondition::And(Box::new(self.condition), Box::new(other.condition)),
+        }
+    }
+
+    /// Keeps messages matching either this filter or `other`
+    pub fn or(self, other: Filter<T>) -> Self {
+        Filter {
+            condition: FilterCondition::Or(Box::new(self.condition), Box::new(other.condition)),
+        }
+    }
+}
+
+impl<T> Not for Filter<T> {
+    type Output = Filter<T>;
+
+    fn not(self) -> Filter<T> {
+        Filter {
+            condition: FilterCondition::Not(Box::new(self.condition)),
+        }
+    }
+}
+
+impl<T: AsRef<str>> Filter<T> {
     pub fn with_pattern(pattern: &str) -> Result<Self, regex::Error> {
         Ok(Filter {
-            condition: FilterCondition::Regex(Regex::new(pattern)?),
+            condition: FilterCondition::Regex(Regex::new(pattern)?, T::as_ref),
         })
     }
 
-    pub fn with_lambda<F>(f: F) -> Self 
-    where 
-        F: Fn(&str) -> bool + Send + Sync + 'static 
+    pub fn with_lambda<F>(f: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static
     {
         Filter {
-            condition: FilterCondition::Lambda(Arc::new(f)),
+            condition: FilterCondition::Lambda(Arc::new(move |msg: &Message<T>| f(msg.payload.as_ref()))),
         }
     }
 }
 
-impl PipelineComponent for Filter {
-    type Input = String;
-    type Output = String;
+impl<T: Send + Sync + 'static> PipelineComponent for Filter<T> {
+    type Input = T;
+    type Output = T;
 
     fn new() -> Self {
-        Filter {
-            condition: FilterCondition::Regex(Regex::new(".*").expect("Default pattern should always be valid")),
-        }
+        Filter::with_predicate(|_| true)
+    }
+
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Self::Input, Self::Output>>> {
+        Some(self)
     }
 
     async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
         debug!("Filter starting");
-        
-        while let Ok(msg) = input.recv() {
-            let text = msg.payload.clone();
 
-            debug!("Filter received text: {}", text);
+        while let Ok(msg) = input.recv() {
+            debug!("Filter received item");
 
-            let matches = match &self.condition {
-                FilterCondition::Regex(pattern) => pattern.is_match(&text),
-                FilterCondition::Lambda(f) => f(&text),
-            };
-            
-            if matches {
-                debug!("Text matches condition, forwarding");
+            if self.condition.matches(&msg) {
+                debug!("Item matches condition, forwarding");
                 if let Err(e) = output.send(msg) {
-                    error!("Failed to send filtered text: {:?}", e);
+                    error!("Failed to send filtered item: {:?}", e);
                     break;
                 }
             } else {
-                debug!("Text does not match condition, dropping");
+                debug!("Item does not match condition, dropping");
             }
         }
-        
+
         debug!("Filter completed");
     }
 }
+
+impl<T: Send + Sync + 'static> StatelessComponent<T, T> for Filter<T> {
+    fn process(&self, msg: Message<T>, emit: &mut dyn FnMut(Message<T>)) {
+        if self.condition.matches(&msg) {
+            emit(msg);
+        }
+    }
+}
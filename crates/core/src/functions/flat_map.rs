@@ -0,0 +1,95 @@
+use crate::pipeline::{PipelineComponent, StatelessComponent, ComponentContext, Message};
+use crate::pipeline::{Receiver, Sender};
+use std::sync::Arc;
+use std::marker::PhantomData;
+use tracing::{debug, error};
+
+/// Transforms each item into zero or more items, e.g. splitting a post into words.
+/// Every output message keeps the metadata of the input it came from.
+pub struct FlatMap<I, O> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    transform: Arc<dyn Fn(I) -> Vec<O> + Send + Sync>,
+    _phantom: PhantomData<(I, O)>,
+}
+
+impl<I, O> Clone for FlatMap<I, O>
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            transform: self.transform.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, O> FlatMap<I, O> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    pub fn new<F, It>(transform: F) -> Self 
+    where 
+        F: Fn(I) -> It + Send + Sync + 'static,
+        It: IntoIterator<Item = O>,
+    {
+        FlatMap {
+            transform: Arc::new(move |item| transform(item).into_iter().collect()),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, O> PipelineComponent for FlatMap<I, O> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    type Input = I;
+    type Output = O;
+
+    fn new() -> Self {
+        panic!("FlatMap requires a transform function. Use FlatMap::new() instead.")
+    }
+
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Self::Input, Self::Output>>> {
+        Some(self)
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("FlatMap starting");
+        
+        'outer: while let Ok(msg) = input.recv() {
+            debug!("FlatMap received item");
+            let (payload, metadata) = msg.into_parts();
+            
+            for item in (self.transform)(payload) {
+                if let Err(e) = output.send(metadata.clone().with_new_payload(item)) {
+                    error!("Failed to send transformed item: {:?}", e);
+                    break 'outer;
+                }
+            }
+            debug!("FlatMap sent transformed items");
+        }
+        
+        debug!("FlatMap completed");
+    }
+}
+
+impl<I, O> StatelessComponent<I, O> for FlatMap<I, O> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    fn process(&self, msg: Message<I>, emit: &mut dyn FnMut(Message<O>)) {
+        let (payload, metadata) = msg.into_parts();
+        for item in (self.transform)(payload) {
+            emit(metadata.clone().with_new_payload(item));
+        }
+    }
+}
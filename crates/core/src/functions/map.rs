@@ -1,4 +1,4 @@
-use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::{PipelineComponent, StatelessComponent, ComponentContext, Message};
 use crate::pipeline::{Receiver, Sender};
 use std::sync::Arc;
 use std::marker::PhantomData;
@@ -44,8 +44,8 @@ where
 
 impl<I, O> PipelineComponent for Map<I, O> 
 where 
-    I: Send + Sync + Clone + 'static,
-    O: Send + Sync + Clone + 'static,
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
 {
     type Input = I;
     type Output = O;
@@ -54,14 +54,19 @@ where
         panic!("Map requires a transform function. Use Map::new() instead.")
     }
 
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Self::Input, Self::Output>>> {
+        Some(self)
+    }
+
     async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
         debug!("Map starting");
         
         while let Ok(msg) = input.recv() {
             debug!("Map received item");
-            let transformed = (self.transform)(msg.payload.clone());
+            let (payload, metadata) = msg.into_parts();
+            let transformed = (self.transform)(payload);
             
-            if let Err(e) = output.send(msg.with_new_payload(transformed)) {
+            if let Err(e) = output.send(metadata.with_new_payload(transformed)) {
                 error!("Failed to send transformed item: {:?}", e);
                 break;
             }
@@ -71,3 +76,14 @@ where
         debug!("Map completed");
     }
 }
+
+impl<I, O> StatelessComponent<I, O> for Map<I, O> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    fn process(&self, msg: Message<I>, emit: &mut dyn FnMut(Message<O>)) {
+        let (payload, metadata) = msg.into_parts();
+        emit(metadata.with_new_payload((self.transform)(payload)));
+    }
+}
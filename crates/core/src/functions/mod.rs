@@ -1,9 +1,17 @@
 pub mod filter;
 pub mod map;
+pub mod flat_map;
+pub mod try_map;
+pub mod async_map;
 pub mod reduce;
 pub mod window; 
+pub mod parse_json;
 
 pub use filter::Filter;
 pub use map::Map;
+pub use flat_map::FlatMap;
+pub use try_map::TryMap;
+pub use async_map::AsyncMap;
 pub use reduce::Reduce;
-pub use window::Window; 
\ No newline at end of file
+pub use window::{Window, SlidingWindow};
+pub use parse_json::{ParseJson, JsonPath, JsonParseError}; 
\ No newline at end of file
@@ -0,0 +1,233 @@
+use crate::pipeline::{PipelineComponent, StatelessComponent, ComponentContext, Message};
+use crate::pipeline::{Receiver, Sender};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tracing::{debug, error, warn};
+
+/// Location of a field in a JSON document, written JSONPath style, e.g.
+/// `$.account.username`, `$.tags[0].name` or `$['display name']`. The leading
+/// `$` is optional. Wildcards and filters are not supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPath {
+    path: String,
+    segments: Vec<Segment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+impl JsonPath {
+    pub fn parse(path: &str) -> Result<JsonPath, String> {
+        let invalid = |reason: &str| format!("invalid JSON path {:?}: {}", path, reason);
+        let mut segments = Vec::new();
+        let mut rest = match path.strip_prefix('$') {
+            Some(rest) => rest,
+            // Without `$` the path may start with a key, e.g. `account.username`
+            None if !path.starts_with(['.', '[']) => {
+                let end = path.find(['.', '[']).unwrap_or(path.len());
+                if end == 0 {
+                    return Err(invalid("empty key"));
+                }
+                segments.push(Segment::Key(path[..end].to_string()));
+                &path[end..]
+            }
+            None => path,
+        };
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end == 0 {
+                    return Err(invalid("empty key"));
+                }
+                if &after[..end] == "*" {
+                    return Err(invalid("wildcards are not supported"));
+                }
+                segments.push(Segment::Key(after[..end].to_string()));
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("unclosed ["))?;
+                let inner = after[..end].trim();
+                let quoted = inner.len() >= 2
+                    && ((inner.starts_with('\'') && inner.ends_with('\'')) || (inner.starts_with('"') && inner.ends_with('"')));
+                if quoted {
+                    segments.push(Segment::Key(inner[1..inner.len() - 1].to_string()));
+                } else {
+                    let index = inner.parse().map_err(|_| invalid("expected an index or a quoted key in []"))?;
+                    segments.push(Segment::Index(index));
+                }
+                rest = &after[end + 1..];
+            } else {
+                return Err(invalid("expected . or ["));
+            }
+        }
+
+        Ok(JsonPath {
+            path: path.to_string(),
+            segments,
+        })
+    }
+
+    /// The value at this path, if the document has one
+    pub fn get<'a>(&self, document: &'a Value) -> Option<&'a Value> {
+        self.segments.iter().try_fold(document, |value, segment| match segment {
+            Segment::Key(key) => value.get(key),
+            Segment::Index(index) => value.get(index),
+        })
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)
+    }
+}
+
+/// A line that could not be parsed, with the reason
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonParseError {
+    pub line: String,
+    pub message: String,
+}
+
+impl fmt::Display for JsonParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Parses lines of JSON into `T`, which is a `serde_json::Value` unless a
+/// `Deserialize` type is given, e.g. `ParseJson::<Post>::new()`.
+///
+/// `with_extract` parses only the part of the document at a path, and
+/// `with_field` flattens nested fields into an object with one field per call,
+/// which is then parsed into `T`. Missing fields are `null`.
+///
+/// Lines that are not valid JSON or do not match `T` are sent to the error
+/// sender if one is configured and dropped otherwise.
+pub struct ParseJson<T = Value> {
+    extract: Option<JsonPath>,
+    fields: Vec<(String, JsonPath)>,
+    errors: Option<Sender<JsonParseError>>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> Clone for ParseJson<T> {
+    fn clone(&self) -> Self {
+        ParseJson {
+            extract: self.extract.clone(),
+            fields: self.fields.clone(),
+            errors: self.errors.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T: DeserializeOwned> ParseJson<T> {
+    /// Parses the value at `path` instead of the whole document. Lines without a
+    /// value at `path` are errors.
+    ///
+    /// Panics if `path` is not a valid `JsonPath`.
+    pub fn with_extract(mut self, path: &str) -> Self {
+        self.extract = Some(JsonPath::parse(path).unwrap_or_else(|e| panic!("{}", e)));
+        self
+    }
+
+    /// Adds the value at `path` to the projected object as `name`
+    ///
+    /// Panics if `path` is not a valid `JsonPath`.
+    pub fn with_field(mut self, name: impl Into<String>, path: &str) -> Self {
+        let path = JsonPath::parse(path).unwrap_or_else(|e| panic!("{}", e));
+        self.fields.push((name.into(), path));
+        self
+    }
+
+    /// Routes lines that fail to parse to `errors`, keeping the metadata of the
+    /// input message
+    pub fn with_errors(mut self, errors: Sender<JsonParseError>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    pub fn parse(&self, line: &str) -> Result<T, String> {
+        let mut document: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+
+        if let Some(extract) = &self.extract {
+            document = extract.get(&document)
+                .cloned()
+                .ok_or_else(|| format!("no value at {}", extract))?;
+        }
+
+        if !self.fields.is_empty() {
+            document = Value::Object(self.fields.iter()
+                .map(|(name, path)| (name.clone(), path.get(&document).cloned().unwrap_or(Value::Null)))
+                .collect());
+        }
+
+        serde_json::from_value(document).map_err(|e| e.to_string())
+    }
+
+    fn route_error(&self, error: Message<JsonParseError>) {
+        match &self.errors {
+            Some(errors) => {
+                if errors.send(error).is_err() {
+                    warn!("ParseJson error channel closed, dropping failed line");
+                }
+            }
+            None => warn!("ParseJson dropped line: {}", error.payload),
+        }
+    }
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static> PipelineComponent for ParseJson<T> {
+    type Input = String;
+    type Output = T;
+
+    fn new() -> Self {
+        ParseJson {
+            extract: None,
+            fields: Vec::new(),
+            errors: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Self::Input, Self::Output>>> {
+        Some(self)
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("ParseJson starting");
+
+        while let Ok(msg) = input.recv() {
+            let (line, metadata) = msg.into_parts();
+            match self.parse(&line) {
+                Ok(parsed) => {
+                    if let Err(e) = output.send(metadata.with_new_payload(parsed)) {
+                        error!("Failed to send parsed item: {:?}", e);
+                        break;
+                    }
+                }
+                Err(message) => self.route_error(metadata.with_new_payload(JsonParseError { line, message })),
+            }
+        }
+
+        debug!("ParseJson completed");
+    }
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static> StatelessComponent<String, T> for ParseJson<T> {
+    fn process(&self, msg: Message<String>, emit: &mut dyn FnMut(Message<T>)) {
+        let (line, metadata) = msg.into_parts();
+        match self.parse(&line) {
+            Ok(parsed) => emit(metadata.with_new_payload(parsed)),
+            Err(message) => self.route_error(metadata.with_new_payload(JsonParseError { line, message })),
+        }
+    }
+}
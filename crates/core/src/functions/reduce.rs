@@ -1,18 +1,44 @@
-use crate::pipeline::{PipelineComponent, ComponentContext, Receiver, Sender, Message};
+use crate::pipeline::{PipelineComponent, ComponentContext, Receiver, Sender, Message, AggregateMetadata};
 use std::sync::{Arc, Mutex};
 use std::marker::PhantomData;
 use tracing::{debug, error};
 
+type Reducer<I, O> = Arc<dyn Fn(&mut O, I) + Send + Sync>;
+
 pub struct Reduce<I, O> 
 where 
     I: Send + Sync + 'static,
     O: Send + Sync + 'static,
 {
-    reducer: Arc<dyn Fn(&mut O, I) + Send + Sync>,
-    current_value: Arc<Mutex<O>>,
+    reducer: Reducer<I, O>,
+    state: Arc<Mutex<ReduceState<O>>>,
     _phantom: PhantomData<I>,
 }
 
+/// The value folded so far with its lineage, kept under one lock so slots
+/// never emit a value with the metadata of another update
+struct ReduceState<O> {
+    value: O,
+    aggregate: AggregateMetadata,
+    event_time: u64,  // Latest event time folded so far
+}
+
+impl<O> ReduceState<O> {
+    fn fold<I>(&mut self, item: &Message<I>) {
+        let (start, end) = match &item.aggregate {
+            Some(inner) => (inner.window_start, inner.window_end),
+            None => (item.event_timestamp, item.event_timestamp),
+        };
+        if self.aggregate.count == 0 {
+            self.aggregate.window_start = start;
+        }
+        self.aggregate.window_start = self.aggregate.window_start.min(start);
+        self.aggregate.window_end = self.aggregate.window_end.max(end);
+        self.aggregate.add(item);
+        self.event_time = self.event_time.max(item.event_timestamp);
+    }
+}
+
 impl<I, O> Clone for Reduce<I, O>
 where 
     I: Send + Sync + 'static,
@@ -21,7 +47,7 @@ where
     fn clone(&self) -> Self {
         Self {
             reducer: self.reducer.clone(),  // Now this works because Arc implements Clone
-            current_value: self.current_value.clone(),
+            state: self.state.clone(),
             _phantom: PhantomData,
         }
     }
@@ -38,13 +64,17 @@ where
     {
         Reduce {
             reducer: Arc::new(reducer),  // Wrap in Arc here
-            current_value: Arc::new(Mutex::new(initial)),
+            state: Arc::new(Mutex::new(ReduceState {
+                value: initial,
+                aggregate: AggregateMetadata::default(),
+                event_time: 0,
+            })),
             _phantom: PhantomData,
         }
     }
 
     pub fn get_result(&self) -> O {
-        self.current_value.lock().unwrap().clone()
+        self.state.lock().unwrap().value.clone()
     }
 }
 
@@ -66,12 +96,15 @@ where
         while let Ok(item) = input.recv() {
             debug!("Reduce received item");
             
-            // Apply reducer function to current value
-            if let Ok(mut current) = self.current_value.lock() {
-                (self.reducer)(&mut current, item.payload);
-                
+            // Apply reducer function to current value, tracking the lineage of
+            // everything folded into it
+            if let Ok(mut state) = self.state.lock() {
+                state.fold(&item);
+                (self.reducer)(&mut state.value, item.payload);
+
                 // Send the updated value
-                if let Err(e) = output.send(Message::new(current.clone())) {
+                let updated = Message::with_aggregate(state.value.clone(), state.event_time, state.aggregate.clone());
+                if let Err(e) = output.send(updated) {
                     error!("Failed to send reduced value: {:?}", e);
                     break;
                 }
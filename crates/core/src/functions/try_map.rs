@@ -0,0 +1,124 @@
+use crate::pipeline::{PipelineComponent, StatelessComponent, ComponentContext, Message};
+use crate::pipeline::{Receiver, Sender};
+use std::sync::Arc;
+use std::marker::PhantomData;
+use tracing::{debug, error, warn};
+
+/// Like `Map`, but the transform may fail. Successful results continue down the
+/// pipeline, failures are sent to the error sender if one is configured and
+/// dropped otherwise.
+pub struct TryMap<I, O, E> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    transform: Arc<dyn Fn(I) -> Result<O, E> + Send + Sync>,
+    errors: Option<Sender<E>>,
+    _phantom: PhantomData<(I, O)>,
+}
+
+impl<I, O, E> Clone for TryMap<I, O, E>
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            transform: self.transform.clone(),
+            errors: self.errors.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<I, O, E> TryMap<I, O, E> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    pub fn new<F>(transform: F) -> Self 
+    where 
+        F: Fn(I) -> Result<O, E> + Send + Sync + 'static,
+    {
+        TryMap {
+            transform: Arc::new(transform),
+            errors: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Routes failed items to `errors`, keeping the metadata of the input message
+    pub fn with_errors(mut self, errors: Sender<E>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+
+    fn route_error(&self, error: Message<E>) {
+        match &self.errors {
+            Some(errors) => {
+                if errors.send(error).is_err() {
+                    warn!("TryMap error channel closed, dropping failed item");
+                }
+            }
+            None => warn!("TryMap dropped failed item"),
+        }
+    }
+}
+
+impl<I, O, E> PipelineComponent for TryMap<I, O, E> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    type Input = I;
+    type Output = O;
+
+    fn new() -> Self {
+        panic!("TryMap requires a transform function. Use TryMap::new() instead.")
+    }
+
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Self::Input, Self::Output>>> {
+        Some(self)
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("TryMap starting");
+        
+        while let Ok(msg) = input.recv() {
+            debug!("TryMap received item");
+            let (payload, metadata) = msg.into_parts();
+
+            match (self.transform)(payload) {
+                Ok(transformed) => {
+                    if let Err(e) = output.send(metadata.with_new_payload(transformed)) {
+                        error!("Failed to send transformed item: {:?}", e);
+                        break;
+                    }
+                    debug!("TryMap sent transformed item");
+                }
+                Err(err) => self.route_error(metadata.with_new_payload(err)),
+            }
+        }
+        
+        debug!("TryMap completed");
+    }
+}
+
+impl<I, O, E> StatelessComponent<I, O> for TryMap<I, O, E> 
+where 
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    fn process(&self, msg: Message<I>, emit: &mut dyn FnMut(Message<O>)) {
+        let (payload, metadata) = msg.into_parts();
+        match (self.transform)(payload) {
+            Ok(transformed) => emit(metadata.with_new_payload(transformed)),
+            Err(err) => self.route_error(metadata.with_new_payload(err)),
+        }
+    }
+}
@@ -1,6 +1,6 @@
-use crate::pipeline::{PipelineComponent, ComponentContext, Receiver, Sender, Message};
+use crate::pipeline::{PipelineComponent, ComponentContext, Receiver, Sender, Message, AggregateMetadata, Clock};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::Duration;
 use std::marker::PhantomData;
 use tracing::{debug, error};
 
@@ -8,12 +8,10 @@ use tracing::{debug, error};
 pub enum WindowCondition {
     Count(usize),
     Time(Duration),
-    Sliding {
-        window_size: Duration,
-        slide_interval: Duration,
-    },
 }
 
+/// Groups items into batches of a count or a duration, moving the items into the
+/// batch. Overlapping windows are built with `with_sliding_window`.
 #[derive(Clone)]
 pub struct Window<T> {
     condition: WindowCondition,
@@ -34,57 +32,95 @@ impl<T> Window<T> {
             _phantom: PhantomData,
         }
     }
+}
 
-    pub fn with_sliding_window(window_size: Duration, slide_interval: Duration) -> Self {
-        Window {
-            condition: WindowCondition::Sliding { window_size, slide_interval },
+impl<T: Clone> Window<T> {
+    /// Emits the items of the last `window_size` every `slide_interval`, see
+    /// `SlidingWindow`
+    pub fn with_sliding_window(window_size: Duration, slide_interval: Duration) -> SlidingWindow<T> {
+        SlidingWindow::new(window_size, slide_interval)
+    }
+}
+
+/// Emits the items of the last `window_size` every `slide_interval`. Windows
+/// overlap, so each batch gets clones of the items still in the window; prefer
+/// cheaply cloneable payloads such as `Arc<T>` or `Bytes`.
+#[derive(Clone)]
+pub struct SlidingWindow<T> {
+    window_size: Duration,
+    slide_interval: Duration,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Clone> SlidingWindow<T> {
+    pub fn new(window_size: Duration, slide_interval: Duration) -> Self {
+        SlidingWindow {
+            window_size,
+            slide_interval,
             _phantom: PhantomData,
         }
     }
+}
 
-    fn should_trigger(&self, buffer_len: usize, last_trigger_time: u64) -> bool {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+/// Returns the earliest and latest event time of the buffered messages.
+fn event_time_bounds<T>(buffer: &[Message<T>]) -> (u64, u64) {
+    let start = buffer.iter().map(|msg| msg.event_timestamp).min().unwrap_or(0);
+    let end = buffer.iter().map(|msg| msg.event_timestamp).max().unwrap_or(0);
+    (start, end)
+}
 
-        match self.condition {
-            WindowCondition::Count(count) => buffer_len >= count,
-            WindowCondition::Time(duration) => {
-                now >= last_trigger_time + duration.as_millis() as u64
-            },
-            WindowCondition::Sliding { slide_interval, .. } => {
-                now >= last_trigger_time + slide_interval.as_millis() as u64
+/// Moves the buffered messages into one batch, spanning `bounds` or, without
+/// them, the event times of the messages
+fn drain_window<T>(buffer: &mut Vec<Message<T>>, bounds: Option<(u64, u64)>) -> Message<Vec<T>> {
+    let (start, end) = event_time_bounds(buffer);
+    let (start, window_end) = bounds.unwrap_or((start, end));
+    let window = AggregateMetadata::from_messages(buffer.iter()).with_bounds(start, window_end);
+    let items = buffer.drain(..).map(|msg| msg.payload).collect();
+    Message::with_aggregate(items, end, window)
+}
+
+/// Buffers the input, sending the batch `take` builds whenever `due` says a
+/// window is complete. What is left once the input completes is sent by
+/// `flush`. Both are given the current and the last trigger time.
+fn run_windows<T>(
+    input: &Receiver<T>,
+    output: &Sender<Vec<T>>,
+    clock: &dyn Clock,
+    due: impl Fn(u64, usize, u64) -> bool,
+    take: impl Fn(u64, u64, &mut Vec<Message<T>>) -> Message<Vec<T>>,
+    flush: impl Fn(u64, u64, &mut Vec<Message<T>>) -> Message<Vec<T>>,
+) {
+    let mut buffer: Vec<Message<T>> = Vec::new();
+    let mut last_trigger = clock.now_millis();
+
+    while let Ok(msg) = input.recv() {
+        let now = clock.now_millis();
+
+        // Use the message's event timestamp
+        buffer.push(msg);
+
+        if due(now, buffer.len(), last_trigger) {
+            let items_to_send = take(now, last_trigger, &mut buffer);
+            if !items_to_send.payload.is_empty() {
+                if let Err(e) = output.send(items_to_send) {
+                    error!("Failed to send windowed items: {:?}", e);
+                    return;
+                }
             }
+            last_trigger = now;
         }
     }
 
-    fn get_window_items(&self, now: u64, buffer: &mut Vec<Message<T>>) -> Message<Vec<T>> 
-    where T: Clone 
-    {
-        match self.condition {
-            WindowCondition::Count(_) => {
-                let items = buffer.drain(0..).map(|msg| msg.payload.clone()).collect();
-                Message::new(items)
-            },
-            WindowCondition::Time(_) => {
-                let items = buffer.drain(0..).map(|msg| msg.payload.clone()).collect();
-                Message::new(items)
-            },
-            WindowCondition::Sliding { window_size, .. } => {
-                // Remove items outside the window
-                let cutoff = now - window_size.as_millis() as u64;
-                buffer.retain(|msg| msg.event_timestamp >= cutoff);
-                
-                // Return clones of all items in the window
-                let items = buffer.iter().map(|msg| msg.payload.clone()).collect();
-                Message::new(items)
-            }
+    // Send any remaining items
+    if !buffer.is_empty() {
+        let remaining = flush(clock.now_millis(), last_trigger, &mut buffer);
+        if let Err(e) = output.send(remaining) {
+            error!("Failed to send final windowed items: {:?}", e);
         }
     }
 }
 
-impl<T: Send + Sync + Clone + 'static> PipelineComponent for Window<T> {
+impl<T: Send + Sync + 'static> PipelineComponent for Window<T> {
     type Input = T;
     type Output = Vec<T>;
 
@@ -92,44 +128,61 @@ impl<T: Send + Sync + Clone + 'static> PipelineComponent for Window<T> {
         Window::<T>::with_count(10)
     }
 
-    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
         debug!("Window starting");
-        
-        let mut buffer: Vec<Message<T>> = Vec::new();
-        let mut last_trigger = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
-        
-        while let Ok(msg) = input.recv() {
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64;
-            
-            // Use the message's event timestamp
-            buffer.push(msg);
-            
-            if self.should_trigger(buffer.len(), last_trigger) {
-                let items_to_send = self.get_window_items(now, &mut buffer);
-                if !items_to_send.payload.is_empty() {
-                    if let Err(e) = output.send(items_to_send) {
-                        error!("Failed to send windowed items: {:?}", e);
-                        break;
-                    }
-                }
-                last_trigger = now;
-            }
-        }
-        
-        // Send any remaining items
-        if !buffer.is_empty() {
-            let remaining_items = buffer.drain(..).map(|msg| msg.payload.clone()).collect::<Vec<_>>();
-            if let Err(e) = output.send(Message::new(remaining_items)) {
-                error!("Failed to send final windowed items: {:?}", e);
+
+        match self.condition {
+            WindowCondition::Count(count) => run_windows(
+                &input,
+                &output,
+                context.clock(),
+                |_, buffer_len, _| buffer_len >= count,
+                |_, _, buffer| drain_window(buffer, None),
+                |_, _, buffer| drain_window(buffer, None),
+            ),
+            WindowCondition::Time(duration) => {
+                let duration = duration.as_millis() as u64;
+                let take = |now, last_trigger, buffer: &mut Vec<Message<T>>| drain_window(buffer, Some((last_trigger, now)));
+                run_windows(&input, &output, context.clock(), |now, _, last_trigger| now >= last_trigger + duration, take, take)
             }
         }
-        
+
         debug!("Window completed");
     }
 }
+
+impl<T: Clone + Send + Sync + 'static> PipelineComponent for SlidingWindow<T> {
+    type Input = T;
+    type Output = Vec<T>;
+
+    fn new() -> Self {
+        panic!("SlidingWindow requires a window size and slide interval. Use SlidingWindow::new() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("SlidingWindow starting");
+
+        let window_size = self.window_size.as_millis() as u64;
+        let slide_interval = self.slide_interval.as_millis() as u64;
+        run_windows(
+            &input,
+            &output,
+            context.clock(),
+            |now, _, last_trigger| now >= last_trigger + slide_interval,
+            |now, _, buffer| {
+                // Remove items outside the window
+                let cutoff = now.saturating_sub(window_size);
+                buffer.retain(|msg| msg.event_timestamp >= cutoff);
+
+                // Items stay buffered for the next slide, so the batch gets clones
+                let (_, end) = event_time_bounds(buffer);
+                let window = AggregateMetadata::from_messages(buffer.iter()).with_bounds(cutoff, now);
+                let items = buffer.iter().map(|msg| msg.payload.clone()).collect();
+                Message::with_aggregate(items, end, window)
+            },
+            |now, last_trigger, buffer| drain_window(buffer, Some((last_trigger, now))),
+        );
+
+        debug!("SlidingWindow completed");
+    }
+}
@@ -3,4 +3,9 @@ pub mod test_utils;
 pub mod sources;
 pub mod transformers;
 pub mod slots;
-pub mod functions;
\ No newline at end of file
+pub mod functions;
+pub mod config;
+pub mod remote;
+pub mod sqlite;
+pub mod mqtt;
+pub mod socket;
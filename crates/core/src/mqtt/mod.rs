@@ -0,0 +1,62 @@
+pub mod mqtt_sink;
+pub mod mqtt_source;
+pub mod websocket_mqtt_source;
+
+pub use mqtt_sink::MqttSink;
+pub use mqtt_source::MqttSource;
+pub use websocket_mqtt_source::WebSocketMqttSource;
+pub use rumqttc::QoS;
+
+use rumqttc::{MqttOptions, TlsConfiguration, Transport};
+use std::time::Duration;
+use url::Url;
+
+/// How `MqttSink` and `MqttSource` connect to a broker
+#[derive(Clone)]
+struct BrokerOptions {
+    url: String,
+    client_id: String,
+    credentials: Option<(String, String)>,
+    clean_session: bool,
+    ca: Option<Vec<u8>>,
+}
+
+impl BrokerOptions {
+    fn new(url: &str, client_id: &str) -> Self {
+        BrokerOptions {
+            url: url.to_string(),
+            client_id: client_id.to_string(),
+            credentials: None,
+            clean_session: true,
+            ca: None,
+        }
+    }
+
+    /// Options for the broker at `url`: `mqtt://host:1883` for plain TCP or
+    /// `mqtts://host:8883` for TLS
+    fn mqtt_options(&self) -> Result<MqttOptions, String> {
+        let mut url = Url::parse(&self.url).map_err(|e| format!("invalid MQTT URL {:?}: {}", self.url, e))?;
+        url.query_pairs_mut().append_pair("client_id", &self.client_id);
+        let tls = matches!(url.scheme(), "mqtts" | "ssl");
+
+        let mut options = MqttOptions::try_from(url).map_err(|e| format!("invalid MQTT URL {:?}: {}", self.url, e))?;
+        options.set_keep_alive(Duration::from_secs(20));
+        options.set_clean_session(self.clean_session);
+        if let Some((username, password)) = &self.credentials {
+            options.set_credentials(username, password);
+        }
+        if let (true, Some(ca)) = (tls, &self.ca) {
+            options.set_transport(Transport::Tls(TlsConfiguration::Simple {
+                ca: ca.clone(),
+                alpn: None,
+                client_auth: None,
+            }));
+        }
+        Ok(options)
+    }
+}
+
+/// The QoS for a level of 0, 1 or 2
+pub fn qos(level: u8) -> Result<QoS, String> {
+    rumqttc::qos(level).map_err(|_| format!("invalid QoS level {}, expected 0, 1 or 2", level))
+}
@@ -0,0 +1,136 @@
+use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::channel::{Sender, Receiver};
+use crate::pipeline::codec::payload_bytes;
+use super::{BrokerOptions, QoS};
+use rumqttc::{AsyncClient, Event, Outgoing};
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+// How long publishes still queued when the input completes may take to go out
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Publishes payloads to an MQTT broker over TCP, or TLS with an `mqtts://` URL.
+/// Strings are published as they are, other payloads as JSON.
+///
+/// The topic is a template in which `{source}`, `{position}` and `{event_time}`
+/// are replaced with the message's metadata, e.g. `sensors/{source}/readings`.
+/// Missing metadata is replaced with `unknown`.
+pub struct MqttSink<T> {
+    broker: BrokerOptions,
+    topic: String,
+    qos: QoS,
+    retain: bool,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> MqttSink<T> {
+    pub fn new(url: &str, client_id: &str, topic: &str) -> Self {
+        MqttSink {
+            broker: BrokerOptions::new(url, client_id),
+            topic: topic.to_string(),
+            qos: QoS::AtLeastOnce,
+            retain: false,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// At least once by default
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Asks the broker to keep the last payload of each topic for new subscribers
+    pub fn with_retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.broker.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Whether the broker discards the session on disconnect, true by default
+    pub fn with_clean_session(mut self, clean_session: bool) -> Self {
+        self.broker.clean_session = clean_session;
+        self
+    }
+
+    /// Trusts the PEM encoded CA certificates in `ca` instead of the platform's
+    pub fn with_ca(mut self, ca: Vec<u8>) -> Self {
+        self.broker.ca = Some(ca);
+        self
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> PipelineComponent for MqttSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("MqttSink requires a broker URL, client id and topic. Use MqttSink::new() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("MqttSink starting, publishing to {}", self.topic);
+
+        let options = match self.broker.mqtt_options() {
+            Ok(options) => options,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+        let (client, mut eventloop) = AsyncClient::new(options, 100);
+
+        // The event loop sends the publishes and reconnects, until the disconnect goes out
+        let connection = tokio::spawn(async move {
+            loop {
+                match eventloop.poll().await {
+                    Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!("MQTT connection error: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        });
+
+        // Receiving blocks, so it runs apart from the event loop
+        let template = self.topic.clone();
+        let (qos, retain) = (self.qos, self.retain);
+        let runtime = tokio::runtime::Handle::current();
+        let publisher = tokio::task::spawn_blocking(move || {
+            while let Ok(msg) = input.recv() {
+                let payload = match payload_bytes(&msg.payload) {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        error!("Failed to encode payload: {}", e);
+                        continue;
+                    }
+                };
+                let topic = msg.render(&template);
+                if let Err(e) = runtime.block_on(client.publish(topic, qos, retain, payload)) {
+                    error!("Failed to publish: {}", e);
+                    break;
+                }
+            }
+            runtime.block_on(client.disconnect())
+        });
+
+        match publisher.await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Failed to disconnect from MQTT broker: {}", e),
+            Err(e) => error!("MqttSink failed: {:?}", e),
+        }
+        if tokio::time::timeout(DRAIN_TIMEOUT, connection).await.is_err() {
+            error!("Gave up on publishes still queued for the MQTT broker");
+        }
+        debug!("MqttSink completed");
+    }
+}
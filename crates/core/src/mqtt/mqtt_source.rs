@@ -0,0 +1,137 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Sender, Receiver};
+use super::{BrokerOptions, QoS};
+use rumqttc::{AsyncClient, Event, Packet, SubscribeFilter};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+/// Subscribes to topics on an MQTT broker over TCP, or TLS with an `mqtts://`
+/// URL, and emits the payloads published to them as text. Messages carry the
+/// topic they were published to as their source.
+///
+/// Topic filters may use the `+` and `#` wildcards, e.g. `sensors/+/temperature`.
+/// With `with_clean_session(false)` the broker keeps the subscriptions and
+/// queues messages published at QoS 1 or 2 while the source is disconnected,
+/// for as long as the source connects with the same client id.
+///
+/// The source reconnects when the connection drops, and runs until the
+/// pipeline stops unless `with_idle_timeout` is set.
+pub struct MqttSource {
+    broker: BrokerOptions,
+    topics: Vec<SubscribeFilter>,
+    idle_timeout: Option<Duration>,
+}
+
+impl MqttSource {
+    pub fn new(url: &str, client_id: &str) -> Self {
+        MqttSource {
+            broker: BrokerOptions::new(url, client_id),
+            topics: Vec::new(),
+            idle_timeout: None,
+        }
+    }
+
+    /// Subscribes to `filter`, which may be given more than once
+    pub fn with_topic(mut self, filter: &str, qos: QoS) -> Self {
+        self.topics.push(SubscribeFilter::new(filter.to_string(), qos));
+        self
+    }
+
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.broker.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// Whether the broker discards the session on disconnect, true by default
+    pub fn with_clean_session(mut self, clean_session: bool) -> Self {
+        self.broker.clean_session = clean_session;
+        self
+    }
+
+    /// Trusts the PEM encoded CA certificates in `ca` instead of the platform's
+    pub fn with_ca(mut self, ca: Vec<u8>) -> Self {
+        self.broker.ca = Some(ca);
+        self
+    }
+
+    /// Stops once nothing has been published to the topics for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+}
+
+impl PipelineComponent for MqttSource {
+    type Input = ();
+    type Output = String;
+
+    fn new() -> Self {
+        panic!("MqttSource requires a broker URL and client id. Use MqttSource::new() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("MqttSource starting");
+
+        let options = match self.broker.mqtt_options() {
+            Ok(options) => options,
+            Err(e) => {
+                error!("{}", e);
+                return;
+            }
+        };
+        let (client, mut eventloop) = AsyncClient::new(options, 10);
+        let mut last_publish = Instant::now();
+
+        loop {
+            let event = match self.idle_timeout {
+                Some(timeout) => {
+                    let remaining = timeout.saturating_sub(last_publish.elapsed());
+                    match tokio::time::timeout(remaining, eventloop.poll()).await {
+                        Ok(event) => event,
+                        Err(_) => break,
+                    }
+                }
+                None => eventloop.poll().await,
+            };
+
+            match event {
+                Ok(Event::Incoming(Packet::ConnAck(ack))) => {
+                    // A persistent session still has the subscriptions of the last connection
+                    if !ack.session_present {
+                        if let Err(e) = client.try_subscribe_many(self.topics.clone()) {
+                            error!("Failed to subscribe: {}", e);
+                            break;
+                        }
+                    }
+                    debug!("Connected to MQTT broker, session present: {}", ack.session_present);
+                }
+                Ok(Event::Incoming(Packet::Publish(publish))) => {
+                    last_publish = Instant::now();
+                    let payload = match String::from_utf8(publish.payload.to_vec()) {
+                        Ok(payload) => payload,
+                        Err(_) => {
+                            warn!("Skipping message on {} that is not UTF-8", publish.topic);
+                            continue;
+                        }
+                    };
+                    if let Err(e) = output.send(Message::new(payload).with_source(publish.topic)) {
+                        error!("Failed to send message to output: {}", e);
+                        break;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("MQTT connection error: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+
+        if let Err(e) = client.try_disconnect() {
+            debug!("Failed to disconnect from MQTT broker: {}", e);
+        }
+        debug!("MqttSource completed");
+    }
+}
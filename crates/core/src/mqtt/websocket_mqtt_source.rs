@@ -46,8 +46,6 @@ impl PipelineComponent for WebSocketMqttSource {
             }
         };
 
-        println!("mqtt_options: {:?}", mqtt_options);
-
         mqtt_options.set_keep_alive(Duration::from_secs(20));
         debug!("MQTT Options: {:?}", mqtt_options);
 
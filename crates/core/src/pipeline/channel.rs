@@ -1,6 +1,6 @@
 use crossbeam_channel::{Receiver as CrossbeamReceiver, Sender as CrossbeamSender};
 use super::message::Message;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::fmt;
 
@@ -11,8 +11,52 @@ pub struct Sender<T> {
 }
 
 pub struct Receiver<T> {
-    inner: CrossbeamReceiver<Message<T>>,
+    inner: ReceiverInner<T>,
     last_receive_time: Arc<AtomicU64>,
+    close: Option<Arc<CloseSignal>>,
+}
+
+/// Retires the slot reading a receiver: dropping the trigger disconnects
+/// `closed`, which wakes the slot if it is blocked in `recv`.
+struct CloseSignal {
+    trigger: Mutex<Option<CrossbeamSender<()>>>,
+    closed: CrossbeamReceiver<()>,
+}
+
+impl CloseSignal {
+    fn new() -> Self {
+        let (trigger, closed) = crossbeam_channel::bounded(0);
+        CloseSignal {
+            trigger: Mutex::new(Some(trigger)),
+            closed,
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        matches!(self.closed.try_recv(), Err(crossbeam_channel::TryRecvError::Disconnected))
+    }
+}
+
+/// A receiver that produces messages by pulling from another receiver, used to
+/// fuse stateless operators into the stage that consumes their output.
+pub(crate) trait PullSource<T>: Send + Sync {
+    fn recv(&self) -> Result<Message<T>, crossbeam_channel::RecvError>;
+    fn try_recv(&self) -> Result<Message<T>, crossbeam_channel::TryRecvError>;
+    fn len(&self) -> usize;
+}
+
+enum ReceiverInner<T> {
+    Channel(CrossbeamReceiver<Message<T>>),
+    Fused(Arc<dyn PullSource<T>>),
+}
+
+impl<T> Clone for ReceiverInner<T> {
+    fn clone(&self) -> Self {
+        match self {
+            ReceiverInner::Channel(receiver) => ReceiverInner::Channel(receiver.clone()),
+            ReceiverInner::Fused(source) => ReceiverInner::Fused(source.clone()),
+        }
+    }
 }
 
 // Manual Debug implementations that don't require T: Debug
@@ -49,6 +93,7 @@ impl<T> Clone for Receiver<T> {
         Receiver {
             inner: self.inner.clone(),
             last_receive_time: self.last_receive_time.clone(),
+            close: self.close.clone(),
         }
     }
 }
@@ -91,6 +136,10 @@ impl<T> Sender<T> {
         self.inner.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
     pub fn capacity(&self) -> Option<usize> {
         self.inner.capacity()
     }
@@ -101,8 +150,61 @@ impl<T> Sender<T> {
 }
 
 impl<T> Receiver<T> {
+    pub(crate) fn from_source(source: Arc<dyn PullSource<T>>) -> Self {
+        Receiver {
+            inner: ReceiverInner::Fused(source),
+            last_receive_time: Arc::new(AtomicU64::new(0)),
+            close: None,
+        }
+    }
+
+    /// Marks the receiver as closed: once the messages already queued are drained,
+    /// `recv` returns an error even if senders are still alive. Used to retire slots,
+    /// only receivers from `closable_channel` can be closed.
+    pub(crate) fn close(&self) {
+        if let Some(signal) = &self.close {
+            signal.trigger.lock().unwrap().take();
+        }
+    }
+
+    fn recv_channel(&self, receiver: &CrossbeamReceiver<Message<T>>) -> Result<Message<T>, crossbeam_channel::RecvError> {
+        let Some(signal) = &self.close else {
+            return receiver.recv();
+        };
+        crossbeam_channel::select! {
+            recv(receiver) -> msg => msg,
+            // Messages queued before the slot was retired are still delivered
+            recv(signal.closed) -> _ => receiver.try_recv().map_err(|_| crossbeam_channel::RecvError),
+        }
+    }
+
     pub fn recv(&self) -> Result<Message<T>, crossbeam_channel::RecvError> {
-        let msg = self.inner.recv()?;
+        let msg = match &self.inner {
+            ReceiverInner::Channel(receiver) => self.recv_channel(receiver)?,
+            ReceiverInner::Fused(source) => source.recv()?,
+        };
+        self.last_receive_time.store(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u64,
+            Ordering::Relaxed
+        );
+        Ok(msg)
+    }
+
+    /// Receives a message if one is queued, without blocking. Components reading
+    /// several receivers at once poll them with this.
+    pub fn try_recv(&self) -> Result<Message<T>, crossbeam_channel::TryRecvError> {
+        let msg = match &self.inner {
+            ReceiverInner::Channel(receiver) => match receiver.try_recv() {
+                Err(crossbeam_channel::TryRecvError::Empty) if self.close.as_ref().is_some_and(|signal| signal.is_closed()) => {
+                    return Err(crossbeam_channel::TryRecvError::Disconnected);
+                }
+                result => result?,
+            },
+            ReceiverInner::Fused(source) => source.try_recv()?,
+        };
         self.last_receive_time.store(
             std::time::SystemTime::now()
                 .duration_since(std::time::UNIX_EPOCH)
@@ -114,11 +216,21 @@ impl<T> Receiver<T> {
     }
 
     pub fn len(&self) -> usize {
-        self.inner.len()
+        match &self.inner {
+            ReceiverInner::Channel(receiver) => receiver.len(),
+            ReceiverInner::Fused(source) => source.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 
     pub fn capacity(&self) -> Option<usize> {
-        self.inner.capacity()
+        match &self.inner {
+            ReceiverInner::Channel(receiver) => receiver.capacity(),
+            ReceiverInner::Fused(_) => None,
+        }
     }
 
     pub fn last_receive_time(&self) -> u64 {
@@ -135,8 +247,9 @@ pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
             last_send_time: Arc::new(AtomicU64::new(0)),
         },
         Receiver { 
-            inner: r,
+            inner: ReceiverInner::Channel(r),
             last_receive_time: Arc::new(AtomicU64::new(0)),
+            close: None,
         },
     )
 }
@@ -150,8 +263,17 @@ pub fn with_source<T>(source_id: impl Into<String>) -> (Sender<T>, Receiver<T>)
             last_send_time: Arc::new(AtomicU64::new(0)),
         },
         Receiver { 
-            inner: r,
+            inner: ReceiverInner::Channel(r),
             last_receive_time: Arc::new(AtomicU64::new(0)),
+            close: None,
         },
     )
-} 
\ No newline at end of file
+} 
+
+/// A channel whose receiver can be closed, for the slots of stages that can be
+/// rescaled
+pub(crate) fn closable_channel<T>() -> (Sender<T>, Receiver<T>) {
+    let (sender, mut receiver) = channel();
+    receiver.close = Some(Arc::new(CloseSignal::new()));
+    (sender, receiver)
+}
@@ -0,0 +1,115 @@
+use super::message::Message;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+
+/// Turns messages into bytes and back, for anything that persists or transmits
+/// them, e.g. replay logs. The whole message is encoded, including timestamps,
+/// source and aggregate metadata.
+///
+/// Bytes must be decoded with the codec that encoded them.
+pub trait Codec: Send + Sync + 'static {
+    /// Short name of the format, e.g. for file extensions and error messages
+    fn name(&self) -> &'static str;
+
+    fn encode<T: Serialize>(&self, msg: &Message<T>) -> Result<Vec<u8>, CodecError>;
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<Message<T>, CodecError>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodecError {
+    Encode { codec: &'static str, message: String },
+    Decode { codec: &'static str, message: String },
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Encode { codec, message } => write!(f, "failed to encode message as {}: {}", codec, message),
+            CodecError::Decode { codec, message } => write!(f, "failed to decode {} message: {}", codec, message),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl From<CodecError> for std::io::Error {
+    fn from(e: CodecError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Encodes a payload on its own, without the message metadata, for protocols
+/// that carry plain text: strings as they are, anything else as JSON
+pub(crate) fn payload_bytes<T: Serialize>(payload: &T) -> serde_json::Result<Vec<u8>> {
+    Ok(match serde_json::to_value(payload)? {
+        serde_json::Value::String(text) => text.into_bytes(),
+        value => value.to_string().into_bytes(),
+    })
+}
+
+fn encode_error(codec: &'static str, e: impl fmt::Display) -> CodecError {
+    CodecError::Encode { codec, message: e.to_string() }
+}
+
+fn decode_error(codec: &'static str, e: impl fmt::Display) -> CodecError {
+    CodecError::Decode { codec, message: e.to_string() }
+}
+
+/// Human readable, one JSON object per message
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn encode<T: Serialize>(&self, msg: &Message<T>) -> Result<Vec<u8>, CodecError> {
+        serde_json::to_vec(msg).map_err(|e| encode_error(self.name(), e))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<Message<T>, CodecError> {
+        serde_json::from_slice(bytes).map_err(|e| decode_error(self.name(), e))
+    }
+}
+
+/// Compact binary encoding, also used by the Bluesky firehose
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborCodec;
+
+impl Codec for CborCodec {
+    fn name(&self) -> &'static str {
+        "cbor"
+    }
+
+    fn encode<T: Serialize>(&self, msg: &Message<T>) -> Result<Vec<u8>, CodecError> {
+        let mut bytes = Vec::new();
+        ciborium::into_writer(msg, &mut bytes).map_err(|e| encode_error(self.name(), e))?;
+        Ok(bytes)
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<Message<T>, CodecError> {
+        ciborium::from_reader(bytes).map_err(|e| decode_error(self.name(), e))
+    }
+}
+
+/// Compact binary encoding. Fields are encoded by name, so other MessagePack
+/// implementations can read the messages.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackCodec;
+
+impl Codec for MessagePackCodec {
+    fn name(&self) -> &'static str {
+        "msgpack"
+    }
+
+    fn encode<T: Serialize>(&self, msg: &Message<T>) -> Result<Vec<u8>, CodecError> {
+        rmp_serde::to_vec_named(msg).map_err(|e| encode_error(self.name(), e))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<Message<T>, CodecError> {
+        rmp_serde::from_slice(bytes).map_err(|e| decode_error(self.name(), e))
+    }
+}
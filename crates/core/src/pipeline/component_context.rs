@@ -1,11 +1,44 @@
 use super::channel::{Sender, Receiver};
+use super::clock::{Clock, SystemClock};
+use std::sync::{Arc, RwLock};
 
+/// What a component gets to see of the stage it runs in. Build one with
+/// `ComponentContext::new`, the struct may gain fields.
+#[non_exhaustive]
 pub struct ComponentContext<Input, Output> {
     pub output_senders: Vec<Sender<Output>>,
     pub input_receivers: Vec<Receiver<Input>>,
+    pub(crate) routing: Arc<RwLock<Vec<Sender<Output>>>>,
+    pub(crate) clock: Arc<dyn Clock>,
 }
 
 impl<Input, Output> ComponentContext<Input, Output> {
+    pub fn new(output_senders: Vec<Sender<Output>>, input_receivers: Vec<Receiver<Input>>) -> Self {
+        let routing = Arc::new(RwLock::new(output_senders.clone()));
+        ComponentContext {
+            output_senders,
+            input_receivers,
+            routing,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub(crate) fn with_routing(routing: Arc<RwLock<Vec<Sender<Output>>>>, input_receivers: Vec<Receiver<Input>>) -> Self {
+        let output_senders = routing.read().unwrap().clone();
+        ComponentContext {
+            output_senders,
+            input_receivers,
+            routing,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Replaces the wall clock, e.g. with a `MockClock` in tests
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn get_output_senders(&self) -> &Vec<Sender<Output>> {
         &self.output_senders
     }
@@ -13,4 +46,17 @@ impl<Input, Output> ComponentContext<Input, Output> {
     pub fn get_input_receivers(&self) -> &Vec<Receiver<Input>> {
         &self.input_receivers
     }
+
+    /// Clock time based components should read the time from
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Runs `f` with the current output senders. Unlike `output_senders`, which is
+    /// fixed at deployment, these follow the next stage being rescaled while the
+    /// pipeline runs, so components distributing messages over slots should use this.
+    pub fn with_output_senders<R>(&self, f: impl FnOnce(&[Sender<Output>]) -> R) -> R {
+        let senders = self.routing.read().unwrap();
+        f(&senders)
+    }
 }
@@ -0,0 +1,61 @@
+use super::channel::{PullSource, Receiver};
+use super::message::Message;
+use super::pipeline_component::StatelessComponent;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Runs a stateless component inline on the consuming side: every `recv` pulls
+/// from the upstream receiver and applies the component until it emits a result.
+struct FusedReceiver<I, O> {
+    component: Arc<dyn StatelessComponent<I, O>>,
+    upstream: Receiver<I>,
+    pending: Mutex<VecDeque<Message<O>>>,
+}
+
+impl<I, O> FusedReceiver<I, O> {
+    /// Takes the next pending output. The lock is only held while the queue is
+    /// touched, never while waiting for the upstream receiver.
+    fn pop_pending(&self) -> Option<Message<O>> {
+        self.pending.lock().unwrap().pop_front()
+    }
+
+    fn apply(&self, msg: Message<I>) {
+        let mut outputs = Vec::new();
+        self.component.process(msg, &mut |out| outputs.push(out));
+        self.pending.lock().unwrap().extend(outputs);
+    }
+}
+
+impl<I: Send, O: Send> PullSource<O> for FusedReceiver<I, O> {
+    fn recv(&self) -> Result<Message<O>, crossbeam_channel::RecvError> {
+        loop {
+            if let Some(msg) = self.pop_pending() {
+                return Ok(msg);
+            }
+            let msg = self.upstream.recv()?;
+            self.apply(msg);
+        }
+    }
+
+    fn try_recv(&self) -> Result<Message<O>, crossbeam_channel::TryRecvError> {
+        loop {
+            if let Some(msg) = self.pop_pending() {
+                return Ok(msg);
+            }
+            let msg = self.upstream.try_recv()?;
+            self.apply(msg);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.upstream.len() + self.pending.lock().unwrap().len()
+    }
+}
+
+pub(crate) fn fuse<I: Send + 'static, O: Send + 'static>(component: Arc<dyn StatelessComponent<I, O>>, upstream: Receiver<I>) -> Receiver<O> {
+    Receiver::from_source(Arc::new(FusedReceiver {
+        component,
+        upstream,
+        pending: Mutex::new(VecDeque::new()),
+    }))
+}
@@ -1,16 +1,64 @@
 use std::time::{SystemTime, UNIX_EPOCH};
-use serde::Serialize;
+use std::collections::BTreeSet;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Message<T> {
     pub payload: T,
     pub event_timestamp: u64,  // Unix timestamp in milliseconds
     pub ingestion_timestamp: u64,
     pub source_id: Option<String>,
+    #[serde(default)]
+    pub aggregate: Option<AggregateMetadata>,  // Set by operators that combine several messages
+    #[serde(default)]
+    pub position: Option<u64>,  // Line or record number within the source, e.g. a file
     // Add other metadata fields as needed
 }
 
+/// Lineage of a message produced by combining several input messages,
+/// e.g. the output of a `Window` or `Reduce`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AggregateMetadata {
+    pub window_start: u64,  // Unix timestamp in milliseconds
+    pub window_end: u64,
+    pub sources: BTreeSet<String>,
+    pub count: usize,
+}
+
+impl AggregateMetadata {
+    /// Folds a contributing message into the metadata. If the message is itself
+    /// an aggregate, its count and sources are carried over.
+    pub fn add<T>(&mut self, msg: &Message<T>) {
+        match &msg.aggregate {
+            Some(inner) => {
+                self.count += inner.count;
+                self.sources.extend(inner.sources.iter().cloned());
+            }
+            None => {
+                self.count += 1;
+                if let Some(source_id) = &msg.source_id {
+                    self.sources.insert(source_id.clone());
+                }
+            }
+        }
+    }
+
+    pub fn from_messages<'a, T: 'a>(messages: impl IntoIterator<Item = &'a Message<T>>) -> Self {
+        let mut metadata = AggregateMetadata::default();
+        for msg in messages {
+            metadata.add(msg);
+        }
+        metadata
+    }
+
+    pub fn with_bounds(mut self, window_start: u64, window_end: u64) -> Self {
+        self.window_start = window_start;
+        self.window_end = window_end;
+        self
+    }
+}
+
 impl<T: fmt::Display> fmt::Display for Message<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.payload)
@@ -29,6 +77,8 @@ impl<T> Message<T> {
             event_timestamp: now,
             ingestion_timestamp: now,
             source_id: None,
+            aggregate: None,
+            position: None,
         }
     }
 
@@ -37,10 +87,27 @@ impl<T> Message<T> {
             payload: new_payload,
             event_timestamp: self.event_timestamp,
             ingestion_timestamp: self.ingestion_timestamp,
-            source_id: self.source_id.clone(),
+            source_id: self.source_id,
+            aggregate: self.aggregate,
+            position: self.position,
         }
     }
 
+    /// Separates the payload from the metadata, so the metadata can be reused for
+    /// several new payloads with `with_new_payload`.
+    pub fn into_parts(self) -> (T, Message<()>) {
+        let payload = self.payload;
+        let metadata = Message {
+            payload: (),
+            event_timestamp: self.event_timestamp,
+            ingestion_timestamp: self.ingestion_timestamp,
+            source_id: self.source_id,
+            aggregate: self.aggregate,
+            position: self.position,
+        };
+        (payload, metadata)
+    }
+
     pub fn with_event_time(payload: T, event_time: u64) -> Self {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -52,11 +119,62 @@ impl<T> Message<T> {
             event_timestamp: event_time,
             ingestion_timestamp: now,
             source_id: None,
+            aggregate: None,
+            position: None,
         }
     }
 
+    /// Creates a message combining several inputs. The event time is the latest
+    /// event time of the contributing messages and the source is kept only when
+    /// all of them came from the same source.
+    pub fn with_aggregate(payload: T, event_time: u64, aggregate: AggregateMetadata) -> Self {
+        let mut msg = Message::with_event_time(payload, event_time);
+        if aggregate.sources.len() == 1 {
+            msg.source_id = aggregate.sources.iter().next().cloned();
+        }
+        msg.aggregate = Some(aggregate);
+        msg
+    }
+
     pub fn with_source(mut self, source_id: impl Into<String>) -> Self {
         self.source_id = Some(source_id.into());
         self
     }
-} 
\ No newline at end of file
+
+    pub fn with_position(mut self, position: u64) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    /// Replaces `{source}`, `{position}` and `{event_time}` in `template` with the
+    /// message's metadata, and missing metadata with `unknown`. The template is
+    /// rendered in one pass, so placeholders within the metadata are kept as is.
+    pub(crate) fn render(&self, template: &str) -> String {
+        let position = self.position.map(|position| position.to_string());
+        let event_time = self.event_timestamp.to_string();
+        let placeholders = [
+            ("{source}", self.source_id.as_deref().unwrap_or("unknown")),
+            ("{position}", position.as_deref().unwrap_or("unknown")),
+            ("{event_time}", event_time.as_str()),
+        ];
+
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            rest = &rest[start..];
+            match placeholders.iter().find(|(placeholder, _)| rest.starts_with(placeholder)) {
+                Some((placeholder, value)) => {
+                    rendered.push_str(value);
+                    rest = &rest[placeholder.len()..];
+                }
+                None => {
+                    rendered.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+}
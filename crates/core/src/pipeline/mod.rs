@@ -1,12 +1,19 @@
 pub mod channel;
+pub mod clock;
+pub mod codec;
 pub mod component_context;
 pub mod message;
 pub mod pipeline_component;
 pub mod pipeline_task;
 pub mod pipeline_monitor;
+pub mod slot_scaler;
+pub(crate) mod fusion;
 pub use channel::{Sender, Receiver};
-pub use message::Message;
+pub use message::{Message, AggregateMetadata};
+pub use clock::{Clock, SystemClock, MockClock};
+pub use codec::{Codec, CodecError, JsonCodec, CborCodec, MessagePackCodec};
 pub use component_context::ComponentContext;
-pub use pipeline_component::PipelineComponent;
+pub use pipeline_component::{PipelineComponent, StatelessComponent};
 pub use pipeline_task::PipelineTask;
-pub use pipeline_monitor::PipelineMonitor;
\ No newline at end of file
+pub use pipeline_monitor::PipelineMonitor;
+pub use slot_scaler::{SlotScaler, AutoscalePolicy, ScaleError};
\ No newline at end of file
@@ -1,4 +1,5 @@
 use super::channel::{Sender, Receiver};
+use super::message::Message;
 use std::sync::Arc;
 use super::component_context::ComponentContext;
 
@@ -9,5 +10,16 @@ pub trait PipelineComponent: Send + Sync + 'static {
     fn new() -> Self;
     fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) 
         -> impl std::future::Future<Output = ()> + Send;
+
+    /// Stateless components return themselves here, which allows the pipeline to
+    /// fuse adjacent stateless stages into one task.
+    fn into_stateless(self: Arc<Self>) -> Option<Arc<dyn StatelessComponent<Self::Input, Self::Output>>> {
+        None
+    }
 }
 
+/// A component that handles every message independently of the others.
+pub trait StatelessComponent<I, O>: Send + Sync {
+    /// Handles a single message, passing results to `emit`.
+    fn process(&self, msg: Message<I>, emit: &mut dyn FnMut(Message<O>));
+}
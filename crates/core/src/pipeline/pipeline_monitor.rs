@@ -11,6 +11,12 @@ pub struct PipelineMonitor {
     is_monitoring: bool,
 }
 
+impl Default for PipelineMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PipelineMonitor {
     pub fn new() -> Self {
         Self {
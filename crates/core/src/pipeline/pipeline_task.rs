@@ -1,22 +1,30 @@
 use super::channel::{Sender, Receiver};
 
 use std::ops::BitOr;
-use tokio::task::JoinHandle;
+use tokio::task::{AbortHandle, JoinHandle};
 use tracing::{debug, error};
-use std::sync::{Arc, Mutex};
-use super::pipeline_component::PipelineComponent;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex, RwLock};
+use super::pipeline_component::{PipelineComponent, StatelessComponent};
 use super::component_context::ComponentContext;
 use super::pipeline_monitor::{MonitoredTask, PipelineMonitor};
+use super::fusion::fuse;
+use super::slot_scaler::{spawn_slot, Deployment, ScalerState, SlotScaler, Upstream};
 
 pub struct PipelineTaskArc<T: PipelineComponent, S: PipelineComponent<Output = T::Output> = T> {
     component: Arc<T>,
     input_receivers: Vec<Receiver<T::Input>>,
     input_senders: Vec<Sender<T::Input>>,
     output_receivers: Arc<Mutex<Vec<Receiver<T::Output>>>>,
-    output_senders: Arc<Mutex<Vec<Sender<T::Output>>>>,
+    output_senders: Arc<RwLock<Vec<Sender<T::Output>>>>,
     tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    // Slots of the first stage, which `stop_sources` stops
+    sources: Arc<Mutex<Vec<AbortHandle>>>,
     slots: usize,
     combined_sources: Vec<Arc<PipelineTaskArc<S>>>,
+    fusion: bool,
+    scaler: Arc<Mutex<ScalerState<T>>>,
+    running: Arc<AtomicUsize>,
 }
 
 impl<T: PipelineComponent, S: PipelineComponent<Output = T::Output>> MonitoredTask for PipelineTaskArc<T, S> {
@@ -27,7 +35,7 @@ impl<T: PipelineComponent, S: PipelineComponent<Output = T::Output>> MonitoredTa
             .unwrap()
             .as_millis() as u64;
 
-        if let Ok(senders) = self.output_senders.lock() {
+        if let Ok(senders) = self.output_senders.read() {
             for sender in senders.iter() {
                 metrics.push(("output_senders".to_string(), sender.len(), sender.capacity().unwrap_or(0)));
                 let last_send = sender.last_send_time();
@@ -53,74 +61,131 @@ impl<T: PipelineComponent, S: PipelineComponent<Output = T::Output>> MonitoredTa
 }
 
 impl<T: PipelineComponent, S: PipelineComponent<Output = T::Output>> PipelineTaskArc<T, S> {
-    fn deploy_to_slots(&self) -> Vec<JoinHandle<()>> {
-        let mut new_tasks = Vec::new();
-
-        let output_senders = self.output_senders.lock().unwrap();
+    fn deploy_to_slots(&self, is_final: bool) {
+        // A stage nothing was connected in front of is the first stage
+        let is_first = self.scaler.lock().unwrap().upstream.is_none();
+        let mut tasks = self.tasks.lock().unwrap();
         for index in 0..self.slots {
-            let component = Arc::clone(&self.component);
-            let context = Arc::new(ComponentContext {
-                output_senders: output_senders.clone(),
-                input_receivers: self.input_receivers.clone(),
-            });
-            let default_receiver = self.input_receivers[index].clone();
-            let default_sender = output_senders[index % output_senders.len()].clone();
-            
-            let task = tokio::spawn(async move {
-                debug!("Starting pipeline task");
-                component.run(default_receiver, default_sender, context).await;
-                debug!("Pipeline task completed");
-            });
-            new_tasks.push(task);
+            let context = Arc::new(ComponentContext::with_routing(
+                self.output_senders.clone(),
+                self.input_receivers.clone(),
+            ));
+            let task = spawn_slot(
+                Arc::clone(&self.component),
+                index,
+                self.input_receivers[index].clone(),
+                context,
+                is_final,
+                self.running.clone(),
+            );
+            if is_first {
+                self.sources.lock().unwrap().push(task.abort_handle());
+            }
+            tasks.push(task);
         }
 
-        new_tasks
+        // Keep what is needed to add slots to the running stage later
+        self.scaler.lock().unwrap().deployment = Some(Deployment {
+            component: Arc::clone(&self.component),
+            input_receivers: self.input_receivers.clone(),
+            routing: Arc::downgrade(&self.output_senders),
+            is_final,
+            tasks: self.tasks.clone(),
+        });
+    }
+
+    /// Adjacent stateless stages with matching slot counts are fused: instead of a
+    /// channel and a task of its own, the source runs inline when the target pulls.
+    /// Stages with a `SlotScaler` keep their own slots so they can be rescaled.
+    /// Returns the source as a stateless component if it can be fused.
+    fn fusable_with<B: PipelineComponent<Input = T::Output>>(&self, target: &PipelineTaskArc<B>) -> Option<Arc<dyn StatelessComponent<T::Input, T::Output>>> {
+        let fusable = self.fusion
+            && target.fusion
+            && !self.scaler.lock().unwrap().scalable
+            && !target.scaler.lock().unwrap().scalable
+            && Arc::clone(&target.component).into_stateless().is_some()
+            && self.slots == target.slots
+            && self.combined_sources.is_empty();
+        if !fusable {
+            return None;
+        }
+        Arc::clone(&self.component).into_stateless()
     }
 
     fn connect_with<B: PipelineComponent<Input = T::Output>>(
         source: Arc<PipelineTaskArc<T, S>>, 
         target: Arc<PipelineTaskArc<B>>
     ) -> PipelineTaskArc<B> {
+        if let Some(stateless) = source.fusable_with(&target) {
+            debug!("Fusing stateless stage into the next stage");
+            let input_receivers = source.input_receivers.iter()
+                .map(|receiver| fuse(Arc::clone(&stateless), receiver.clone()))
+                .collect();
+
+            return PipelineTaskArc {
+                component: target.component.clone(),
+                input_receivers,
+                input_senders: target.input_senders.clone(),
+                output_receivers: target.output_receivers.clone(),
+                output_senders: target.output_senders.clone(),
+                tasks: source.tasks.clone(),
+                sources: source.sources.clone(),
+                slots: target.slots,
+                combined_sources: Vec::new(),
+                fusion: target.fusion,
+                scaler: target.scaler.clone(),
+                running: target.running.clone(),
+            };
+        }
+
         // Clear and recreate output channels
-        let mut source_senders = source.output_senders.lock().unwrap();
+        let mut source_senders = source.output_senders.write().unwrap();
         let mut source_receivers = source.output_receivers.lock().unwrap();
 
         source_senders.clear();
         source_receivers.clear();
         
+        // Slots of a stage that can be rescaled are retired by closing their input
+        let scalable = target.scaler.lock().unwrap().scalable;
         for _ in 0..target.slots {
-            let (output_s, output_r) = crate::pipeline::channel::channel();
+            let (output_s, output_r) = if scalable {
+                crate::pipeline::channel::closable_channel()
+            } else {
+                crate::pipeline::channel::channel()
+            };
             source_senders.push(output_s);
             source_receivers.push(output_r);
         }
         drop(source_senders);
         drop(source_receivers);
         
-        let mut new_tasks = source.deploy_to_slots();
+        source.deploy_to_slots(false);
         
         if !source.combined_sources.is_empty() {
             for src in &source.combined_sources {
                 let component = Arc::clone(&src.component);
-                let context = Arc::new(ComponentContext {
-                    output_senders: source.output_senders.lock().unwrap().clone(),
-                    input_receivers: src.input_receivers.clone(),
-                });
+                let context = Arc::new(ComponentContext::with_routing(
+                    source.output_senders.clone(),
+                    src.input_receivers.clone(),
+                ));
                 let default_receiver = src.input_receivers[0].clone();
-                let default_sender = source.output_senders.lock().unwrap()[0].clone();
+                let default_sender = source.output_senders.read().unwrap()[0].clone();
                 
                 let task = tokio::spawn(async move {
                     debug!("Starting pipeline task");
                     component.run(default_receiver, default_sender, context).await;
                     debug!("Pipeline task completed");
                 });
-                new_tasks.push(task);
+                source.sources.lock().unwrap().push(task.abort_handle());
+                source.tasks.lock().unwrap().push(task);
             }
         }
 
-        // Move tasks from source into new_tasks
-        if let Ok(mut tasks) = source.tasks.lock() {
-            new_tasks.extend(tasks.drain(..));
-        }
+        // The upstream routing is what a rescale of the target re-targets
+        target.scaler.lock().unwrap().upstream = Some(Upstream {
+            routing: Arc::downgrade(&source.output_senders),
+            slots: source.slots,
+        });
 
         PipelineTaskArc {
             component: target.component.clone(),
@@ -128,46 +193,41 @@ impl<T: PipelineComponent, S: PipelineComponent<Output = T::Output>> PipelineTas
             input_senders: target.input_senders.clone(),
             output_receivers: target.output_receivers.clone(),
             output_senders: target.output_senders.clone(),
-            tasks: Arc::new(Mutex::new(new_tasks)),
+            tasks: source.tasks.clone(),
+            sources: source.sources.clone(),
             slots: target.slots,
             combined_sources: Vec::new(),
+            fusion: target.fusion,
+            scaler: target.scaler.clone(),
+            running: target.running.clone(),
         }
     }
 
-    pub async fn run(&self) {
-        let mut final_tasks = Vec::new();
-        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
-        let context = Arc::new(ComponentContext {
-            output_senders: self.output_senders.lock().unwrap().clone(),
-            input_receivers: self.input_receivers.clone(),
-        });
+    /// Stops the slots of the first stage, and of sources combined into it, at
+    /// their next await. Their outputs close once dropped, so the stages after
+    /// them process what is queued and then complete as usual.
+    fn stop_sources(&self) {
+        for source in self.sources.lock().unwrap().drain(..) {
+            source.abort();
+        }
+    }
 
+    pub async fn run(&self) {
         // Spawn a task for each slot
-        for (index, input_receiver) in self.input_receivers.iter().cloned().enumerate() {
-            let component = Arc::clone(&self.component);
-            let context = Arc::clone(&context);
-            
-            let task = tokio::spawn(async move {
-                let (null_sender, _) = crate::pipeline::channel::channel();
-                
-                debug!("Running slot component {}", index);
-                component.run(input_receiver, null_sender, context).await;
-                debug!("Slot component {} completed", index);
-            });
-            final_tasks.push(task);
-        }
+        self.deploy_to_slots(true);
 
-        // Wait for previous tasks
-        for task in tasks {
-            if let Err(e) = task.await {
-                error!("Task failed: {:?}", e);
+        // Wait for all tasks, including slots added by rescaling while waiting
+        loop {
+            let tasks = std::mem::take(&mut *self.tasks.lock().unwrap());
+            if tasks.is_empty() {
+                break;
             }
-        }
-
-        // Wait for final tasks
-        for task in final_tasks {
-            if let Err(e) = task.await {
-                error!("Slot task failed: {:?}", e);
+            for task in tasks {
+                match task.await {
+                    Err(e) if e.is_cancelled() => debug!("Source stopped"),
+                    Err(e) => error!("Task failed: {:?}", e),
+                    Ok(()) => {}
+                }
             }
         }
     }
@@ -192,10 +252,14 @@ impl<T: PipelineComponent> PipelineTask<T> {
             input_receivers: vec![input_receiver],
             input_senders: vec![input_sender],
             output_receivers: Arc::new(Mutex::new(output_receivers)),
-            output_senders: Arc::new(Mutex::new(output_senders)),
+            output_senders: Arc::new(RwLock::new(output_senders)),
             tasks: Arc::new(Mutex::new(Vec::new())),
+            sources: Arc::new(Mutex::new(Vec::new())),
             slots: 1,
             combined_sources: Vec::new(),
+            fusion: true,
+            scaler: Arc::new(Mutex::new(ScalerState::new())),
+            running: Arc::new(AtomicUsize::new(0)),
         }))
     }   
 
@@ -226,10 +290,14 @@ impl<T: PipelineComponent> PipelineTask<T> {
             input_receivers,
             input_senders,
             output_receivers: Arc::new(Mutex::new(output_receivers)),
-            output_senders: Arc::new(Mutex::new(output_senders)),
+            output_senders: Arc::new(RwLock::new(output_senders)),
             tasks: Arc::new(Mutex::new(Vec::new())),
+            sources: Arc::new(Mutex::new(Vec::new())),
             slots,
             combined_sources: Vec::new(),
+            fusion: true,
+            scaler: Arc::new(Mutex::new(ScalerState::new())),
+            running: Arc::new(AtomicUsize::new(0)),
         }))
     }
 
@@ -244,11 +312,41 @@ impl<T: PipelineComponent> PipelineTask<T> {
             output_receivers: self.0.output_receivers.clone(),
             output_senders: self.0.output_senders.clone(),
             tasks: self.0.tasks.clone(),
+            sources: self.0.sources.clone(),
             slots: self.0.slots,
             combined_sources: sources.into_iter().map(|task| task.0).collect(),
+            fusion: self.0.fusion,
+            scaler: self.0.scaler.clone(),
+            running: self.0.running.clone(),
+        }))
+    }
+
+    /// Keeps this stage in a task of its own even if it could be fused with
+    /// its neighbours, e.g. to let a costly `Map` run in parallel with the next stage.
+    pub fn without_fusion(self) -> Self {
+        PipelineTask(Arc::new(PipelineTaskArc {
+            component: self.0.component.clone(),
+            input_receivers: self.0.input_receivers.clone(),
+            input_senders: self.0.input_senders.clone(),
+            output_receivers: self.0.output_receivers.clone(),
+            output_senders: self.0.output_senders.clone(),
+            tasks: self.0.tasks.clone(),
+            sources: self.0.sources.clone(),
+            slots: self.0.slots,
+            combined_sources: self.0.combined_sources.clone(),
+            fusion: false,
+            scaler: self.0.scaler.clone(),
+            running: self.0.running.clone(),
         }))
     }
 
+    /// Returns a handle for changing the slot count of this stage once the
+    /// pipeline is running. The stage is then no longer fused with its neighbours.
+    pub fn scaler(&self) -> SlotScaler<T> {
+        self.0.scaler.lock().unwrap().scalable = true;
+        SlotScaler::new(self.0.scaler.clone(), self.0.running.clone())
+    }
+
     pub fn inner(&self) -> Arc<PipelineTaskArc<T>> {
         self.0.clone()
     }
@@ -257,6 +355,14 @@ impl<T: PipelineComponent> PipelineTask<T> {
         self.0.run().await
     }
 
+    /// Stops the sources of a running pipeline, e.g. on Ctrl-C, letting the
+    /// rest of the pipeline drain: `run` returns once every stage has handled
+    /// what was already received and completed, so sinks flush and commit as
+    /// when the sources end by themselves.
+    pub fn stop_sources(&self) {
+        self.0.stop_sources()
+    }
+
     pub fn register_with_monitor(&self, monitor: &mut PipelineMonitor) {
         monitor.register_monitor(self.0.clone());
     }
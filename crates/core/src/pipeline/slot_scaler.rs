@@ -0,0 +1,291 @@
+use super::channel::{Sender, Receiver};
+use super::component_context::ComponentContext;
+use super::pipeline_component::PipelineComponent;
+use super::pipeline_monitor::MonitoredTask;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock, Weak};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScaleError {
+    /// The stage has not been deployed yet, i.e. the pipeline is not running
+    NotDeployed,
+    /// The stage, or the stage feeding it, has already completed
+    Completed,
+    /// The stage has no upstream stage routing messages to it
+    NoUpstream,
+    /// Slots receiving from the upstream stage's default senders cannot be removed
+    BelowMinimum(usize),
+}
+
+impl fmt::Display for ScaleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScaleError::NotDeployed => write!(f, "stage is not deployed"),
+            ScaleError::Completed => write!(f, "stage has completed"),
+            ScaleError::NoUpstream => write!(f, "stage has no upstream stage"),
+            ScaleError::BelowMinimum(min) => write!(f, "stage requires at least {} slots", min),
+        }
+    }
+}
+
+impl std::error::Error for ScaleError {}
+
+// Routings are held weakly: the senders in them must be dropped once the stages
+// using them complete, so that downstream stages see their input disconnect.
+
+/// Routing of the upstream stage, i.e. the senders feeding each slot of this stage
+pub(crate) struct Upstream<T> {
+    pub(crate) routing: Weak<RwLock<Vec<Sender<T>>>>,
+    pub(crate) slots: usize,
+}
+
+/// Everything needed to start more slots of an already running stage
+pub(crate) struct Deployment<T: PipelineComponent> {
+    pub(crate) component: Arc<T>,
+    pub(crate) input_receivers: Vec<Receiver<T::Input>>,
+    pub(crate) routing: Weak<RwLock<Vec<Sender<T::Output>>>>,
+    pub(crate) is_final: bool,
+    pub(crate) tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+pub(crate) struct ScalerState<T: PipelineComponent> {
+    pub(crate) upstream: Option<Upstream<T::Input>>,
+    /// Set once a `SlotScaler` was handed out, which keeps the stage out of fusion
+    pub(crate) scalable: bool,
+    pub(crate) deployment: Option<Deployment<T>>,
+}
+
+impl<T: PipelineComponent> ScalerState<T> {
+    pub(crate) fn new() -> Self {
+        ScalerState {
+            upstream: None,
+            scalable: false,
+            deployment: None,
+        }
+    }
+}
+
+/// Spawns a slot of a stage. Final stages get a sender nobody receives from.
+pub(crate) fn spawn_slot<T: PipelineComponent>(
+    component: Arc<T>,
+    index: usize,
+    receiver: Receiver<T::Input>,
+    context: Arc<ComponentContext<T::Input, T::Output>>,
+    is_final: bool,
+    running: Arc<AtomicUsize>,
+) -> JoinHandle<()> {
+    let sender = if is_final {
+        crate::pipeline::channel::channel().0
+    } else {
+        let senders = &context.output_senders;
+        senders[index % senders.len()].clone()
+    };
+
+    running.fetch_add(1, Ordering::SeqCst);
+    tokio::spawn(async move {
+        debug!("Running slot component {}", index);
+        component.run(receiver, sender, context).await;
+        debug!("Slot component {} completed", index);
+        running.fetch_sub(1, Ordering::SeqCst);
+    })
+}
+
+/// Changes the number of slots of a stage while the pipeline runs. Obtained with
+/// `PipelineTask::scaler` before the stage is connected into a pipeline.
+///
+/// Added slots receive messages as soon as the upstream stage routes to them,
+/// which requires a splitter such as `RoundRobinSplitter` in front of the stage.
+/// Removed slots stop receiving new messages, finish the ones already queued and
+/// then complete.
+pub struct SlotScaler<T: PipelineComponent> {
+    state: Arc<Mutex<ScalerState<T>>>,
+    running: Arc<AtomicUsize>,
+}
+
+impl<T: PipelineComponent> Clone for SlotScaler<T> {
+    fn clone(&self) -> Self {
+        SlotScaler {
+            state: self.state.clone(),
+            running: self.running.clone(),
+        }
+    }
+}
+
+impl<T: PipelineComponent> SlotScaler<T> {
+    pub(crate) fn new(state: Arc<Mutex<ScalerState<T>>>, running: Arc<AtomicUsize>) -> Self {
+        SlotScaler { state, running }
+    }
+
+    /// Current number of slots receiving messages
+    pub fn slots(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.deployment.as_ref().map(|d| d.input_receivers.len()).unwrap_or(0)
+    }
+
+    /// Number of slot tasks still running, including retired slots draining their queue
+    pub fn running_slots(&self) -> usize {
+        self.running.load(Ordering::SeqCst)
+    }
+
+    /// Total number of messages queued for this stage
+    pub fn queue_depth(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        state.deployment.as_ref()
+            .map(|d| d.input_receivers.iter().map(|r| r.len()).sum())
+            .unwrap_or(0)
+    }
+
+    /// Sets the number of slots. When called from within the runtime, call it from
+    /// `spawn_blocking` like the autoscaler does, see the comment there.
+    pub fn rescale(&self, slots: usize) -> Result<(), ScaleError> {
+        let mut state = self.state.lock().unwrap();
+        if state.deployment.is_none() {
+            return Err(ScaleError::NotDeployed);
+        }
+        let (upstream_routing, min_slots) = match &state.upstream {
+            Some(upstream) => (upstream.routing.clone(), upstream.slots.max(1)),
+            None => return Err(ScaleError::NoUpstream),
+        };
+        let deployment = state.deployment.as_mut().ok_or(ScaleError::NotDeployed)?;
+        if slots < min_slots {
+            return Err(ScaleError::BelowMinimum(min_slots));
+        }
+        let upstream_routing = upstream_routing.upgrade().ok_or(ScaleError::Completed)?;
+        let routing = deployment.routing.upgrade().ok_or(ScaleError::Completed)?;
+
+        let current = deployment.input_receivers.len();
+        info!("Rescaling stage from {} to {} slots", current, slots);
+
+        // Holding the routing lock guarantees no upstream send is in progress, so
+        // once it is released removed slots never receive anything new
+        let mut upstream_senders = upstream_routing.write().unwrap();
+
+        if slots > current {
+            for _ in current..slots {
+                let (sender, receiver) = crate::pipeline::channel::closable_channel();
+                upstream_senders.push(sender);
+                deployment.input_receivers.push(receiver);
+            }
+
+            let mut tasks = deployment.tasks.lock().unwrap();
+            for index in current..slots {
+                let context = Arc::new(ComponentContext::with_routing(
+                    routing.clone(),
+                    deployment.input_receivers.clone(),
+                ));
+                tasks.push(spawn_slot(
+                    deployment.component.clone(),
+                    index,
+                    deployment.input_receivers[index].clone(),
+                    context,
+                    deployment.is_final,
+                    self.running.clone(),
+                ));
+            }
+        } else {
+            upstream_senders.truncate(slots);
+            for receiver in deployment.input_receivers.drain(slots..) {
+                receiver.close();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adjusts the slot count periodically based on the queue depth of the stage
+    /// until all its slots have completed. Each slot holds a runtime worker
+    /// while it waits for input, so the stage is never scaled beyond the
+    /// runtime's worker threads, whatever `max_slots` allows.
+    pub fn autoscale(&self, policy: AutoscalePolicy) -> JoinHandle<()> {
+        let scaler = self.clone();
+        let workers = tokio::runtime::Handle::current().metrics().num_workers();
+        let max_slots = policy.max_slots.min(workers);
+        if max_slots < policy.max_slots {
+            info!("Autoscaler limited to {} slots, the number of runtime workers", max_slots);
+        }
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(policy.interval);
+            loop {
+                interval.tick().await;
+
+                let slots = scaler.slots();
+                if slots == 0 {
+                    debug!("Autoscaler waiting for the stage to be deployed");
+                    continue;
+                }
+                if scaler.running_slots() == 0 {
+                    debug!("Stage completed, stopping autoscaler");
+                    break;
+                }
+
+                let depth: usize = scaler.get_metrics().iter()
+                    .filter(|(name, _, _)| name == "input_receivers")
+                    .map(|(_, len, _)| *len)
+                    .sum();
+                let per_slot = depth / slots;
+
+                let target = if per_slot > policy.scale_up_depth && slots < max_slots {
+                    slots + 1
+                } else if per_slot < policy.scale_down_depth && slots > policy.min_slots {
+                    slots - 1
+                } else {
+                    continue;
+                };
+
+                // Slots block their worker thread while waiting for input, and a task
+                // spawned from a worker runs next on that same worker, stranding the
+                // tasks queued behind it. Spawning from the blocking pool avoids this.
+                let rescaler = scaler.clone();
+                let result = tokio::task::spawn_blocking(move || rescaler.rescale(target)).await;
+                if let Ok(Err(e)) = result {
+                    error!("Autoscaler failed to rescale to {} slots: {}", target, e);
+                }
+            }
+        })
+    }
+}
+
+impl<T: PipelineComponent> MonitoredTask for SlotScaler<T> {
+    fn get_metrics(&self) -> Vec<(String, usize, usize)> {
+        let state = self.state.lock().unwrap();
+        let mut metrics = Vec::new();
+        if let Some(deployment) = &state.deployment {
+            metrics.push(("slots".to_string(), deployment.input_receivers.len(), self.running_slots()));
+            for receiver in &deployment.input_receivers {
+                metrics.push(("input_receivers".to_string(), receiver.len(), receiver.capacity().unwrap_or(0)));
+            }
+        }
+        metrics
+    }
+}
+
+/// Thresholds are average queued messages per slot: above `scale_up_depth` a slot
+/// is added, below `scale_down_depth` one is removed, once per `interval`.
+///
+/// Slots block a runtime worker thread while waiting for input, so `max_slots`
+/// is capped at the number of workers. Slots of all stages together, scaled or
+/// not, must stay below that number for the pipeline to make progress.
+#[derive(Debug, Clone)]
+pub struct AutoscalePolicy {
+    pub min_slots: usize,
+    pub max_slots: usize,
+    pub scale_up_depth: usize,
+    pub scale_down_depth: usize,
+    pub interval: Duration,
+}
+
+impl Default for AutoscalePolicy {
+    fn default() -> Self {
+        AutoscalePolicy {
+            min_slots: 1,
+            max_slots: 8,
+            scale_up_depth: 100,
+            scale_down_depth: 1,
+            interval: Duration::from_secs(1),
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::io::{self, Read};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Starts the `Hello` frame, so other protocols are rejected early
+pub(crate) const HANDSHAKE_MAGIC: &[u8; 8] = b"FLOQRMT\x01";
+
+// Guards against allocating for a corrupt length; senders drop larger items
+pub(crate) const MAX_FRAME_LEN: u32 = 64 * 1024 * 1024;
+
+const HELLO: u8 = 0;
+const DATA: u8 = 1;
+const CREDIT: u8 = 2;
+const END: u8 = 3;
+
+/// Unit of the remote channel protocol. Each frame is a tag byte, the big-endian
+/// `u32` length of the body and the body.
+///
+/// The sender opens with `Hello`, naming its codec, and may then send one `Data`
+/// frame per credit the receiver has granted. `End` marks the end of the stream;
+/// the receiver closes the connection once it has handled it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Frame {
+    Hello { codec: String },
+    Data(Vec<u8>),
+    Credit(u32),
+    End,
+}
+
+impl Frame {
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut frame = Vec::new();
+        match self {
+            Frame::Hello { codec } => {
+                let body = [HANDSHAKE_MAGIC.as_slice(), codec.as_bytes()].concat();
+                push_frame(&mut frame, HELLO, &body);
+            }
+            Frame::Data(bytes) => push_frame(&mut frame, DATA, bytes),
+            Frame::Credit(credits) => push_frame(&mut frame, CREDIT, &credits.to_be_bytes()),
+            Frame::End => push_frame(&mut frame, END, &[]),
+        }
+        frame
+    }
+
+    fn decode(tag: u8, body: Vec<u8>) -> io::Result<Frame> {
+        match tag {
+            HELLO => {
+                let codec = body.strip_prefix(HANDSHAKE_MAGIC.as_slice())
+                    .ok_or_else(|| invalid("peer is not a floq remote sender".to_string()))?;
+                String::from_utf8(codec.to_vec())
+                    .map(|codec| Frame::Hello { codec })
+                    .map_err(|e| invalid(e.to_string()))
+            }
+            DATA => Ok(Frame::Data(body)),
+            CREDIT => body.try_into()
+                .map(|credits| Frame::Credit(u32::from_be_bytes(credits)))
+                .map_err(|_| invalid("credit frame must be 4 bytes".to_string())),
+            END => Ok(Frame::End),
+            _ => Err(invalid(format!("unknown frame tag {}", tag))),
+        }
+    }
+
+    fn body_len(header: [u8; 5]) -> io::Result<usize> {
+        let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        if len > MAX_FRAME_LEN {
+            return Err(invalid(format!("frame of {} bytes exceeds the limit", len)));
+        }
+        Ok(len as usize)
+    }
+
+    /// Reads a frame, or `None` if the connection was closed between frames
+    pub(crate) fn read(reader: &mut impl Read) -> io::Result<Option<Frame>> {
+        let mut header = [0u8; 5];
+        match reader.read_exact(&mut header) {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let mut body = vec![0u8; Frame::body_len(header)?];
+        reader.read_exact(&mut body)?;
+        Frame::decode(header[0], body).map(Some)
+    }
+
+    /// Async counterpart of `read`
+    pub(crate) async fn read_async(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Option<Frame>> {
+        let mut header = [0u8; 5];
+        match reader.read_exact(&mut header).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let mut body = vec![0u8; Frame::body_len(header)?];
+        reader.read_exact(&mut body).await?;
+        Frame::decode(header[0], body).map(Some)
+    }
+}
+
+fn push_frame(frame: &mut Vec<u8>, tag: u8, body: &[u8]) {
+    frame.push(tag);
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(body);
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
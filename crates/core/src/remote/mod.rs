@@ -0,0 +1,6 @@
+pub mod remote_sink;
+pub mod remote_source;
+mod frame;
+
+pub use remote_sink::RemoteSink;
+pub use remote_source::RemoteSource;
@@ -0,0 +1,243 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Codec, CborCodec};
+use crate::pipeline::channel::{Receiver, Sender};
+use super::frame::{Frame, MAX_FRAME_LEN};
+use serde::Serialize;
+use std::io::{self, BufWriter, Write};
+use std::marker::PhantomData;
+use std::net::{Shutdown, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Sends messages to a `RemoteSource` in another process over TCP, so that the
+/// stages after it can run on another machine or core set.
+///
+/// Messages are encoded with the sink's codec, CBOR unless set with `with_codec`,
+/// and sent only as fast as the receiving side grants credits, so a slow remote
+/// stage slows down this pipeline instead of buffering without bound.
+///
+/// The sink keeps reconnecting, waiting up to `max_backoff` between attempts, so
+/// the two processes can be started in any order. Messages already written to a
+/// connection that breaks may be lost. Each slot of the sink opens its own
+/// connection. The sink completes once the receiver has handled the end of the
+/// stream. Messages encoding to more than 64 MiB are logged and dropped.
+pub struct RemoteSink<T, C = CborCodec> {
+    address: String,
+    codec: Arc<C>,
+    max_backoff: Duration,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> RemoteSink<T> {
+    /// `address` is a `host:port` the receiving `RemoteSource` is bound to
+    pub fn connect(address: impl Into<String>) -> Self {
+        RemoteSink {
+            address: address.into(),
+            codec: Arc::new(CborCodec),
+            max_backoff: Duration::from_secs(5),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, C: Codec> RemoteSink<T, C> {
+    /// Encodes messages with `codec`; the `RemoteSource` must use the same codec
+    pub fn with_codec<D: Codec>(self, codec: D) -> RemoteSink<T, D> {
+        RemoteSink {
+            address: self.address,
+            codec: Arc::new(codec),
+            max_backoff: self.max_backoff,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+}
+
+#[derive(Default)]
+struct CreditState {
+    available: u64,
+    // The receiver closed the connection, or it broke
+    closed: bool,
+    error: Option<String>,
+}
+
+struct Credits {
+    state: Mutex<CreditState>,
+    changed: Condvar,
+}
+
+/// A connection to the receiver, with a thread reading the credits it grants
+struct Connection {
+    stream: TcpStream,
+    writer: BufWriter<TcpStream>,
+    credits: Arc<Credits>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl Connection {
+    fn open(address: &str, codec: &str) -> io::Result<Connection> {
+        let stream = TcpStream::connect(address)?;
+        stream.set_nodelay(true)?;
+
+        let credits = Arc::new(Credits {
+            state: Mutex::new(CreditState::default()),
+            changed: Condvar::new(),
+        });
+        let mut reader_stream = stream.try_clone()?;
+        let reader_credits = credits.clone();
+        let reader = std::thread::spawn(move || {
+            let error = loop {
+                match Frame::read(&mut reader_stream) {
+                    Ok(Some(Frame::Credit(credits))) => {
+                        reader_credits.state.lock().unwrap().available += credits as u64;
+                        reader_credits.changed.notify_all();
+                    }
+                    Ok(Some(frame)) => break Some(format!("unexpected frame from receiver: {:?}", frame)),
+                    Ok(None) => break None,
+                    Err(e) => break Some(e.to_string()),
+                }
+            };
+            let mut state = reader_credits.state.lock().unwrap();
+            state.closed = true;
+            state.error = error;
+            reader_credits.changed.notify_all();
+        });
+
+        let mut connection = Connection {
+            writer: BufWriter::new(stream.try_clone()?),
+            stream,
+            credits,
+            reader: Some(reader),
+        };
+        connection.writer.write_all(&Frame::Hello { codec: codec.to_string() }.encode())?;
+        Ok(connection)
+    }
+
+    /// Blocks until the receiver grants a credit and takes it
+    fn acquire_credit(&mut self) -> io::Result<()> {
+        if self.credits.state.lock().unwrap().available == 0 {
+            // The receiver only grants credits for what it has received
+            self.writer.flush()?;
+        }
+
+        let mut state = self.credits.state.lock().unwrap();
+        while state.available == 0 && !state.closed {
+            state = self.credits.changed.wait(state).unwrap();
+        }
+        if state.closed {
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "receiver closed the connection"));
+        }
+        state.available -= 1;
+        Ok(())
+    }
+
+    /// Sends the input until it completes, then ends the stream. `pending` holds
+    /// a frame that could not be sent, to be sent first on the next connection.
+    fn stream<T: Serialize, C: Codec>(&mut self, input: &Receiver<T>, codec: &C, pending: &mut Option<Vec<u8>>) -> io::Result<()> {
+        loop {
+            let frame = match pending.take() {
+                Some(frame) => frame,
+                None => match input.recv() {
+                    Ok(msg) => match codec.encode(&msg) {
+                        // The receiver would drop the connection, and the frame
+                        // would be sent again on every reconnect
+                        Ok(bytes) if bytes.len() > MAX_FRAME_LEN as usize => {
+                            error!("Dropping item of {} bytes, larger than the {} bytes a frame may carry", bytes.len(), MAX_FRAME_LEN);
+                            continue;
+                        }
+                        Ok(bytes) => Frame::Data(bytes).encode(),
+                        Err(e) => {
+                            error!("Failed to encode item: {}", e);
+                            continue;
+                        }
+                    },
+                    Err(_) => break,
+                },
+            };
+
+            if let Err(e) = self.acquire_credit().and_then(|_| self.writer.write_all(&frame)) {
+                *pending = Some(frame);
+                return Err(e);
+            }
+            if input.is_empty() {
+                self.writer.flush()?;
+            }
+        }
+
+        self.writer.write_all(&Frame::End.encode())?;
+        self.writer.flush()?;
+
+        // The receiver closes the connection once it has handled everything
+        let mut state = self.credits.state.lock().unwrap();
+        while !state.closed {
+            state = self.credits.changed.wait(state).unwrap();
+        }
+        match state.error.take() {
+            Some(e) => Err(io::Error::new(io::ErrorKind::ConnectionAborted, e)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        let _ = self.stream.shutdown(Shutdown::Both);
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+fn send_all<T: Serialize, C: Codec>(address: &str, codec: &C, max_backoff: Duration, input: Receiver<T>) {
+    let mut pending = None;
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        let result = Connection::open(address, codec.name()).and_then(|mut connection| {
+            debug!("RemoteSink connected to {}", address);
+            backoff = INITIAL_BACKOFF;
+            connection.stream(&input, codec, &mut pending)
+        });
+
+        match result {
+            Ok(()) => return,
+            Err(e) => {
+                warn!("Connection to {} failed: {}, retrying in {:?}", address, e, backoff);
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(max_backoff);
+            }
+        }
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static, C: Codec> PipelineComponent for RemoteSink<T, C> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("RemoteSink requires an address. Use RemoteSink::connect() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("RemoteSink starting");
+
+        let address = self.address.clone();
+        let codec = self.codec.clone();
+        let max_backoff = self.max_backoff;
+        let result = tokio::task::spawn_blocking(move || {
+            send_all(&address, codec.as_ref(), max_backoff, input)
+        }).await;
+        if let Err(e) = result {
+            error!("RemoteSink failed: {:?}", e);
+        }
+
+        debug!("RemoteSink completed");
+    }
+}
@@ -0,0 +1,225 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Codec, CborCodec};
+use crate::pipeline::channel::{Receiver, Sender};
+use super::frame::Frame;
+use serde::de::DeserializeOwned;
+use std::io;
+use std::marker::PhantomData;
+use std::sync::atomic::{self, AtomicBool, AtomicU64};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tracing::{debug, error, warn};
+
+// How often the receiver checks whether it can grant more credits
+const CREDIT_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Receives messages sent by `RemoteSink`s in other processes and emits them
+/// with their original timestamps and source.
+///
+/// Each connected sink may have at most `window` messages on the wire or queued
+/// for the next stage; it is granted more as the next stage catches up. The
+/// source completes once `senders` sinks, one per slot of the sending stage,
+/// have ended their streams. Only one slot of the source listens.
+pub struct RemoteSource<T, C = CborCodec> {
+    address: String,
+    codec: Arc<C>,
+    window: u32,
+    senders: usize,
+    claimed: AtomicBool,
+    listener: Mutex<Option<std::net::TcpListener>>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> RemoteSource<T> {
+    /// Listens on `address`, e.g. `0.0.0.0:7000`
+    pub fn bind(address: impl Into<String>) -> Self {
+        RemoteSource {
+            address: address.into(),
+            codec: Arc::new(CborCodec),
+            window: 1024,
+            senders: 1,
+            claimed: AtomicBool::new(false),
+            listener: Mutex::new(None),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Accepts senders on an already bound listener, e.g. one bound to port 0
+    /// whose address is handed to the senders before the source runs. Senders
+    /// connecting early wait in the listener's backlog.
+    pub fn from_listener(listener: std::net::TcpListener) -> io::Result<Self> {
+        let address = listener.local_addr()?.to_string();
+        listener.set_nonblocking(true)?;
+        let source = RemoteSource::bind(address);
+        *source.listener.lock().unwrap() = Some(listener);
+        Ok(source)
+    }
+}
+
+impl<T, C: Codec> RemoteSource<T, C> {
+    /// Decodes messages with `codec`; the `RemoteSink`s must use the same codec
+    pub fn with_codec<D: Codec>(self, codec: D) -> RemoteSource<T, D> {
+        RemoteSource {
+            address: self.address,
+            codec: Arc::new(codec),
+            window: self.window,
+            senders: self.senders,
+            claimed: self.claimed,
+            listener: self.listener,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_window(mut self, window: u32) -> Self {
+        assert!(window > 0, "RemoteSource window must be positive");
+        self.window = window;
+        self
+    }
+
+    /// Number of sinks whose streams must end before the source completes
+    pub fn with_senders(mut self, senders: usize) -> Self {
+        self.senders = senders;
+        self
+    }
+}
+
+/// Grants the sender credits for the room left in the window, until aborted
+async fn grant_credits<T: Send + 'static>(mut writer: OwnedWriteHalf, output: Sender<T>, received: Arc<AtomicU64>, window: u32) {
+    let window = window as u64;
+    let mut granted = 0u64;
+    let mut interval = tokio::time::interval(CREDIT_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        let in_flight = granted.saturating_sub(received.load(atomic::Ordering::SeqCst));
+        let free = window.saturating_sub(in_flight + output.len() as u64);
+
+        // Credits are granted in batches, unless the sender has run out
+        if free > 0 && (free >= window / 2 || in_flight == 0) {
+            if writer.write_all(&Frame::Credit(free as u32).encode()).await.is_err() {
+                return;
+            }
+            granted += free;
+        }
+    }
+}
+
+/// Emits what one sender sends, returning whether it ended its stream
+async fn receive<T: DeserializeOwned + Send + 'static, C: Codec>(stream: TcpStream, output: Sender<T>, codec: Arc<C>, window: u32) -> bool {
+    let peer = stream.peer_addr().map(|peer| peer.to_string()).unwrap_or_default();
+    let (reader, writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    match Frame::read_async(&mut reader).await {
+        Ok(Some(Frame::Hello { codec: name })) if name == codec.name() => {}
+        Ok(Some(Frame::Hello { codec: name })) => {
+            error!("Sender {} uses codec {}, expected {}", peer, name, codec.name());
+            return false;
+        }
+        Ok(_) => {
+            warn!("Sender {} did not start with a handshake", peer);
+            return false;
+        }
+        Err(e) => {
+            warn!("Handshake with {} failed: {}", peer, e);
+            return false;
+        }
+    }
+    debug!("RemoteSource accepted sender {}", peer);
+
+    let received = Arc::new(AtomicU64::new(0));
+    let credits = tokio::spawn(grant_credits(writer, output.clone(), received.clone(), window));
+
+    let ended = loop {
+        match Frame::read_async(&mut reader).await {
+            Ok(Some(Frame::Data(bytes))) => {
+                received.fetch_add(1, atomic::Ordering::SeqCst);
+                match codec.decode::<T>(&bytes) {
+                    Ok(msg) => {
+                        if let Err(e) = output.send(msg) {
+                            error!("Failed to send received item: {}", e);
+                            break false;
+                        }
+                    }
+                    Err(e) => error!("Failed to decode item from {}: {}", peer, e),
+                }
+            }
+            Ok(Some(Frame::End)) => break true,
+            Ok(Some(frame)) => {
+                warn!("Unexpected frame from {}: {:?}", peer, frame);
+                break false;
+            }
+            Ok(None) => {
+                warn!("Sender {} disconnected", peer);
+                break false;
+            }
+            Err(e) => {
+                warn!("Connection to {} failed: {}", peer, e);
+                break false;
+            }
+        }
+    };
+
+    // Dropping the writer closes the connection, telling the sender all is handled
+    credits.abort();
+    let _ = credits.await;
+    ended
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static, C: Codec> PipelineComponent for RemoteSource<T, C> {
+    type Input = ();
+    type Output = T;
+
+    fn new() -> Self {
+        panic!("RemoteSource requires an address. Use RemoteSource::bind() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("RemoteSource starting");
+
+        if self.claimed.swap(true, atomic::Ordering::SeqCst) {
+            debug!("RemoteSource is listening in another slot");
+            return;
+        }
+
+        let bound = self.listener.lock().unwrap().take();
+        let listener = match bound {
+            Some(listener) => TcpListener::from_std(listener),
+            None => TcpListener::bind(&self.address).await,
+        };
+        let listener = match listener {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to listen on {}: {}", self.address, e);
+                return;
+            }
+        };
+        debug!("RemoteSource listening on {}", self.address);
+
+        let mut connections = JoinSet::new();
+        let mut ended = 0;
+        while ended < self.senders {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        if let Err(e) = stream.set_nodelay(true) {
+                            warn!("Failed to disable Nagle's algorithm: {}", e);
+                        }
+                        connections.spawn(receive(stream, output.clone(), self.codec.clone(), self.window));
+                    }
+                    Err(e) => warn!("Failed to accept connection: {}", e),
+                },
+                Some(result) = connections.join_next() => {
+                    if matches!(result, Ok(true)) {
+                        ended += 1;
+                    }
+                }
+            }
+        }
+
+        debug!("RemoteSource completed");
+    }
+}
@@ -0,0 +1,50 @@
+use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::channel::{Receiver, Sender};
+use tracing::{debug, error};
+use std::sync::Arc;
+use std::marker::PhantomData;
+
+/// Sends every item to all output slots. Each slot but the last receives a clone,
+/// so fan-out of large payloads is cheapest with `Arc<T>` or `Bytes` payloads.
+pub struct Broadcaster<T: Clone + Send + Sync + 'static> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Clone + Send + Sync + 'static> PipelineComponent for Broadcaster<T> {
+    type Input = T;
+    type Output = T;
+
+    fn new() -> Self {
+        Broadcaster {
+            _phantom: PhantomData,
+        }
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("Broadcaster starting");
+        debug!("Number of output senders: {}", context.output_senders.len());
+
+        assert!(!context.output_senders.is_empty(), "Broadcaster requires at least one output sender");
+        assert!(context.input_receivers.len() == 1, "Broadcaster requires exactly one input receiver");
+
+        while let Ok(item) = input.recv() {
+            // Senders are looked up per item, as the next stage may be rescaled
+            let result = context.with_output_senders(|output_senders| {
+                let (last, rest) = output_senders.split_last().unwrap();
+                for (index, sender) in rest.iter().enumerate() {
+                    sender.send(item.clone()).map_err(|e| (index, e))?;
+                }
+                last.send(item).map_err(|e| (rest.len(), e))?;
+                debug!("Broadcast item to {} outputs", output_senders.len());
+                Ok(())
+            });
+
+            if let Err((index, e)) = result {
+                error!("Failed to send to output {}: {:?}", index, e);
+                break;
+            }
+        }
+
+        debug!("Broadcaster completed");
+    }
+}
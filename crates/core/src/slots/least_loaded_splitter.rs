@@ -0,0 +1,66 @@
+use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::channel::{Receiver, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{debug, error};
+use std::sync::Arc;
+use std::marker::PhantomData;
+
+/// Sends each item to the output slot with the fewest queued messages, so a slot
+/// stuck on an expensive message stops receiving work while the others drain.
+/// Ties are broken round robin.
+///
+/// Only queued messages are counted: a slot busy with a message but with an empty
+/// queue looks idle, so at most one more message ends up waiting behind a slow one.
+pub struct LeastLoadedSplitter<T: Send + Sync + 'static> {
+    next_index: AtomicUsize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Send + Sync + 'static> LeastLoadedSplitter<T> {
+    fn select(&self, output_senders: &[Sender<T>]) -> usize {
+        let start = self.next_index.fetch_add(1, Ordering::SeqCst) % output_senders.len();
+        (0..output_senders.len())
+            .map(|offset| (start + offset) % output_senders.len())
+            .min_by_key(|index| output_senders[*index].len())
+            .unwrap()
+    }
+}
+
+impl<T: Send + Sync + 'static> PipelineComponent for LeastLoadedSplitter<T> {
+    type Input = T;
+    type Output = T;
+
+    fn new() -> Self {
+        LeastLoadedSplitter {
+            next_index: AtomicUsize::new(0),
+            _phantom: PhantomData,
+        }
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("LeastLoadedSplitter starting");
+        debug!("Number of output senders: {}", context.output_senders.len());
+
+        assert!(context.output_senders.len() > 1, "LeastLoadedSplitter requires more than one output sender");
+        assert!(context.input_receivers.len() == 1, "LeastLoadedSplitter requires exactly one input receiver");
+
+        while let Ok(item) = input.recv() {
+            // Senders are looked up per item, as the next stage may be rescaled
+            let result = context.with_output_senders(|output_senders| {
+                let index = self.select(output_senders);
+                debug!("Sending to output {} with {} queued", index, output_senders[index].len());
+                output_senders[index].send(item).map_err(|e| (index, e))
+            });
+
+            match result {
+                Ok(()) => debug!("Successfully sent to output"),
+                Err((index, e)) => {
+                    error!("Failed to send to output {}: {:?}", index, e);
+                    break;
+                }
+            }
+        }
+
+        debug!("LeastLoadedSplitter completed");
+    }
+}
@@ -1,40 +1,237 @@
-use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::{PipelineComponent, ComponentContext, Message, Clock};
 use crate::pipeline::channel::{Receiver, Sender};
+use crossbeam_channel::RecvTimeoutError;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::marker::PhantomData;
-use tracing::{debug, error};
+use std::sync::atomic::{self, AtomicBool};
+use std::time::Duration;
+use tracing::{debug, error, warn};
 use std::sync::Arc;
 
+/// Merges all input slots into a single output. Only one slot of the merger stage
+/// reads the inputs, so the merged stream is a single ordered sequence.
+///
+/// With `with_ordered(true)` messages are emitted in `event_timestamp` order. Each
+/// input is expected to be in event time order itself; a message is held back
+/// until every other input has reached its event time, i.e. the watermark. Inputs
+/// that have sent nothing for `idle_timeout` do not hold back the watermark, so a
+/// quiet stream does not stall the others. Messages arriving behind the
+/// watermark are emitted immediately, out of order. At most `max_pending`
+/// messages are held back; beyond that the earliest is emitted ahead of the
+/// watermark.
 pub struct Merger<T: Send + Sync + 'static> {
+    ordered: bool,
+    idle_timeout: Duration,
+    max_pending: usize,
+    claimed: AtomicBool,
     _phantom: PhantomData<T>,
 }
 
+struct Input {
+    watermark: u64,
+    last_active: u64,
+    done: bool,
+}
+
+/// What the readers of the inputs pass on to the merging loop
+enum Event<T> {
+    Received(usize, Message<T>),
+    Completed(usize),
+}
+
+/// Heap entry ordered by event time, then arrival, reversed to make a min-heap
+struct Pending<T> {
+    msg: Message<T>,
+    seq: u64,
+}
+
+impl<T> PartialEq for Pending<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Pending<T> {}
+
+impl<T> PartialOrd for Pending<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Pending<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (other.msg.event_timestamp, other.seq).cmp(&(self.msg.event_timestamp, self.seq))
+    }
+}
+
+impl<T: Send + Sync + 'static> Merger<T> {
+    pub fn with_ordered(mut self, ordered: bool) -> Self {
+        self.ordered = ordered;
+        self
+    }
+
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Bounds the messages held back waiting for the watermark, 10 000 by default
+    pub fn with_max_pending(mut self, max_pending: usize) -> Self {
+        self.max_pending = max_pending;
+        self
+    }
+
+    fn is_active(&self, input: &Input, now: u64) -> bool {
+        !input.done && now.saturating_sub(input.last_active) < self.idle_timeout.as_millis() as u64
+    }
+
+    /// Lowest event time every active input has reached
+    fn watermark(&self, inputs: &[Input], now: u64) -> u64 {
+        inputs.iter()
+            .filter(|input| self.is_active(input, now))
+            .map(|input| input.watermark)
+            .min()
+            .unwrap_or(u64::MAX)
+    }
+
+    /// How long until an input holding back the earliest pending message goes
+    /// idle, which releases it without anything being received
+    fn idle_wait(&self, inputs: &[Input], heap: &BinaryHeap<Pending<T>>, now: u64) -> Option<Duration> {
+        let earliest = heap.peek()?.msg.event_timestamp;
+        let idle_timeout = self.idle_timeout.as_millis() as u64;
+        inputs.iter()
+            .filter(|input| self.is_active(input, now) && input.watermark < earliest)
+            .map(|input| (input.last_active + idle_timeout).saturating_sub(now).max(1))
+            .min()
+            .map(Duration::from_millis)
+    }
+
+    /// Emits the pending messages the watermark has passed. Returns false once
+    /// the output is gone.
+    fn emit_ready(&self, inputs: &[Input], heap: &mut BinaryHeap<Pending<T>>, output: &Sender<T>, now: u64) -> bool {
+        let watermark = self.watermark(inputs, now);
+        while let Some(pending) = heap.peek() {
+            if pending.msg.event_timestamp > watermark && heap.len() <= self.max_pending {
+                break;
+            }
+            if pending.msg.event_timestamp > watermark {
+                warn!("Merger holds {} messages, emitting ahead of the watermark", heap.len());
+            }
+            let pending = heap.pop().unwrap();
+            if let Err(e) = output.send(pending.msg) {
+                error!("Failed to send merged item: {:?}", e);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn merge(&self, input_receivers: &[Receiver<T>], output: &Sender<T>, clock: &dyn Clock) {
+        // Each input is read by a blocking reader, so the merging loop only wakes
+        // up when something arrives on any of them
+        let (events_sender, events) = crossbeam_channel::unbounded();
+        for (index, receiver) in input_receivers.iter().enumerate() {
+            let (receiver, events) = (receiver.clone(), events_sender.clone());
+            tokio::task::spawn_blocking(move || {
+                while let Ok(msg) = receiver.recv() {
+                    if events.send(Event::Received(index, msg)).is_err() {
+                        return;
+                    }
+                }
+                let _ = events.send(Event::Completed(index));
+            });
+        }
+        drop(events_sender);
+
+        let started = clock.now_millis();
+        let mut inputs: Vec<Input> = input_receivers.iter()
+            .map(|_| Input {
+                watermark: 0,
+                last_active: started,
+                done: false,
+            })
+            .collect();
+        let mut heap = BinaryHeap::new();
+        let mut seq = 0;
+
+        while inputs.iter().any(|input| !input.done) {
+            let event = match self.idle_wait(&inputs, &heap, clock.now_millis()) {
+                Some(wait) => events.recv_timeout(wait),
+                None => events.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+
+            match event {
+                Ok(Event::Received(index, msg)) => {
+                    debug!("Merger received item");
+                    let input = &mut inputs[index];
+                    input.watermark = input.watermark.max(msg.event_timestamp);
+                    input.last_active = clock.now_millis();
+
+                    if self.ordered {
+                        heap.push(Pending { msg, seq });
+                        seq += 1;
+                    } else if let Err(e) = output.send(msg) {
+                        error!("Failed to send merged item: {:?}", e);
+                        return;
+                    }
+                }
+                Ok(Event::Completed(index)) => {
+                    debug!("Merger input completed");
+                    inputs[index].done = true;
+                }
+                // An input holding back the watermark went idle
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            if !self.emit_ready(&inputs, &mut heap, output, clock.now_millis()) {
+                return;
+            }
+        }
+
+        // All inputs completed, nothing can arrive ahead of what is left
+        while let Some(pending) = heap.pop() {
+            if let Err(e) = output.send(pending.msg) {
+                error!("Failed to send merged item: {:?}", e);
+                return;
+            }
+        }
+    }
+}
+
 impl<T: Send + Sync + 'static> PipelineComponent for Merger<T> {
     type Input = T;
     type Output = T;
 
     fn new() -> Self {
-        Merger {    
+        Merger {
+            ordered: false,
+            idle_timeout: Duration::from_secs(5),
+            max_pending: 10_000,
+            claimed: AtomicBool::new(false),
             _phantom: PhantomData,
         }
     }
 
-    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
         debug!("Merger starting");
         let input_receivers = &context.input_receivers;
         let output_senders = &context.output_senders;
 
         assert!(input_receivers.len() > 1, "Merger requires more than one input receiver");
         assert!(output_senders.len() == 1, "Merger requires exactly one output sender");
-        
-        while let Ok(item) = input.recv() {
-            debug!("Merger received item"); 
-            if let Err(e) = output_senders[0].send(item) {
-                error!("Failed to send merged item: {:?}", e);
-                break;
-            }
-            debug!("Merger forwarded item");
+
+        if self.claimed.swap(true, atomic::Ordering::SeqCst) {
+            debug!("Merger inputs are read by another slot");
+            return;
         }
-    
+
+        self.merge(input_receivers, &output, context.clock());
+
+        // The inputs are released, so the merger can be run again
+        self.claimed.store(false, atomic::Ordering::SeqCst);
         debug!("Merger completed");
     }
 }
@@ -1,5 +1,9 @@
 pub mod round_robin_splitter;
+pub mod least_loaded_splitter;
 pub mod merger;
+pub mod broadcaster;
 
 pub use round_robin_splitter::RoundRobinSplitter;
-pub use merger::Merger;
\ No newline at end of file
+pub use least_loaded_splitter::LeastLoadedSplitter;
+pub use merger::Merger;
+pub use broadcaster::Broadcaster;
\ No newline at end of file
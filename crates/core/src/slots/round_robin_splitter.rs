@@ -23,22 +23,26 @@ impl<T: Send + Sync + 'static> PipelineComponent for RoundRobinSplitter<T> {
 
     async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
         debug!("RoundRobinSplitter starting");
-        let output_senders = &context.output_senders;
-        debug!("Number of output senders: {}", output_senders.len());
+        debug!("Number of output senders: {}", context.output_senders.len());
 
-        assert!(output_senders.len() > 1, "RoundRobinSplitter requires more than one output sender");
+        assert!(context.output_senders.len() > 1, "RoundRobinSplitter requires more than one output sender");
         assert!(context.input_receivers.len() == 1, "RoundRobinSplitter requires exactly one input receiver");
         
         while let Ok(item) = input.recv() {
-            let index = self.current_index.fetch_add(1, Ordering::SeqCst) % output_senders.len();
-            debug!("Sending to output {}", index);
-            
-            if let Err(e) = output_senders[index].send(item) {
-                error!("Failed to send to output {}: {:?}", index, e);
-                break;
+            // Senders are looked up per item, as the next stage may be rescaled
+            let result = context.with_output_senders(|output_senders| {
+                let index = self.current_index.fetch_add(1, Ordering::SeqCst) % output_senders.len();
+                debug!("Sending to output {}", index);
+                output_senders[index].send(item).map_err(|e| (index, e))
+            });
+
+            match result {
+                Ok(()) => debug!("Successfully sent to output"),
+                Err((index, e)) => {
+                    error!("Failed to send to output {}: {:?}", index, e);
+                    break;
+                }
             }
-            
-            debug!("Successfully sent to output {}", index);
         }
         
         debug!("RoundRobinSplitter completed");
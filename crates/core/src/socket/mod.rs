@@ -0,0 +1,244 @@
+pub mod tcp;
+pub mod udp;
+#[cfg(unix)]
+pub mod unix;
+
+pub use tcp::{TcpSource, TcpSink};
+pub use udp::{UdpSource, UdpSink};
+#[cfg(unix)]
+pub use unix::{UnixSocketSource, UnixSocketSink};
+
+use crate::pipeline::Message;
+use crate::pipeline::channel::{Receiver, Sender};
+use crate::pipeline::codec::payload_bytes;
+use crossbeam_channel::TryRecvError;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io::{self, BufWriter, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How messages are delimited on a stream socket
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// Lines ending in a newline, e.g. syslog over TCP. Sinks drop payloads
+    /// that contain newlines themselves, as they would arrive split.
+    #[default]
+    Lines,
+    /// A big-endian `u32` length followed by that many bytes, as in
+    /// `LengthPrefixed` files
+    LengthPrefixed,
+}
+
+impl Framing {
+    fn frame(self, mut payload: Vec<u8>) -> io::Result<Vec<u8>> {
+        match self {
+            Framing::Lines => {
+                if payload.contains(&b'\n') {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "payload contains a newline"));
+                }
+                payload.push(b'\n');
+                Ok(payload)
+            }
+            Framing::LengthPrefixed => {
+                let len = u32::try_from(payload.len())
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "payload is longer than a frame can be"))?;
+                let mut frame = len.to_be_bytes().to_vec();
+                frame.append(&mut payload);
+                Ok(frame)
+            }
+        }
+    }
+}
+
+/// Encodes a payload into a frame, logging payloads that cannot be sent
+fn encode<T: Serialize>(msg: &Message<T>, framing: Framing) -> Option<Vec<u8>> {
+    let frame = payload_bytes(&msg.payload)
+        .map_err(io::Error::from)
+        .and_then(|payload| framing.frame(payload));
+    match frame {
+        Ok(frame) => Some(frame),
+        Err(e) => {
+            error!("Failed to encode payload: {}", e);
+            None
+        }
+    }
+}
+
+/// When a source last received something, so it can stop once idle
+pub(crate) struct Activity {
+    last: Mutex<Instant>,
+    idle_timeout: Option<Duration>,
+}
+
+impl Activity {
+    pub(crate) fn new(idle_timeout: Option<Duration>) -> Self {
+        Activity {
+            last: Mutex::new(Instant::now()),
+            idle_timeout,
+        }
+    }
+
+    pub(crate) fn touch(&self) {
+        *self.last.lock().unwrap() = Instant::now();
+    }
+
+    /// Completes once nothing has been received for the idle timeout, if any
+    pub(crate) async fn idle(&self) {
+        let Some(idle_timeout) = self.idle_timeout else {
+            return std::future::pending().await;
+        };
+        loop {
+            let deadline = *self.last.lock().unwrap() + idle_timeout;
+            if Instant::now() >= deadline {
+                return;
+            }
+            tokio::time::sleep_until(deadline).await;
+        }
+    }
+}
+
+/// Emits the frames read from one connection, with `peer` as their source and
+/// their number within the connection as their position. Returns false if the
+/// output closed.
+pub(crate) async fn read_frames<R: AsyncRead + Unpin>(stream: R, peer: &str, framing: Framing, max_frame_size: usize, output: &Sender<String>, activity: &Activity) -> bool {
+    let mut reader = BufReader::new(stream);
+    let mut frame = Vec::new();
+    let mut number = 0;
+
+    loop {
+        frame.clear();
+        let read = match framing {
+            Framing::Lines => {
+                let limit = max_frame_size as u64 + 1;
+                match (&mut reader).take(limit).read_until(b'\n', &mut frame).await {
+                    Ok(0) => Ok(false),
+                    Ok(_) if frame.len() > max_frame_size && !frame.ends_with(b"\n") => {
+                        Err(format!("line longer than {} bytes", max_frame_size))
+                    }
+                    Ok(_) => Ok(true),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            Framing::LengthPrefixed => match reader.read_u32().await {
+                Ok(len) if len as usize > max_frame_size => Err(format!("frame of {} bytes is longer than {} bytes", len, max_frame_size)),
+                Ok(len) => {
+                    frame.resize(len as usize, 0);
+                    reader.read_exact(&mut frame).await.map(|_| true).map_err(|e| e.to_string())
+                }
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+                Err(e) => Err(e.to_string()),
+            },
+        };
+
+        match read {
+            Ok(true) => {}
+            Ok(false) => {
+                debug!("{} disconnected", peer);
+                return true;
+            }
+            Err(e) => {
+                warn!("Dropping connection from {}: {}", peer, e);
+                return true;
+            }
+        }
+
+        number += 1;
+        activity.touch();
+        let text = String::from_utf8_lossy(&frame).trim_end_matches(['\n', '\r']).to_string();
+        if let Err(e) = output.send(Message::new(text).with_source(peer).with_position(number)) {
+            error!("Failed to send message to output: {}", e);
+            return false;
+        }
+    }
+}
+
+/// Writes the input to the connections `connect` opens, reconnecting with
+/// backoff when one breaks. Frames that were not flushed to a connection are
+/// written again on the next one. Once the input has completed, a connection
+/// that fails is not retried and the frames left are dropped.
+pub(crate) fn write_frames<T, W, F>(target: &str, connect: F, framing: Framing, max_backoff: Duration, input: Receiver<T>)
+where
+    T: Serialize,
+    W: Write,
+    F: Fn() -> io::Result<W>,
+{
+    let mut pending = VecDeque::new();
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        let result = connect().and_then(|connection| {
+            debug!("Connected to {}", target);
+            backoff = INITIAL_BACKOFF;
+            let mut writer = BufWriter::new(connection);
+            let result = write_connection(&mut writer, &mut pending, framing, &input);
+            if result.is_err() {
+                // The frames still buffered are pending, and written to the next connection
+                drop(writer.into_parts());
+            }
+            result
+        });
+
+        let Err(e) = result else {
+            return;
+        };
+        if input_completed(&input, framing, &mut pending) {
+            error!("Connection to {} failed: {}, dropping {} frames as the input has completed", target, e, pending.len());
+            return;
+        }
+        warn!("Connection to {} failed: {}, retrying in {:?}", target, e, backoff);
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(max_backoff);
+    }
+}
+
+/// Writes the pending frames and then the input to one connection, until the
+/// input completes. Frames stay pending until a flush has written them.
+fn write_connection<T: Serialize, W: Write>(writer: &mut BufWriter<W>, pending: &mut VecDeque<Vec<u8>>, framing: Framing, input: &Receiver<T>) -> io::Result<()> {
+    let mut written = 0;
+    let mut buffered = 0;
+    loop {
+        if written == pending.len() {
+            match input.recv() {
+                Ok(msg) => match encode(&msg, framing) {
+                    Some(frame) => pending.push_back(frame),
+                    None => continue,
+                },
+                Err(_) => break,
+            }
+        }
+        writer.write_all(&pending[written])?;
+        buffered += pending[written].len();
+        written += 1;
+
+        // Flushed at least once per buffer, so no more than that is written again
+        if (written == pending.len() && input.is_empty()) || buffered >= writer.capacity() {
+            writer.flush()?;
+            pending.drain(..written);
+            written = 0;
+            buffered = 0;
+        }
+    }
+    writer.flush()?;
+    pending.drain(..written);
+    Ok(())
+}
+
+/// Whether the input has completed with nothing left in it. A message taken
+/// while checking is added to `pending`, so each failed connection holds at
+/// most one more message back from the input.
+fn input_completed<T: Serialize>(input: &Receiver<T>, framing: Framing, pending: &mut VecDeque<Vec<u8>>) -> bool {
+    match input.try_recv() {
+        Ok(msg) => {
+            pending.extend(encode(&msg, framing));
+            false
+        }
+        Err(TryRecvError::Empty) => false,
+        Err(TryRecvError::Disconnected) => true,
+    }
+}
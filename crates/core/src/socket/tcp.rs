@@ -0,0 +1,171 @@
+use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::channel::{Receiver, Sender};
+use super::{read_frames, write_frames, Activity, Framing};
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tracing::{debug, error, warn};
+
+/// Listens for TCP connections, e.g. from legacy sensors or log shippers, and
+/// emits one message per line received, or per frame with
+/// `Framing::LengthPrefixed`. Messages carry the peer address as their source
+/// and the line number within the connection as their position.
+///
+/// Only one slot of the source listens. It runs until the pipeline stops,
+/// unless `with_idle_timeout` is set.
+pub struct TcpSource {
+    address: String,
+    framing: Framing,
+    max_frame_size: usize,
+    idle_timeout: Option<Duration>,
+    claimed: AtomicBool,
+}
+
+impl TcpSource {
+    /// Listens on `address`, e.g. `0.0.0.0:5140`
+    pub fn bind(address: impl Into<String>) -> Self {
+        TcpSource {
+            address: address.into(),
+            framing: Framing::Lines,
+            max_frame_size: 64 * 1024,
+            idle_timeout: None,
+            claimed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Connections sending longer lines or frames are dropped, 64 KiB by default
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Stops once nothing has been received for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+}
+
+impl PipelineComponent for TcpSource {
+    type Input = ();
+    type Output = String;
+
+    fn new() -> Self {
+        panic!("TcpSource requires an address. Use TcpSource::bind() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        if self.claimed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        debug!("TcpSource starting");
+
+        let listener = match TcpListener::bind(&self.address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to listen on {}: {}", self.address, e);
+                return;
+            }
+        };
+        debug!("TcpSource listening on {}", self.address);
+
+        let activity = Arc::new(Activity::new(self.idle_timeout));
+        // Dropping the connections when the source stops releases their senders
+        let mut connections = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        debug!("TCP connection from {}", peer);
+                        let (output, activity) = (output.clone(), activity.clone());
+                        let (framing, max_frame_size) = (self.framing, self.max_frame_size);
+                        connections.spawn(async move {
+                            read_frames(stream, &peer.to_string(), framing, max_frame_size, &output, &activity).await
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept connection: {}", e),
+                },
+                _ = activity.idle() => break,
+                Some(result) = connections.join_next() => {
+                    if matches!(result, Ok(false)) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        debug!("TcpSource completed");
+    }
+}
+
+/// Writes payloads to a TCP server, one line per message or length-prefixed
+/// frames with `Framing::LengthPrefixed`. Strings are written as they are,
+/// other payloads as JSON.
+///
+/// The sink keeps reconnecting while its input is open, waiting up to
+/// `with_max_backoff` between attempts, so it can be started before the
+/// server. Messages flushed to a connection that breaks may be lost, the ones
+/// not yet flushed are written again. Each slot of the sink opens its own
+/// connection.
+pub struct TcpSink<T> {
+    address: String,
+    framing: Framing,
+    max_backoff: Duration,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> TcpSink<T> {
+    /// Connects to `address`, e.g. `logs.internal:5140`
+    pub fn connect(address: impl Into<String>) -> Self {
+        TcpSink {
+            address: address.into(),
+            framing: Framing::Lines,
+            max_backoff: Duration::from_secs(5),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> PipelineComponent for TcpSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("TcpSink requires an address. Use TcpSink::connect() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("TcpSink starting");
+
+        let address = self.address.clone();
+        let (framing, max_backoff) = (self.framing, self.max_backoff);
+        let result = tokio::task::spawn_blocking(move || {
+            write_frames(&address, || std::net::TcpStream::connect(&address), framing, max_backoff, input)
+        }).await;
+        if let Err(e) = result {
+            error!("TcpSink failed: {:?}", e);
+        }
+
+        debug!("TcpSink completed");
+    }
+}
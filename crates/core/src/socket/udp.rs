@@ -0,0 +1,220 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Receiver, Sender};
+use crate::pipeline::codec::payload_bytes;
+use super::{Activity, INITIAL_BACKOFF};
+use crossbeam_channel::TryRecvError;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tracing::{debug, error, warn};
+
+/// Listens for UDP datagrams, e.g. syslog messages, and emits each one as a
+/// message with a trailing line break removed. Messages carry the sender's
+/// address as their source.
+///
+/// Datagrams that are not UTF-8 have invalid bytes replaced. Only one slot of
+/// the source listens. It runs until the pipeline stops, unless
+/// `with_idle_timeout` is set.
+pub struct UdpSource {
+    address: String,
+    idle_timeout: Option<Duration>,
+    claimed: AtomicBool,
+}
+
+impl UdpSource {
+    /// Listens on `address`, e.g. `0.0.0.0:514`
+    pub fn bind(address: impl Into<String>) -> Self {
+        UdpSource {
+            address: address.into(),
+            idle_timeout: None,
+            claimed: AtomicBool::new(false),
+        }
+    }
+
+    /// Stops once no datagram has been received for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+}
+
+impl PipelineComponent for UdpSource {
+    type Input = ();
+    type Output = String;
+
+    fn new() -> Self {
+        panic!("UdpSource requires an address. Use UdpSource::bind() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        if self.claimed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        debug!("UdpSource starting");
+
+        let socket = match UdpSocket::bind(&self.address).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                error!("Failed to listen on {}: {}", self.address, e);
+                return;
+            }
+        };
+        debug!("UdpSource listening on {}", self.address);
+
+        let activity = Activity::new(self.idle_timeout);
+        // Large enough for any datagram
+        let mut buffer = vec![0u8; 65536];
+
+        loop {
+            let (len, peer) = tokio::select! {
+                received = socket.recv_from(&mut buffer) => match received {
+                    Ok(received) => received,
+                    Err(e) => {
+                        warn!("Failed to receive datagram: {}", e);
+                        continue;
+                    }
+                },
+                _ = activity.idle() => break,
+            };
+
+            activity.touch();
+            let text = String::from_utf8_lossy(&buffer[..len]).trim_end_matches(['\n', '\r']).to_string();
+            if let Err(e) = output.send(Message::new(text).with_source(peer.to_string())) {
+                error!("Failed to send message to output: {}", e);
+                break;
+            }
+        }
+
+        debug!("UdpSource completed");
+    }
+}
+
+/// Sends each payload as a UDP datagram, strings as they are and other
+/// payloads as JSON.
+///
+/// Datagrams may be lost without notice. When sending fails, e.g. because the
+/// target reported that nothing listens, the payload is dropped and the
+/// address is resolved again for the next one. Payloads waiting for the address
+/// to resolve are dropped once the input has completed.
+pub struct UdpSink<T> {
+    address: String,
+    max_backoff: Duration,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> UdpSink<T> {
+    /// Sends to `address`, e.g. `logs.internal:514`
+    pub fn connect(address: impl Into<String>) -> Self {
+        UdpSink {
+            address: address.into(),
+            max_backoff: Duration::from_secs(5),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// The longest wait between attempts to resolve the address
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+}
+
+/// A socket bound to any local port of the target's address family and
+/// connected to the target
+fn open(address: &str) -> io::Result<std::net::UdpSocket> {
+    let target = address.to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} did not resolve", address)))?;
+    let local = match target {
+        SocketAddr::V4(_) => SocketAddr::from(([0, 0, 0, 0], 0)),
+        SocketAddr::V6(_) => SocketAddr::from(([0u16; 8], 0)),
+    };
+    let socket = std::net::UdpSocket::bind(local)?;
+    socket.connect(target)?;
+    Ok(socket)
+}
+
+/// Encodes a payload, logging payloads that cannot be sent
+fn encode<T: Serialize>(msg: &Message<T>) -> Option<Vec<u8>> {
+    match payload_bytes(&msg.payload) {
+        Ok(payload) => Some(payload),
+        Err(e) => {
+            error!("Failed to encode payload: {}", e);
+            None
+        }
+    }
+}
+
+fn send_all<T: Serialize>(address: &str, max_backoff: Duration, input: Receiver<T>) {
+    let mut socket = None;
+    let mut pending = VecDeque::new();
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        if pending.is_empty() {
+            match input.recv() {
+                Ok(msg) => pending.extend(encode(&msg)),
+                Err(_) => return,
+            }
+            continue;
+        }
+
+        let connected = match &socket {
+            Some(connected) => connected,
+            None => match open(address) {
+                Ok(opened) => {
+                    backoff = INITIAL_BACKOFF;
+                    socket.insert(opened)
+                }
+                Err(e) => {
+                    // Each failed attempt takes at most one more payload from the input
+                    match input.try_recv() {
+                        Ok(msg) => pending.extend(encode(&msg)),
+                        Err(TryRecvError::Empty) => {}
+                        Err(TryRecvError::Disconnected) => {
+                            error!("Failed to open socket to {}: {}, dropping {} payloads as the input has completed", address, e, pending.len());
+                            return;
+                        }
+                    }
+                    warn!("Failed to open socket to {}: {}, retrying in {:?}", address, e, backoff);
+                    std::thread::sleep(backoff);
+                    backoff = (backoff * 2).min(max_backoff);
+                    continue;
+                }
+            },
+        };
+        let payload = pending.pop_front().unwrap();
+        if let Err(e) = connected.send(&payload) {
+            warn!("Failed to send datagram to {}: {}", address, e);
+            socket = None;
+        }
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> PipelineComponent for UdpSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("UdpSink requires an address. Use UdpSink::connect() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("UdpSink starting");
+
+        let address = self.address.clone();
+        let max_backoff = self.max_backoff;
+        let result = tokio::task::spawn_blocking(move || send_all(&address, max_backoff, input)).await;
+        if let Err(e) = result {
+            error!("UdpSink failed: {:?}", e);
+        }
+
+        debug!("UdpSink completed");
+    }
+}
@@ -0,0 +1,176 @@
+use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::channel::{Receiver, Sender};
+use super::{read_frames, write_frames, Activity, Framing};
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::UnixListener;
+use tokio::task::JoinSet;
+use tracing::{debug, error, warn};
+
+/// Removes a socket file left behind by an earlier run, but nothing else
+fn remove_stale_socket(path: &Path) -> std::io::Result<()> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => std::fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// Listens for connections on a Unix domain socket and emits one message per
+/// line received, or per frame with `Framing::LengthPrefixed`. Messages carry
+/// the peer's socket path as their source, or the listening path for the
+/// usual unnamed peers, and the line number within the connection as their
+/// position.
+///
+/// A socket file left at the path by an earlier run is replaced, and the file
+/// is removed once the source stops. Otherwise it behaves as `TcpSource`.
+pub struct UnixSocketSource {
+    path: PathBuf,
+    framing: Framing,
+    max_frame_size: usize,
+    idle_timeout: Option<Duration>,
+    claimed: AtomicBool,
+}
+
+impl UnixSocketSource {
+    pub fn bind(path: impl Into<PathBuf>) -> Self {
+        UnixSocketSource {
+            path: path.into(),
+            framing: Framing::Lines,
+            max_frame_size: 64 * 1024,
+            idle_timeout: None,
+            claimed: AtomicBool::new(false),
+        }
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Connections sending longer lines or frames are dropped, 64 KiB by default
+    pub fn with_max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    /// Stops once nothing has been received for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+}
+
+impl PipelineComponent for UnixSocketSource {
+    type Input = ();
+    type Output = String;
+
+    fn new() -> Self {
+        panic!("UnixSocketSource requires a path. Use UnixSocketSource::bind() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        if self.claimed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        debug!("UnixSocketSource starting");
+
+        let listener = match remove_stale_socket(&self.path).and_then(|_| UnixListener::bind(&self.path)) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to listen on {}: {}", self.path.display(), e);
+                return;
+            }
+        };
+        debug!("UnixSocketSource listening on {}", self.path.display());
+
+        let activity = Arc::new(Activity::new(self.idle_timeout));
+        let mut connections = JoinSet::new();
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        let peer = peer.as_pathname().unwrap_or(&self.path).display().to_string();
+                        debug!("Unix socket connection from {}", peer);
+                        let (output, activity) = (output.clone(), activity.clone());
+                        let (framing, max_frame_size) = (self.framing, self.max_frame_size);
+                        connections.spawn(async move {
+                            read_frames(stream, &peer, framing, max_frame_size, &output, &activity).await
+                        });
+                    }
+                    Err(e) => warn!("Failed to accept connection: {}", e),
+                },
+                _ = activity.idle() => break,
+                Some(result) = connections.join_next() => {
+                    if matches!(result, Ok(false)) {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = remove_stale_socket(&self.path) {
+            warn!("Failed to remove {}: {}", self.path.display(), e);
+        }
+        debug!("UnixSocketSource completed");
+    }
+}
+
+/// Writes payloads to a Unix domain socket, reconnecting as `TcpSink` does
+pub struct UnixSocketSink<T> {
+    path: PathBuf,
+    framing: Framing,
+    max_backoff: Duration,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> UnixSocketSink<T> {
+    pub fn connect(path: impl Into<PathBuf>) -> Self {
+        UnixSocketSink {
+            path: path.into(),
+            framing: Framing::Lines,
+            max_backoff: Duration::from_secs(5),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> PipelineComponent for UnixSocketSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("UnixSocketSink requires a path. Use UnixSocketSink::connect() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("UnixSocketSink starting");
+
+        let path = self.path.clone();
+        let (framing, max_backoff) = (self.framing, self.max_backoff);
+        let result = tokio::task::spawn_blocking(move || {
+            let target = path.display().to_string();
+            write_frames(&target, || std::os::unix::net::UnixStream::connect(&path), framing, max_backoff, input)
+        }).await;
+        if let Err(e) = result {
+            error!("UnixSocketSink failed: {:?}", e);
+        }
+
+        debug!("UnixSocketSink completed");
+    }
+}
@@ -0,0 +1,181 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Receiver, Sender};
+use super::file_tail::{file_id, FileOffset, OffsetStore, TailedFile};
+use glob::Pattern;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, error};
+
+/// Emits the lines of all files in a directory whose names match a glob
+/// pattern, e.g. `*.log`. Files created later are picked up, and lines
+/// appended to any of the files are emitted as they arrive. Messages are
+/// tagged with the file name as their source.
+///
+/// Offsets are tracked per file and, with `with_offsets`, saved so a restarted
+/// source only emits lines it has not emitted before. Files are recognized by
+/// their inode, so a file renamed by log rotation, e.g. `app.log` to
+/// `app.log.1`, continues where it was left instead of being read again.
+///
+/// The source runs until the pipeline stops, unless `with_idle_timeout` is set.
+pub struct DirectorySource {
+    directory: PathBuf,
+    pattern: Pattern,
+    offsets: Option<PathBuf>,
+    poll_interval: Duration,
+    idle_timeout: Option<Duration>,
+}
+
+impl DirectorySource {
+    /// Panics if `pattern` is not a valid glob pattern
+    pub fn new<P: Into<PathBuf>>(directory: P, pattern: &str) -> Self {
+        DirectorySource {
+            directory: directory.into(),
+            pattern: Pattern::new(pattern).unwrap_or_else(|e| panic!("Invalid glob pattern {:?}: {}", pattern, e)),
+            offsets: None,
+            poll_interval: Duration::from_millis(250),
+            idle_timeout: None,
+        }
+    }
+
+    /// Saves offsets to and resumes from `path`
+    pub fn with_offsets<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.offsets = Some(path.into());
+        self
+    }
+
+    /// How often the directory is scanned for new files and lines
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Stops once no line has been appended to any file for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Matching files and their ids, in name order so older rotated files are
+    /// read first
+    async fn matching_files(&self) -> std::io::Result<Vec<(PathBuf, Option<u64>)>> {
+        let mut entries = tokio::fs::read_dir(&self.directory).await?;
+        let mut files = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let matches = entry.file_name().to_str().is_some_and(|name| self.pattern.matches(name));
+            if matches && entry.file_type().await?.is_file() {
+                let metadata = entry.metadata().await?;
+                files.push((entry.path(), file_id(&metadata)));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+/// Moves the files that were renamed since the last scan to their new names
+fn follow_renames(files: &mut BTreeMap<PathBuf, TailedFile>, offsets: &mut OffsetStore, found: &[(PathBuf, Option<u64>)]) {
+    let renames: Vec<(PathBuf, PathBuf)> = found.iter()
+        .filter_map(|(path, id)| {
+            id.as_ref()?;
+            if files.get(path).is_some_and(|file| file.position().id == *id) {
+                return None;
+            }
+            files.iter()
+                .find(|(other, file)| *other != path && file.position().id == *id)
+                .map(|(other, _)| (other.clone(), path.clone()))
+        })
+        .collect();
+
+    // All renamed files are taken out first, as one may move to the name of another
+    let renamed: Vec<(PathBuf, TailedFile)> = renames.into_iter()
+        .filter_map(|(from, to)| files.remove(&from).map(|file| (to, file)))
+        .collect();
+    for (path, mut file) in renamed {
+        file.rename(path.clone());
+        offsets.set(&path, file.position());
+        files.insert(path, file);
+    }
+}
+
+impl PipelineComponent for DirectorySource {
+    type Input = ();
+    type Output = String;
+
+    fn new() -> Self {
+        panic!("DirectorySource requires a directory and pattern. Use DirectorySource::new() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("DirectorySource starting");
+
+        let mut offsets = OffsetStore::load(self.offsets.clone());
+        let mut files: BTreeMap<PathBuf, TailedFile> = BTreeMap::new();
+        // Files renamed away from a path that was being read, until found again
+        let mut rotated: Vec<FileOffset> = Vec::new();
+        let mut last_line = Instant::now();
+
+        loop {
+            match self.matching_files().await {
+                Ok(found) => {
+                    follow_renames(&mut files, &mut offsets, &found);
+                    files.retain(|path, _| found.iter().any(|(p, _)| p == path));
+                    for (path, id) in &found {
+                        if files.contains_key(path) {
+                            continue;
+                        }
+                        let renamed = rotated.iter()
+                            .position(|position| id.is_some() && position.id == *id)
+                            .map(|index| rotated.swap_remove(index));
+                        if let Some(position) = renamed {
+                            offsets.set(path, position);
+                        }
+                        let resume = renamed.or_else(|| offsets.find(path, *id));
+                        files.insert(path.clone(), TailedFile::new(path.clone(), resume));
+                    }
+                    rotated.clear();
+                    offsets.retain(|path| found.iter().any(|(p, _)| p == path));
+                }
+                Err(e) => error!("Failed to list {}: {}", self.directory.display(), e),
+            }
+
+            let mut emitted = false;
+            for file in files.values_mut() {
+                let lines = match file.read_lines().await {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        error!("Failed to read {}: {}", file.path().display(), e);
+                        continue;
+                    }
+                };
+                rotated.extend(file.take_rotated());
+                if lines.is_empty() {
+                    continue;
+                }
+
+                let source = file.path().file_name().unwrap_or_default().to_string_lossy().into_owned();
+                for line in lines {
+                    if let Err(e) = output.send(Message::new(line).with_source(source.clone())) {
+                        error!("Failed to send line: {}", e);
+                        return;
+                    }
+                }
+                offsets.set(file.path(), file.position());
+                emitted = true;
+            }
+
+            if emitted {
+                offsets.save();
+                last_line = Instant::now();
+            } else if self.idle_timeout.is_some_and(|timeout| last_line.elapsed() >= timeout) {
+                break;
+            } else {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        }
+
+        debug!("DirectorySource completed");
+    }
+}
@@ -0,0 +1,187 @@
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Read};
+use std::marker::PhantomData;
+use tracing::warn;
+
+/// How `FileSource` turns the bytes of a file, after decompression, into records
+pub trait FileFormat: Send + Sync + 'static {
+    type Record: Send + Sync + 'static;
+
+    /// Reads the records in order, passing each to `emit` with its line or record
+    /// number, starting at 1. Stops early when `emit` returns false.
+    fn read(&self, reader: &mut dyn BufRead, emit: &mut dyn FnMut(u64, Self::Record) -> bool) -> io::Result<()>;
+}
+
+/// Lines of UTF-8 text; invalid UTF-8 is replaced rather than ending the file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lines;
+
+impl FileFormat for Lines {
+    type Record = String;
+
+    fn read(&self, reader: &mut dyn BufRead, emit: &mut dyn FnMut(u64, String) -> bool) -> io::Result<()> {
+        let mut line = Vec::new();
+        let mut number = 0;
+        while reader.read_until(b'\n', &mut line)? > 0 {
+            number += 1;
+            let text = String::from_utf8_lossy(&line).trim_end_matches(['\n', '\r']).to_string();
+            if !emit(number, text) {
+                break;
+            }
+            line.clear();
+        }
+        Ok(())
+    }
+}
+
+/// One JSON document per line, parsed into `T`. Blank lines are skipped, and
+/// lines that do not parse are logged and skipped.
+pub struct JsonLines<T = serde_json::Value> {
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> JsonLines<T> {
+    pub fn new() -> Self {
+        JsonLines { _phantom: PhantomData }
+    }
+}
+
+impl<T> Default for JsonLines<T> {
+    fn default() -> Self {
+        JsonLines::new()
+    }
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static> FileFormat for JsonLines<T> {
+    type Record = T;
+
+    fn read(&self, reader: &mut dyn BufRead, emit: &mut dyn FnMut(u64, T) -> bool) -> io::Result<()> {
+        Lines.read(reader, &mut |number, line| {
+            if line.trim().is_empty() {
+                return true;
+            }
+            match serde_json::from_str(&line) {
+                Ok(record) => emit(number, record),
+                Err(e) => {
+                    warn!("Skipping line {}: {}", number, e);
+                    true
+                }
+            }
+        })
+    }
+}
+
+/// Comma separated values, deserialized into `T` by column name, or by position
+/// with `with_headers(false)`. Rows that do not match `T` are logged and skipped;
+/// record numbers count the rows after the header.
+pub struct Csv<T = BTreeMap<String, String>> {
+    delimiter: u8,
+    headers: bool,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Csv<T> {
+    pub fn new() -> Self {
+        Csv {
+            delimiter: b',',
+            headers: true,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Whether the first row names the columns, true by default
+    pub fn with_headers(mut self, headers: bool) -> Self {
+        self.headers = headers;
+        self
+    }
+}
+
+impl<T> Default for Csv<T> {
+    fn default() -> Self {
+        Csv::new()
+    }
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static> FileFormat for Csv<T> {
+    type Record = T;
+
+    fn read(&self, reader: &mut dyn BufRead, emit: &mut dyn FnMut(u64, T) -> bool) -> io::Result<()> {
+        let mut csv = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.headers)
+            .flexible(true)
+            .from_reader(reader);
+
+        for (index, record) in csv.deserialize::<T>().enumerate() {
+            let number = index as u64 + 1;
+            match record {
+                Ok(record) => {
+                    if !emit(number, record) {
+                        break;
+                    }
+                }
+                Err(e) if e.is_io_error() => return Err(e.into()),
+                Err(e) => warn!("Skipping record {}: {}", number, e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Binary records, each a big-endian `u32` length followed by that many bytes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LengthPrefixed;
+
+impl FileFormat for LengthPrefixed {
+    type Record = Vec<u8>;
+
+    fn read(&self, reader: &mut dyn BufRead, emit: &mut dyn FnMut(u64, Vec<u8>) -> bool) -> io::Result<()> {
+        let mut number = 0;
+        loop {
+            // Only a file ending exactly between records ends cleanly, a partial
+            // header is a truncated record like a partial body is
+            let mut len = [0u8; 4];
+            let mut filled = 0;
+            while filled < len.len() {
+                match reader.read(&mut len[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            if filled == 0 {
+                return Ok(());
+            }
+            number += 1;
+            if filled < len.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("record {} has a truncated header of {} bytes", number, filled),
+                ));
+            }
+
+            // The length is not trusted for allocating, a corrupt one only gets as
+            // far as the bytes actually in the file
+            let len = u32::from_be_bytes(len) as u64;
+            let mut record = Vec::new();
+            (&mut *reader).take(len).read_to_end(&mut record)?;
+            if (record.len() as u64) < len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("record {} is {} bytes long, but only {} bytes are left", number, len, record.len()),
+                ));
+            }
+
+            if !emit(number, record) {
+                return Ok(());
+            }
+        }
+    }
+}
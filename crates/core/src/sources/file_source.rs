@@ -1,26 +1,64 @@
 use crate::pipeline::{PipelineComponent, ComponentContext, Message};
 use crate::pipeline::channel::{Receiver, Sender};
+use super::file_formats::{FileFormat, Lines};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tracing::{debug, error};
-use tokio::fs::File;
-use tokio::io::{BufReader, AsyncBufReadExt};
-use std::path::PathBuf;
 
-pub struct FileSource {
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Reads a file once, emitting its lines, or the records of another format set
+/// with `with_format`, e.g. `Csv` or `JsonLines`. Gzip and zstd compressed files
+/// are recognised by their content and decompressed transparently.
+///
+/// Messages carry the file name as their source and the line or record number
+/// as their position.
+pub struct FileSource<F = Lines> {
     path: PathBuf,
+    format: Arc<F>,
 }
 
 impl FileSource {
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
         FileSource {
             path: path.into(),
+            format: Arc::new(Lines),
         }
     }
 }
 
-impl PipelineComponent for FileSource {
+impl<F: FileFormat> FileSource<F> {
+    pub fn with_format<G: FileFormat>(self, format: G) -> FileSource<G> {
+        FileSource {
+            path: self.path,
+            format: Arc::new(format),
+        }
+    }
+}
+
+/// Opens `path`, decompressing it if it starts with a gzip or zstd header
+fn open(path: &Path) -> io::Result<Box<dyn BufRead + Send>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let header = reader.fill_buf()?;
+
+    if header.starts_with(GZIP_MAGIC) {
+        debug!("Reading {} as gzip", path.display());
+        Ok(Box::new(BufReader::new(MultiGzDecoder::new(reader))))
+    } else if header.starts_with(ZSTD_MAGIC) {
+        debug!("Reading {} as zstd", path.display());
+        Ok(Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+impl<F: FileFormat> PipelineComponent for FileSource<F> {
     type Input = ();
-    type Output = String;
+    type Output = F::Record;
 
     fn new() -> Self {
         panic!("FileSource requires a file path. Use FileSource::new() instead.")
@@ -29,26 +67,30 @@ impl PipelineComponent for FileSource {
     async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
         debug!("FileSource starting");
 
-        let file = match File::open(&self.path).await {
-            Ok(file) => file,
-            Err(e) => {
-                error!("Failed to open file {}: {}", self.path.display(), e);
-                return;
-            }
-        };
-
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
-
-        while let Ok(Some(line)) = lines.next_line().await {
-            debug!("FileSource read line");
-            if let Err(e) = output.send(Message::new(line)) {
-                error!("Failed to send line: {}", e);
-                break;
-            }
-            debug!("FileSource sent line");
+        let path = self.path.clone();
+        let format = self.format.clone();
+        let source = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+        // Decompression and parsing are blocking work
+        let result = tokio::task::spawn_blocking(move || {
+            let mut reader = open(&path)?;
+            format.read(&mut reader, &mut |position, record| {
+                match output.send(Message::new(record).with_source(source.clone()).with_position(position)) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        error!("Failed to send record: {}", e);
+                        false
+                    }
+                }
+            })
+        }).await;
+
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => error!("Failed to read file {}: {}", self.path.display(), e),
+            Err(e) => error!("FileSource failed: {:?}", e),
         }
 
         debug!("FileSource completed");
     }
-} 
\ No newline at end of file
+}
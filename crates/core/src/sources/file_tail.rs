@@ -0,0 +1,218 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
+use tracing::{debug, warn};
+
+/// How far a file has been read. `id` tells a rotated file apart from the one
+/// the offset belongs to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct FileOffset {
+    pub id: Option<u64>,
+    pub offset: u64,
+}
+
+/// Offsets of tailed files, persisted as JSON so sources can resume after a restart
+pub(crate) struct OffsetStore {
+    path: Option<PathBuf>,
+    offsets: BTreeMap<PathBuf, FileOffset>,
+}
+
+impl OffsetStore {
+    /// Loads the offsets saved at `path`, or keeps them in memory only without one
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let offsets = path.as_ref()
+            .and_then(|path| match std::fs::read(path) {
+                Ok(bytes) => serde_json::from_slice(&bytes)
+                    .map_err(|e| warn!("Ignoring unreadable offsets in {}: {}", path.display(), e))
+                    .ok(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    warn!("Failed to read offsets from {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+        OffsetStore { path, offsets }
+    }
+
+    pub fn get(&self, file: &Path) -> Option<FileOffset> {
+        self.offsets.get(file).copied()
+    }
+
+    /// Offset of the file at `path` with the given `id`, which may have been
+    /// saved under the name the file had before it was renamed
+    pub fn find(&self, file: &Path, id: Option<u64>) -> Option<FileOffset> {
+        let saved = self.get(file);
+        if id.is_none() || saved.is_some_and(|offset| offset.id == id) {
+            return saved;
+        }
+        self.offsets.values().find(|offset| offset.id == id).copied().or(saved)
+    }
+
+    pub fn set(&mut self, file: &Path, offset: FileOffset) {
+        self.offsets.insert(file.to_path_buf(), offset);
+    }
+
+    pub fn retain(&mut self, keep: impl Fn(&Path) -> bool) {
+        self.offsets.retain(|file, _| keep(file));
+    }
+
+    /// Writes the offsets, replacing the previous file atomically
+    pub fn save(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let temp = path.with_extension("tmp");
+        let result = serde_json::to_vec(&self.offsets)
+            .map_err(io::Error::other)
+            .and_then(|bytes| std::fs::write(&temp, bytes))
+            .and_then(|_| std::fs::rename(&temp, path));
+        if let Err(e) = result {
+            warn!("Failed to save offsets to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Turns the complete line in `partial` into a string, moving past it
+fn take_line(position: &mut FileOffset, partial: &mut Vec<u8>) -> String {
+    position.offset += partial.len() as u64;
+    let line = String::from_utf8_lossy(partial)
+        .trim_end_matches(['\n', '\r'])
+        .to_string();
+    partial.clear();
+    line
+}
+
+#[cfg(unix)]
+pub(crate) fn file_id(metadata: &std::fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+pub(crate) fn file_id(_metadata: &std::fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// A file read line by line as it grows. Only complete lines are returned; a
+/// line still being written is returned once its newline arrives.
+pub(crate) struct TailedFile {
+    path: PathBuf,
+    reader: Option<BufReader<File>>,
+    position: FileOffset,
+    partial: Vec<u8>,
+    rotated: Option<FileOffset>,
+}
+
+impl TailedFile {
+    /// Starts at `resume` if it belongs to the file found at `path` when opened
+    pub fn new(path: PathBuf, resume: Option<FileOffset>) -> Self {
+        TailedFile {
+            path,
+            reader: None,
+            position: resume.unwrap_or_default(),
+            partial: Vec::new(),
+            rotated: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Follows the file to the name it was renamed to, keeping its position
+    pub fn rename(&mut self, path: PathBuf) {
+        debug!("{} was renamed to {}", self.path.display(), path.display());
+        self.path = path;
+    }
+
+    pub fn position(&self) -> FileOffset {
+        self.position
+    }
+
+    /// Where reading stopped in the file that was last rotated away from the path
+    pub fn take_rotated(&mut self) -> Option<FileOffset> {
+        self.rotated.take()
+    }
+
+    async fn open(&mut self) -> io::Result<bool> {
+        let file = match File::open(&self.path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        let metadata = file.metadata().await?;
+        let id = file_id(&metadata);
+
+        let mut reader = BufReader::new(file);
+        if self.position.id == id && self.position.offset <= metadata.len() {
+            reader.seek(SeekFrom::Start(self.position.offset)).await?;
+        } else {
+            self.position.offset = 0;
+        }
+        debug!("Tailing {} from offset {}", self.path.display(), self.position.offset);
+
+        self.position.id = id;
+        self.reader = Some(reader);
+        self.partial.clear();
+        Ok(true)
+    }
+
+    /// Reads the complete lines up to the end of the open file
+    async fn read_available(&mut self, lines: &mut Vec<String>) -> io::Result<()> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(());
+        };
+        loop {
+            let read = reader.read_until(b'\n', &mut self.partial).await?;
+            if read == 0 || !self.partial.ends_with(b"\n") {
+                return Ok(());
+            }
+            lines.push(take_line(&mut self.position, &mut self.partial));
+        }
+    }
+
+    /// Reads the lines appended since the last call. A rotated file is read to
+    /// its end before the new file at the path is opened, a truncated file is
+    /// read again from the start.
+    pub async fn read_lines(&mut self) -> io::Result<Vec<String>> {
+        if self.reader.is_none() && !self.open().await? {
+            return Ok(Vec::new());
+        }
+
+        let mut lines = Vec::new();
+        self.read_available(&mut lines).await?;
+
+        let current = match tokio::fs::metadata(&self.path).await {
+            Ok(metadata) => Some(metadata),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        match current {
+            Some(metadata) if file_id(&metadata) == self.position.id => {
+                if metadata.len() < self.position.offset + self.partial.len() as u64 {
+                    debug!("{} was truncated", self.path.display());
+                    self.reader = None;
+                    self.position.offset = 0;
+                }
+            }
+            _ => {
+                // Rotated or removed. Lines written just before rotating are read
+                // now, as the old file will not grow any further.
+                debug!("{} was rotated", self.path.display());
+                self.read_available(&mut lines).await?;
+                if !self.partial.is_empty() {
+                    lines.push(take_line(&mut self.position, &mut self.partial));
+                }
+                self.reader = None;
+                self.rotated = Some(self.position);
+                self.position = FileOffset::default();
+            }
+        }
+
+        Ok(lines)
+    }
+}
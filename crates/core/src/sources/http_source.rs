@@ -0,0 +1,238 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Receiver, Sender};
+use hyper::body::HttpBody;
+use hyper::header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::convert::Infallible;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+/// Runs an HTTP server that emits the JSON payloads POSTed to it, e.g. by
+/// webhooks. A body holding a JSON array is a batch, emitted one message per
+/// element; any other JSON value is a single payload. A request is accepted
+/// with `202 Accepted` only if all of its payloads deserialize into `T`.
+///
+/// With `with_token` requests must carry an `Authorization: Bearer <token>`
+/// header. Requests to other paths than `with_path`, `/` by default, are
+/// answered with `404 Not Found`.
+///
+/// Only one slot of the source listens. It runs until the pipeline stops,
+/// unless `with_idle_timeout` is set.
+pub struct HttpSource<T = serde_json::Value> {
+    address: String,
+    path: String,
+    token: Option<String>,
+    max_body_size: usize,
+    idle_timeout: Option<Duration>,
+    claimed: AtomicBool,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+/// What the request handlers share
+struct Ingest<T> {
+    path: String,
+    token: Option<String>,
+    max_body_size: usize,
+    output: Sender<T>,
+    last_request: Mutex<Instant>,
+}
+
+impl<T> HttpSource<T> {
+    /// Listens on `address`, e.g. `0.0.0.0:8080`
+    pub fn bind(address: impl Into<String>) -> Self {
+        HttpSource {
+            address: address.into(),
+            path: "/".to_string(),
+            token: None,
+            max_body_size: 1024 * 1024,
+            idle_timeout: None,
+            claimed: AtomicBool::new(false),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Requires requests to carry `token` as a bearer token
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Larger bodies are refused with `413 Payload Too Large`, 1 MiB by default
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Stops once no request has been accepted for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+}
+
+fn respond(status: StatusCode, body: impl Into<String>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.into()))
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    respond(status, serde_json::json!({ "error": message }).to_string())
+}
+
+/// Reads the body, giving up once it is larger than `limit`
+async fn read_body(mut body: Body, limit: usize) -> Result<Vec<u8>, StatusCode> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|_| StatusCode::BAD_REQUEST)?;
+        if bytes.len() + chunk.len() > limit {
+            return Err(StatusCode::PAYLOAD_TOO_LARGE);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// Deserializes a single payload, or each element of a batch
+fn parse_payloads<T: DeserializeOwned>(body: &[u8]) -> Result<Vec<T>, String> {
+    let value: serde_json::Value = serde_json::from_slice(body).map_err(|e| format!("invalid JSON: {}", e))?;
+    let values = match value {
+        serde_json::Value::Array(values) => values,
+        value => vec![value],
+    };
+    values.into_iter()
+        .enumerate()
+        .map(|(index, value)| serde_json::from_value(value).map_err(|e| format!("invalid payload {}: {}", index, e)))
+        .collect()
+}
+
+async fn handle<T: DeserializeOwned>(request: Request<Body>, ingest: Arc<Ingest<T>>) -> Result<Response<Body>, Infallible> {
+    if request.uri().path() != ingest.path {
+        return Ok(error_response(StatusCode::NOT_FOUND, "not found"));
+    }
+    if request.method() != Method::POST {
+        return Ok(error_response(StatusCode::METHOD_NOT_ALLOWED, "only POST is supported"));
+    }
+    if let Some(token) = &ingest.token {
+        let authorized = request.headers().get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|given| given == token);
+        if !authorized {
+            return Ok(error_response(StatusCode::UNAUTHORIZED, "missing or invalid token"));
+        }
+    }
+    let declared_size = request.headers().get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if declared_size.is_some_and(|size| size > ingest.max_body_size) {
+        return Ok(error_response(StatusCode::PAYLOAD_TOO_LARGE, "body too large"));
+    }
+
+    let body = match read_body(request.into_body(), ingest.max_body_size).await {
+        Ok(body) => body,
+        Err(status) => return Ok(error_response(status, "failed to read body")),
+    };
+    let payloads: Vec<T> = match parse_payloads(&body) {
+        Ok(payloads) => payloads,
+        Err(e) => {
+            warn!("Rejecting request: {}", e);
+            return Ok(error_response(StatusCode::BAD_REQUEST, &e));
+        }
+    };
+
+    let accepted = payloads.len();
+    for payload in payloads {
+        if let Err(e) = ingest.output.send(Message::new(payload)) {
+            error!("Failed to send payload: {}", e);
+            return Ok(error_response(StatusCode::SERVICE_UNAVAILABLE, "pipeline is not accepting payloads"));
+        }
+    }
+    *ingest.last_request.lock().unwrap() = Instant::now();
+    Ok(respond(StatusCode::ACCEPTED, serde_json::json!({ "accepted": accepted }).to_string()))
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static> PipelineComponent for HttpSource<T> {
+    type Input = ();
+    type Output = T;
+
+    fn new() -> Self {
+        panic!("HttpSource requires an address. Use HttpSource::bind() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        if self.claimed.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        debug!("HttpSource starting");
+
+        let listener = match TcpListener::bind(&self.address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to listen on {}: {}", self.address, e);
+                return;
+            }
+        };
+        debug!("HttpSource listening on {}{}", self.address, self.path);
+
+        let ingest = Arc::new(Ingest {
+            path: self.path.clone(),
+            token: self.token.clone(),
+            max_body_size: self.max_body_size,
+            output,
+            last_request: Mutex::new(Instant::now()),
+        });
+        // Dropping the connections when the source stops releases their senders
+        let mut connections = JoinSet::new();
+
+        loop {
+            let idle_deadline = self.idle_timeout.map(|timeout| *ingest.last_request.lock().unwrap() + timeout);
+            let idle = async {
+                match idle_deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, peer)) => {
+                        debug!("HTTP connection from {}", peer);
+                        let ingest = ingest.clone();
+                        connections.spawn(async move {
+                            let service = service_fn(move |request| handle(request, ingest.clone()));
+                            if let Err(e) = Http::new().http1_only(true).serve_connection(stream, service).await {
+                                debug!("HTTP connection from {} failed: {}", peer, e);
+                            }
+                        });
+                    }
+                    Err(e) => error!("Failed to accept connection: {}", e),
+                },
+                _ = idle => {
+                    if ingest.last_request.lock().unwrap().elapsed() >= self.idle_timeout.unwrap_or_default() {
+                        break;
+                    }
+                }
+                Some(_) = connections.join_next() => {}
+            }
+        }
+
+        debug!("HttpSource completed");
+    }
+}
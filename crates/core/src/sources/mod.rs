@@ -1,12 +1,29 @@
 pub mod bluesky;
 pub mod mastodon;
 pub mod websocket;
-pub mod websocket_mqtt;
 pub mod file_source;
+pub mod file_formats;
+pub mod replay_source;
+pub mod tail_file_source;
+pub mod directory_source;
+pub mod http_source;
+pub mod sse_source;
+mod file_tail;
+
+/// Moved to `crate::mqtt`, kept so existing paths keep compiling
+pub mod websocket_mqtt {
+    pub use crate::mqtt::websocket_mqtt_source::*;
+}
 
 // Re-export the source types
 pub use bluesky::bluesky_firehose_source::BlueskyFirehoseSource;
 pub use mastodon::mastodon_firehose_source::MastodonFirehoseSource;
 pub use websocket::WebSocketSource;
-pub use websocket_mqtt::WebSocketMqttSource;
-pub use file_source::FileSource;
\ No newline at end of file
+pub use crate::mqtt::WebSocketMqttSource;
+pub use file_source::FileSource;
+pub use file_formats::{FileFormat, Lines, JsonLines, Csv, LengthPrefixed};
+pub use replay_source::ReplaySource;
+pub use tail_file_source::TailFileSource;
+pub use directory_source::DirectorySource;
+pub use http_source::HttpSource;
+pub use sse_source::SseSource;
\ No newline at end of file
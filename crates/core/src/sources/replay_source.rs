@@ -0,0 +1,142 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message, Codec, CborCodec};
+use crate::pipeline::channel::{Receiver, Sender};
+use crate::transformers::record_tap::RECORD_LOG_MAGIC;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, BufReader};
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+/// Plays back a log written by `RecordTap`. Messages are emitted exactly as they
+/// were recorded, keeping their timestamps and source.
+///
+/// By default the log is replayed as fast as possible. `with_speed` replays it
+/// with the original gaps between messages, scaled by the given factor, e.g.
+/// `2.0` replays twice as fast as the stream was recorded.
+///
+/// Logs recorded with a codec other than CBOR are read with `with_codec`.
+pub struct ReplaySource<T, C = CborCodec> {
+    path: PathBuf,
+    codec: C,
+    speed: Option<f64>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> ReplaySource<T> {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        ReplaySource {
+            path: path.into(),
+            codec: CborCodec,
+            speed: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, C: Codec> ReplaySource<T, C> {
+    pub fn with_codec<D: Codec>(self, codec: D) -> ReplaySource<T, D> {
+        ReplaySource {
+            path: self.path,
+            codec,
+            speed: self.speed,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        assert!(speed > 0.0, "Replay speed must be positive");
+        self.speed = Some(speed);
+        self
+    }
+}
+
+/// Reads the next record, or `None` at the end of the log
+async fn read_record<T: DeserializeOwned, C: Codec>(reader: &mut BufReader<File>, codec: &C) -> std::io::Result<Option<(u64, Message<T>)>> {
+    let mut len = [0u8; 4];
+    let mut read = 0;
+    while read < len.len() {
+        match reader.read(&mut len[read..]).await? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    match read {
+        0 => return Ok(None),
+        4 => {}
+        _ => return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "record header is truncated")),
+    }
+
+    let recorded_at = reader.read_u64().await?;
+    // The length is not trusted for allocating, a corrupt one only gets as far
+    // as the bytes actually in the log
+    let len = u32::from_be_bytes(len) as u64;
+    let mut record = Vec::new();
+    (&mut *reader).take(len).read_to_end(&mut record).await?;
+    if (record.len() as u64) < len {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!("record is {} bytes long, but only {} bytes are left", len, record.len()),
+        ));
+    }
+    Ok(Some((recorded_at, codec.decode(&record)?)))
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static, C: Codec> PipelineComponent for ReplaySource<T, C> {
+    type Input = ();
+    type Output = T;
+
+    fn new() -> Self {
+        panic!("ReplaySource requires a log path. Use ReplaySource::new() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("ReplaySource starting");
+
+        let file = match File::open(&self.path).await {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to open record log {}: {}", self.path.display(), e);
+                return;
+            }
+        };
+        let mut reader = BufReader::new(file);
+
+        let mut magic = [0u8; 8];
+        if reader.read_exact(&mut magic).await.is_err() || &magic != RECORD_LOG_MAGIC {
+            error!("{} is not a record log", self.path.display());
+            return;
+        }
+
+        let started = Instant::now();
+        let mut first_recorded_at = None;
+
+        loop {
+            let (recorded_at, msg) = match read_record::<T, C>(&mut reader, &self.codec).await {
+                Ok(Some(record)) => record,
+                Ok(None) => break,
+                Err(e) => {
+                    // Typically the last record of a log whose recording was cut short
+                    warn!("Stopping replay of {}: {}", self.path.display(), e);
+                    break;
+                }
+            };
+
+            if let Some(speed) = self.speed {
+                let first = *first_recorded_at.get_or_insert(recorded_at);
+                let offset = recorded_at.saturating_sub(first) as f64 / speed;
+                tokio::time::sleep_until(started + Duration::from_secs_f64(offset / 1000.0)).await;
+            }
+
+            if let Err(e) = output.send(msg) {
+                error!("Failed to send replayed item: {}", e);
+                break;
+            }
+        }
+
+        debug!("ReplaySource completed");
+    }
+}
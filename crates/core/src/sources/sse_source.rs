@@ -0,0 +1,257 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Receiver, Sender};
+use futures_util::StreamExt;
+use reqwest::header::ACCEPT;
+use reqwest::StatusCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, error, warn};
+
+/// An event dispatched from a `text/event-stream`
+#[derive(Debug, PartialEq)]
+struct Event {
+    event: String,
+    data: String,
+}
+
+/// Parses a `text/event-stream` as it arrives, following the HTML event stream
+/// format: `data` lines are joined, `id` sets the last event id once its event
+/// is dispatched and `retry` the reconnection delay.
+#[derive(Default)]
+struct EventParser {
+    line: Vec<u8>,
+    // A carriage return ended the last line, so a following line feed belongs to it
+    after_cr: bool,
+    event: String,
+    data: String,
+    has_data: bool,
+    // The id of the event being received, which becomes the last event id
+    // only once the event is complete
+    id: Option<String>,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl EventParser {
+    fn feed(&mut self, bytes: &[u8], events: &mut Vec<Event>) {
+        for &byte in bytes {
+            match byte {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\r' | b'\n' => {
+                    self.after_cr = byte == b'\r';
+                    let line = String::from_utf8_lossy(&std::mem::take(&mut self.line)).into_owned();
+                    self.process_line(&line, events);
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.line.push(byte);
+                }
+            }
+        }
+    }
+
+    fn process_line(&mut self, line: &str, events: &mut Vec<Event>) {
+        if line.is_empty() {
+            self.dispatch(events);
+            return;
+        }
+        if line.starts_with(':') {
+            return;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(millis) = value.parse::<u64>() {
+                    self.retry = Some(Duration::from_millis(millis));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self, events: &mut Vec<Event>) {
+        self.last_event_id.clone_from(&self.id);
+        let event = std::mem::take(&mut self.event);
+        let data = std::mem::take(&mut self.data);
+        if std::mem::take(&mut self.has_data) {
+            let event = if event.is_empty() { "message".to_string() } else { event };
+            events.push(Event { event, data });
+        }
+    }
+
+    /// Drops a partly received event, as the stream ended before it was complete
+    fn reset(&mut self) {
+        let last_event_id = self.last_event_id.take();
+        let retry = self.retry.take();
+        *self = EventParser { id: last_event_id.clone(), last_event_id, retry, ..EventParser::default() };
+    }
+}
+
+/// Consumes a Server-Sent Events endpoint, emitting the data of each event.
+/// Messages carry the event type, `message` unless the stream names one, as
+/// their source.
+///
+/// When the stream ends or fails the source reconnects after the delay set by
+/// the server, or `with_retry`, sending the id of the last event it received
+/// in a `Last-Event-ID` header so the server can resume after it. A source
+/// restarted with `with_last_event_id` resumes the same way.
+///
+/// The source runs until the pipeline stops or the server answers with
+/// `204 No Content`, unless `with_idle_timeout` is set.
+pub struct SseSource {
+    url: String,
+    headers: Vec<(String, String)>,
+    last_event_id: Option<String>,
+    retry: Duration,
+    idle_timeout: Option<Duration>,
+}
+
+impl SseSource {
+    pub fn new(url: impl Into<String>) -> Self {
+        SseSource {
+            url: url.into(),
+            headers: Vec::new(),
+            last_event_id: None,
+            retry: Duration::from_secs(3),
+            idle_timeout: None,
+        }
+    }
+
+    /// Sends a header with every request, e.g. `Authorization`
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Resumes after the event with this id
+    pub fn with_last_event_id(mut self, id: impl Into<String>) -> Self {
+        self.last_event_id = Some(id.into());
+        self
+    }
+
+    /// The delay before reconnecting until the server sets one, 3 seconds by default
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Stops once no event has been received for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+}
+
+/// Why reading the stream stopped
+enum StreamEnd {
+    Disconnected,
+    Idle,
+    OutputClosed,
+}
+
+impl SseSource {
+    /// How long the source may still wait for an event, if it has an idle timeout
+    fn remaining(&self, last_event: Instant) -> Option<Duration> {
+        self.idle_timeout.map(|timeout| timeout.saturating_sub(last_event.elapsed()))
+    }
+
+    async fn read_stream(&self, response: reqwest::Response, parser: &mut EventParser, output: &Sender<String>, last_event: &mut Instant) -> StreamEnd {
+        let mut stream = response.bytes_stream();
+        let mut events = Vec::new();
+
+        loop {
+            let chunk = match self.remaining(*last_event) {
+                Some(remaining) => match tokio::time::timeout(remaining, stream.next()).await {
+                    Ok(chunk) => chunk,
+                    Err(_) => return StreamEnd::Idle,
+                },
+                None => stream.next().await,
+            };
+            let bytes = match chunk {
+                Some(Ok(bytes)) => bytes,
+                Some(Err(e)) => {
+                    warn!("Event stream from {} failed: {}", self.url, e);
+                    return StreamEnd::Disconnected;
+                }
+                None => return StreamEnd::Disconnected,
+            };
+
+            parser.feed(&bytes, &mut events);
+            for event in events.drain(..) {
+                *last_event = Instant::now();
+                if let Err(e) = output.send(Message::new(event.data).with_source(event.event)) {
+                    error!("Failed to send event: {}", e);
+                    return StreamEnd::OutputClosed;
+                }
+            }
+        }
+    }
+}
+
+impl PipelineComponent for SseSource {
+    type Input = ();
+    type Output = String;
+
+    fn new() -> Self {
+        panic!("SseSource requires a URL. Use SseSource::new() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("SseSource starting");
+
+        let client = reqwest::Client::new();
+        let mut parser = EventParser {
+            id: self.last_event_id.clone(),
+            last_event_id: self.last_event_id.clone(),
+            ..EventParser::default()
+        };
+        let mut last_event = Instant::now();
+
+        loop {
+            let mut request = client.get(&self.url).header(ACCEPT, "text/event-stream");
+            for (name, value) in &self.headers {
+                request = request.header(name, value);
+            }
+            if let Some(id) = &parser.last_event_id {
+                request = request.header("Last-Event-ID", id);
+            }
+
+            match request.send().await {
+                Ok(response) if response.status() == StatusCode::NO_CONTENT => {
+                    debug!("{} asked not to reconnect", self.url);
+                    break;
+                }
+                Ok(response) if response.status().is_success() => {
+                    debug!("Connected to event stream {}", self.url);
+                    match self.read_stream(response, &mut parser, &output, &mut last_event).await {
+                        StreamEnd::Disconnected => parser.reset(),
+                        StreamEnd::Idle | StreamEnd::OutputClosed => break,
+                    }
+                }
+                Ok(response) => warn!("Event stream {} answered {}", self.url, response.status()),
+                Err(e) => warn!("Failed to connect to event stream {}: {}", self.url, e),
+            }
+
+            let retry = parser.retry.unwrap_or(self.retry);
+            match self.remaining(last_event) {
+                Some(remaining) if remaining <= retry => break,
+                _ => tokio::time::sleep(retry).await,
+            }
+        }
+
+        debug!("SseSource completed");
+    }
+}
@@ -0,0 +1,101 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Receiver, Sender};
+use super::file_tail::{OffsetStore, TailedFile};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, error};
+
+/// Emits the lines of a file as they are appended, like `tail -F`. A rotated
+/// file is read to its end before following the new file at the same path,
+/// and a truncated file is followed from its start again.
+///
+/// With `with_offsets` the byte offset reached is saved to a file after every
+/// batch of lines, so a restarted source resumes where it stopped instead of
+/// emitting the file again. Lines sent just before a crash may be emitted twice.
+///
+/// The source runs until the pipeline stops, unless `with_idle_timeout` is set,
+/// e.g. to catch up on a file and stop.
+pub struct TailFileSource {
+    path: PathBuf,
+    offsets: Option<PathBuf>,
+    poll_interval: Duration,
+    idle_timeout: Option<Duration>,
+}
+
+impl TailFileSource {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        TailFileSource {
+            path: path.into(),
+            offsets: None,
+            poll_interval: Duration::from_millis(250),
+            idle_timeout: None,
+        }
+    }
+
+    /// Saves offsets to and resumes from `path`
+    pub fn with_offsets<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.offsets = Some(path.into());
+        self
+    }
+
+    /// How often the file is checked for new lines once it has been read to its end
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Stops once no line has been appended for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+}
+
+impl PipelineComponent for TailFileSource {
+    type Input = ();
+    type Output = String;
+
+    fn new() -> Self {
+        panic!("TailFileSource requires a file path. Use TailFileSource::new() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("TailFileSource starting");
+
+        let mut offsets = OffsetStore::load(self.offsets.clone());
+        let mut file = TailedFile::new(self.path.clone(), offsets.get(&self.path));
+        let mut last_line = Instant::now();
+
+        loop {
+            let lines = match file.read_lines().await {
+                Ok(lines) => lines,
+                Err(e) => {
+                    error!("Failed to read {}: {}", self.path.display(), e);
+                    Vec::new()
+                }
+            };
+
+            if lines.is_empty() {
+                if self.idle_timeout.is_some_and(|timeout| last_line.elapsed() >= timeout) {
+                    break;
+                }
+                tokio::time::sleep(self.poll_interval).await;
+                continue;
+            }
+
+            last_line = Instant::now();
+            for line in lines {
+                if let Err(e) = output.send(Message::new(line)) {
+                    error!("Failed to send line: {}", e);
+                    return;
+                }
+            }
+            offsets.set(&self.path, file.position());
+            offsets.save();
+        }
+
+        debug!("TailFileSource completed");
+    }
+}
@@ -0,0 +1,24 @@
+pub mod sqlite_sink;
+pub mod sqlite_source;
+
+pub use sqlite_sink::SqliteSink;
+pub use sqlite_source::SqliteSource;
+pub use rusqlite::types::Value;
+pub use rusqlite::Row;
+
+use rusqlite::Connection;
+use std::path::Path;
+use std::time::Duration;
+
+/// Opens the database, waiting for locks held by other connections, e.g. a
+/// source reading the table a sink writes to
+fn open(path: &Path) -> rusqlite::Result<Connection> {
+    let connection = Connection::open(path)?;
+    connection.busy_timeout(Duration::from_secs(5))?;
+    Ok(connection)
+}
+
+/// Quotes a table or column name for use in SQL
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
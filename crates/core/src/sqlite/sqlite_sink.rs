@@ -0,0 +1,146 @@
+use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::channel::{Sender, Receiver};
+use super::{open, quote, Value};
+use rusqlite::{params_from_iter, Connection};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{debug, error};
+
+type RowMapping<T> = Arc<dyn Fn(&T) -> Vec<Value> + Send + Sync>;
+
+/// Inserts payloads into a SQLite table as rows.
+///
+/// The mapping given to `new` turns a payload into the values of `columns`, in
+/// order. With `with_upsert` a row whose key columns match an existing row
+/// replaces that row's other columns instead of failing.
+///
+/// Rows are written in transactions of up to `with_batch_size` rows, and
+/// whenever the input is empty, so a slow stream is not held back waiting for
+/// a full batch. A batch that fails, e.g. on a constraint, is logged and
+/// dropped as a whole.
+pub struct SqliteSink<T> {
+    path: PathBuf,
+    table: String,
+    columns: Vec<String>,
+    mapping: RowMapping<T>,
+    upsert_keys: Option<Vec<String>>,
+    setup: Option<String>,
+    batch_size: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> SqliteSink<T> {
+    pub fn new<P, F>(path: P, table: &str, columns: &[&str], mapping: F) -> Self
+    where
+        P: Into<PathBuf>,
+        F: Fn(&T) -> Vec<Value> + Send + Sync + 'static,
+    {
+        SqliteSink {
+            path: path.into(),
+            table: table.to_string(),
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            mapping: Arc::new(mapping),
+            upsert_keys: None,
+            setup: None,
+            batch_size: 500,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Updates the row with the same `keys` instead of inserting a new one. The
+    /// keys need a primary key or unique index on the table.
+    pub fn with_upsert(mut self, keys: &[&str]) -> Self {
+        self.upsert_keys = Some(keys.iter().map(|key| key.to_string()).collect());
+        self
+    }
+
+    /// SQL run once when the database is opened, e.g. `CREATE TABLE IF NOT EXISTS`
+    pub fn with_setup(mut self, sql: impl Into<String>) -> Self {
+        self.setup = Some(sql.into());
+        self
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    fn statement(&self) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|column| quote(column)).collect();
+        let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+        let mut sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            quote(&self.table), columns.join(", "), placeholders.join(", ")
+        );
+
+        if let Some(keys) = &self.upsert_keys {
+            let updates: Vec<String> = self.columns.iter()
+                .filter(|column| !keys.contains(column))
+                .map(|column| format!("{0} = excluded.{0}", quote(column)))
+                .collect();
+            let keys: Vec<String> = keys.iter().map(|key| quote(key)).collect();
+            sql += &format!(" ON CONFLICT ({}) DO ", keys.join(", "));
+            sql += &if updates.is_empty() {
+                "NOTHING".to_string()
+            } else {
+                format!("UPDATE SET {}", updates.join(", "))
+            };
+        }
+        sql
+    }
+
+    fn connect(&self) -> rusqlite::Result<Connection> {
+        let connection = open(&self.path)?;
+        if let Some(setup) = &self.setup {
+            connection.execute_batch(setup)?;
+        }
+        Ok(connection)
+    }
+
+    fn write(&self, connection: &mut Connection, sql: &str, rows: &[T]) -> rusqlite::Result<()> {
+        let transaction = connection.transaction()?;
+        {
+            let mut statement = transaction.prepare_cached(sql)?;
+            for row in rows {
+                statement.execute(params_from_iter((self.mapping)(row)))?;
+            }
+        }
+        transaction.commit()
+    }
+}
+
+impl<T: Send + Sync + 'static> PipelineComponent for SqliteSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("SqliteSink requires a database, table and mapping. Use SqliteSink::new() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("SqliteSink starting, writing to {} in {}", self.table, self.path.display());
+
+        let mut connection = match self.connect() {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to open {}: {}", self.path.display(), e);
+                return;
+            }
+        };
+        let sql = self.statement();
+        let mut rows = Vec::with_capacity(self.batch_size);
+
+        while let Ok(msg) = input.recv() {
+            rows.push(msg.payload);
+            if rows.len() >= self.batch_size || input.is_empty() {
+                if let Err(e) = self.write(&mut connection, &sql, &rows) {
+                    error!("Failed to write {} rows to {}: {}", rows.len(), self.table, e);
+                }
+                rows.clear();
+            }
+        }
+
+        debug!("SqliteSink completed");
+    }
+}
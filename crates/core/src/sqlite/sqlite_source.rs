@@ -0,0 +1,158 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Receiver, Sender};
+use super::{open, quote, Row};
+use rusqlite::Connection;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, error};
+
+type RowMapping<T> = Arc<dyn Fn(&Row) -> rusqlite::Result<T> + Send + Sync>;
+
+/// Emits the rows of a SQLite table, polling it for new rows.
+///
+/// Rows are read in the order of a monotonic integer column, such as an
+/// autoincrement id or an insertion time, and each poll emits the rows past
+/// the last one emitted. The mapping given to `new` turns a row into a payload;
+/// rows it fails on are logged and skipped. Messages carry the table name as
+/// their source and the column value as their position, so a restarted source
+/// can resume after it with `with_start`.
+///
+/// The source runs until the pipeline stops, unless `with_idle_timeout` is set.
+pub struct SqliteSource<T> {
+    path: PathBuf,
+    table: String,
+    column: String,
+    mapping: RowMapping<T>,
+    start: i64,
+    batch_size: usize,
+    poll_interval: Duration,
+    idle_timeout: Option<Duration>,
+}
+
+impl<T> SqliteSource<T> {
+    pub fn new<P, F>(path: P, table: &str, column: &str, mapping: F) -> Self
+    where
+        P: Into<PathBuf>,
+        F: Fn(&Row) -> rusqlite::Result<T> + Send + Sync + 'static,
+    {
+        SqliteSource {
+            path: path.into(),
+            table: table.to_string(),
+            column: column.to_string(),
+            mapping: Arc::new(mapping),
+            start: i64::MIN,
+            batch_size: 1000,
+            poll_interval: Duration::from_millis(250),
+            idle_timeout: None,
+        }
+    }
+
+    /// Only emits rows whose column value is greater than `after`
+    pub fn with_start(mut self, after: i64) -> Self {
+        self.start = after;
+        self
+    }
+
+    /// The most rows read in one query
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Stops once no new row has been found for `idle_timeout`
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = Some(idle_timeout);
+        self
+    }
+
+    /// Reads the next rows after `last` with their column values. Rows the
+    /// mapping fails on are returned without a payload, so they are not read
+    /// again on every poll.
+    fn read(&self, connection: &Connection, last: i64) -> rusqlite::Result<Vec<(i64, Option<T>)>> {
+        let sql = format!(
+            "SELECT * FROM {0} WHERE {1} > ?1 ORDER BY {1} LIMIT ?2",
+            quote(&self.table), quote(&self.column)
+        );
+        let mut statement = connection.prepare_cached(&sql)?;
+        let mut rows = statement.query((last, self.batch_size as i64))?;
+
+        let mut records = Vec::new();
+        while let Some(row) = rows.next()? {
+            let value: i64 = row.get(self.column.as_str())?;
+            let record = (self.mapping)(row)
+                .map_err(|e| error!("Skipping row {} of {}: {}", value, self.table, e))
+                .ok();
+            records.push((value, record));
+        }
+        Ok(records)
+    }
+}
+
+impl<T: Send + Sync + 'static> PipelineComponent for SqliteSource<T> {
+    type Input = ();
+    type Output = T;
+
+    fn new() -> Self {
+        panic!("SqliteSource requires a database, table, column and mapping. Use SqliteSource::new() instead.")
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("SqliteSource starting, reading {} from {}", self.table, self.path.display());
+
+        let connection = match open(&self.path) {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to open {}: {}", self.path.display(), e);
+                return;
+            }
+        };
+        let mut last = self.start;
+        let mut last_row = Instant::now();
+
+        loop {
+            // A missing table is retried, as a sink may not have created it yet
+            let records = match self.read(&connection, last) {
+                Ok(records) => records,
+                Err(e) => {
+                    error!("Failed to read {}: {}", self.table, e);
+                    Vec::new()
+                }
+            };
+            let full = records.len() == self.batch_size;
+
+            if !records.is_empty() {
+                last_row = Instant::now();
+            }
+            for (value, record) in records {
+                last = value;
+                let Some(record) = record else {
+                    continue;
+                };
+                let mut msg = Message::new(record).with_source(self.table.clone());
+                if let Ok(position) = u64::try_from(value) {
+                    msg = msg.with_position(position);
+                }
+                if let Err(e) = output.send(msg) {
+                    error!("Failed to send row: {}", e);
+                    return;
+                }
+            }
+
+            if full {
+                continue;
+            } else if self.idle_timeout.is_some_and(|timeout| last_row.elapsed() >= timeout) {
+                break;
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+
+        debug!("SqliteSource completed");
+    }
+}
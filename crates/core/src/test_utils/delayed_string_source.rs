@@ -31,7 +31,7 @@ impl PipelineComponent for DelayedStringSource {
             tokio::time::sleep(*delay).await;
             
             // Send the item
-            if let Err(_) = output.send(Message::new(item.clone())) {
+            if output.send(Message::new(item.clone())).is_err() {
                 break;
             }
         }
@@ -0,0 +1,207 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message, Clock, MockClock};
+use crate::pipeline::channel::{self, PullSource, Receiver};
+use crossbeam_channel::{RecvError, TryRecvError};
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Runs a single component against a `MockClock`, so time based components such
+/// as `Window::with_duration` can be tested without sleeping.
+///
+/// `push` returns once the component has handled the message and is waiting for
+/// the next one, which makes `output` deterministic. This holds for components
+/// that handle messages in their `run` loop with a single input, read with
+/// `recv` or polled with `try_recv`; work spawned onto other tasks is not
+/// waited for.
+///
+/// ```no_run
+/// # use floq::functions::Window;
+/// # use floq::test_utils::ComponentHarness;
+/// # use std::time::Duration;
+/// let harness = ComponentHarness::new(Window::<i32>::with_duration(Duration::from_secs(10)));
+/// harness.push(1);
+/// harness.advance(Duration::from_secs(10));
+/// harness.push(2);
+/// assert_eq!(harness.output()[0].payload, vec![1, 2]);
+/// ```
+pub struct ComponentHarness<C: PipelineComponent> {
+    input: Arc<HarnessInput<C::Input>>,
+    output: Receiver<C::Output>,
+    clock: MockClock,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct InputState<T> {
+    queue: VecDeque<Message<T>>,
+    closed: bool,
+    // The component is waiting for input with nothing queued
+    waiting: bool,
+    // The component's `run` returned
+    finished: bool,
+}
+
+struct HarnessInput<T> {
+    state: Mutex<InputState<T>>,
+    changed: Condvar,
+}
+
+impl<T> HarnessInput<T> {
+    fn update(&self, f: impl FnOnce(&mut InputState<T>)) {
+        f(&mut self.state.lock().unwrap());
+        self.changed.notify_all();
+    }
+
+    /// Blocks until the component has handled everything queued
+    fn settle(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !(state.finished || (state.waiting && state.queue.is_empty())) {
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+}
+
+impl<T: Send> PullSource<T> for HarnessInput<T> {
+    fn recv(&self) -> Result<Message<T>, RecvError> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(msg) = state.queue.pop_front() {
+                state.waiting = false;
+                return Ok(msg);
+            }
+            if state.closed {
+                return Err(RecvError);
+            }
+            state.waiting = true;
+            self.changed.notify_all();
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    fn try_recv(&self) -> Result<Message<T>, TryRecvError> {
+        let mut state = self.state.lock().unwrap();
+        match state.queue.pop_front() {
+            Some(msg) => {
+                state.waiting = false;
+                Ok(msg)
+            }
+            None if state.closed => Err(TryRecvError::Disconnected),
+            None => {
+                // A polling component is idle once it finds nothing queued
+                state.waiting = true;
+                self.changed.notify_all();
+                Err(TryRecvError::Empty)
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.state.lock().unwrap().queue.len()
+    }
+}
+
+/// Marks the component finished even if it panics, so `settle` never hangs
+struct FinishGuard<T>(Arc<HarnessInput<T>>);
+
+impl<T> Drop for FinishGuard<T> {
+    fn drop(&mut self) {
+        self.0.update(|state| state.finished = true);
+    }
+}
+
+impl<C: PipelineComponent> ComponentHarness<C> {
+    /// Starts the component with a mock clock at 0
+    pub fn new(component: C) -> Self {
+        ComponentHarness::with_clock(component, MockClock::new(0))
+    }
+
+    pub fn with_clock(component: C, clock: MockClock) -> Self {
+        let input = Arc::new(HarnessInput {
+            state: Mutex::new(InputState {
+                queue: VecDeque::new(),
+                closed: false,
+                waiting: false,
+                finished: false,
+            }),
+            changed: Condvar::new(),
+        });
+        let (output_sender, output) = channel::channel();
+
+        let receiver = Receiver::from_source(input.clone());
+        let context = Arc::new(
+            ComponentContext::new(vec![output_sender.clone()], vec![receiver.clone()])
+                .with_clock(Arc::new(clock.clone()))
+        );
+        let guard = FinishGuard(input.clone());
+
+        let thread = std::thread::spawn(move || {
+            let _guard = guard;
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            runtime.block_on(component.run(receiver, output_sender, context));
+        });
+
+        let harness = ComponentHarness {
+            input,
+            output,
+            clock,
+            thread: Some(thread),
+        };
+        harness.input.settle();
+        harness
+    }
+
+    pub fn clock(&self) -> &MockClock {
+        &self.clock
+    }
+
+    /// Moves the mock clock forward
+    pub fn advance(&self, duration: Duration) {
+        self.clock.advance(duration);
+    }
+
+    /// Sends a payload with the current mock time as its event time and waits
+    /// until the component has handled it
+    pub fn push(&self, payload: C::Input) {
+        self.push_message(Message::with_event_time(payload, self.clock.now_millis()));
+    }
+
+    pub fn push_message(&self, msg: Message<C::Input>) {
+        self.input.update(|state| {
+            state.queue.push_back(msg);
+            state.waiting = false;
+        });
+        self.input.settle();
+    }
+
+    /// Returns what the component has emitted since the last call
+    pub fn output(&self) -> Vec<Message<C::Output>> {
+        std::iter::from_fn(|| self.output.try_recv().ok()).collect()
+    }
+
+    /// Closes the input, waits for the component to complete and returns the
+    /// rest of its output, e.g. windows flushed on completion
+    pub fn finish(mut self) -> Vec<Message<C::Output>> {
+        self.close();
+        self.output()
+    }
+
+    fn close(&mut self) {
+        self.input.update(|state| state.closed = true);
+        if let Some(thread) = self.thread.take() {
+            if let Err(e) = thread.join() {
+                std::panic::resume_unwind(e);
+            }
+        }
+    }
+}
+
+impl<C: PipelineComponent> Drop for ComponentHarness<C> {
+    fn drop(&mut self) {
+        if !std::thread::panicking() {
+            self.close();
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Receiver, Sender};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Collects whole messages, including their metadata, for assertions in tests
+pub struct MessageCollector<T> {
+    pub results: Arc<Mutex<Vec<Message<T>>>>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Send + Sync + 'static> PipelineComponent for MessageCollector<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        MessageCollector {
+            results: Arc::new(Mutex::new(Vec::new())),
+            _phantom: PhantomData,
+        }
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("MessageCollector starting");
+        while let Ok(msg) = input.recv() {
+            debug!("MessageCollector received message");
+            if let Ok(mut results) = self.results.lock() {
+                results.push(msg);
+            }
+        }
+        debug!("MessageCollector completed");
+    }
+}
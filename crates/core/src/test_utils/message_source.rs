@@ -0,0 +1,35 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::channel::{Receiver, Sender};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Emits prepared messages as is, so tests can control event times and sources
+pub struct MessageSource<T> {
+    messages: Vec<Message<T>>,
+}
+
+impl<T> MessageSource<T> {
+    pub fn from_messages(messages: Vec<Message<T>>) -> Self {
+        MessageSource { messages }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> PipelineComponent for MessageSource<T> {
+    type Input = ();
+    type Output = T;
+
+    fn new() -> Self {
+        MessageSource { messages: Vec::new() }
+    }
+
+    async fn run(&self, _input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("MessageSource starting");
+        for msg in &self.messages {
+            if output.send(msg.clone()).is_err() {
+                debug!("MessageSource failed to send message");
+                break;
+            }
+        }
+        debug!("MessageSource completed");
+    }
+}
@@ -4,10 +4,16 @@ mod number_source;
 mod string_source;
 mod string_collector;
 mod delayed_string_source;
+mod message_collector;
+mod message_source;
+mod harness;
 
 pub use number_source::NumberSource;
 pub use number_doubler::NumberDoubler;
 pub use number_collector::NumberCollector;
 pub use string_source::StringSource;
 pub use string_collector::StringCollector;
-pub use delayed_string_source::DelayedStringSource; 
\ No newline at end of file
+pub use delayed_string_source::DelayedStringSource;
+pub use message_collector::MessageCollector;
+pub use message_source::MessageSource;
+pub use harness::ComponentHarness;
\ No newline at end of file
@@ -0,0 +1,319 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message, Codec, JsonCodec};
+use crate::pipeline::codec::payload_bytes;
+use crate::pipeline::channel::{Sender, Receiver};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use crossbeam_channel::TryRecvError;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error};
+
+/// How `FileSink` writes payloads, one per line except for a CSV header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkFormat {
+    /// Strings as they are, other payloads as JSON
+    Text,
+    /// Messages as JSON through `JsonCodec`, including their timestamps and
+    /// source, so they can be read back with `JsonLines::<Message<T>>`
+    JsonLines,
+    /// Payloads as CSV rows, with a header row per file for structs and maps
+    Csv,
+}
+
+impl SinkFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            SinkFormat::Text => "txt",
+            SinkFormat::JsonLines => "jsonl",
+            SinkFormat::Csv => "csv",
+        }
+    }
+
+    fn encode<T: Serialize>(self, msg: &Message<T>, first_in_file: bool) -> io::Result<Vec<u8>> {
+        let mut line = match self {
+            SinkFormat::Text => payload_bytes(&msg.payload)?,
+            SinkFormat::JsonLines => JsonCodec.encode(msg)?,
+            SinkFormat::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(first_in_file)
+                    .from_writer(Vec::new());
+                writer.serialize(&msg.payload)?;
+                return writer.into_inner().map_err(|e| e.into_error());
+            }
+        };
+        line.push(b'\n');
+        Ok(line)
+    }
+}
+
+/// Checks a strftime pattern, as chrono only reports invalid ones when formatting
+pub fn validate_partitioning(pattern: &str) -> Result<(), String> {
+    if StrftimeItems::new(pattern).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("invalid partitioning pattern {:?}", pattern));
+    }
+    Ok(())
+}
+
+/// Writes messages to files in a directory.
+///
+/// Files are rolled once they reach `with_max_bytes` or have been open for
+/// `with_max_duration`. With `with_partitioning` files are written to
+/// subdirectories named after the event time of their messages in UTC, e.g.
+/// `date=%Y-%m-%d/hour=%H`.
+///
+/// A file is written under a hidden `.inprogress` name and renamed once it is
+/// rolled or the input completes, so readers of the directory never see a
+/// partial file. Files left in progress by a crash are not recovered. A line
+/// that fails to write is cut off again, so committed files only hold whole lines.
+pub struct FileSink<T> {
+    directory: PathBuf,
+    format: SinkFormat,
+    max_bytes: Option<u64>,
+    max_duration: Option<Duration>,
+    partitioning: Option<String>,
+    // Numbers files, so slots writing to the same directory never collide
+    sequence: AtomicU64,
+    _phantom: PhantomData<T>,
+}
+
+struct OpenFile {
+    writer: BufWriter<File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+    /// Length of the complete lines written, whether flushed or still buffered
+    bytes: u64,
+    opened_at: u64,
+}
+
+impl OpenFile {
+    /// Flushes the file and moves it to its final name
+    fn commit(mut self) -> io::Result<PathBuf> {
+        self.writer.flush()?;
+        commit_part(self.writer.get_ref(), &self.temp_path, &self.path)?;
+        Ok(self.path)
+    }
+
+    /// Cuts off what a failed write left of its line, keeping the complete lines
+    /// before it. A failed write never leaves any of its line in the buffer,
+    /// only on disk after the lines flushed before it.
+    fn truncate_to_last_line(self) -> io::Result<OpenFile> {
+        let (mut file, buffered) = self.writer.into_parts();
+        let buffered = buffered.map_err(|_| io::Error::other("writer panicked"))?;
+        let flushed = self.bytes - buffered.len() as u64;
+        file.set_len(flushed)?;
+        file.seek(SeekFrom::Start(flushed))?;
+
+        let mut writer = BufWriter::new(file);
+        writer.write_all(&buffered)?;
+        Ok(OpenFile { writer, ..self })
+    }
+}
+
+/// The hidden path a new file is written to and the path it is committed to
+pub(crate) fn part_paths(directory: &Path, now: u64, sequence: u64, extension: &str) -> (PathBuf, PathBuf) {
+    let name = format!("part-{}-{:05}.{}", now, sequence, extension);
+    (directory.join(format!(".{}.inprogress", name)), directory.join(name))
+}
+
+/// Syncs a completely written file and moves it to its final name
+pub(crate) fn commit_part(file: &File, temp_path: &Path, path: &Path) -> io::Result<()> {
+    file.sync_all()?;
+    std::fs::rename(temp_path, path)
+}
+
+// How often an idle sink flushes and checks whether files are due to roll
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What a sink writing rolling files is woken up with
+pub(crate) enum Polled<T> {
+    Message(Message<T>),
+    /// No message arrived within the poll interval
+    Idle,
+    Closed,
+}
+
+/// Takes the next message from `input` without blocking the runtime. Polled
+/// rather than received, so sinks get to roll their files on time while no
+/// messages arrive.
+pub(crate) async fn next_or_idle<T>(input: &Receiver<T>) -> Polled<T> {
+    match input.try_recv() {
+        Ok(msg) => Polled::Message(msg),
+        Err(TryRecvError::Empty) => {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            Polled::Idle
+        }
+        Err(TryRecvError::Disconnected) => Polled::Closed,
+    }
+}
+
+impl<T> FileSink<T> {
+    pub fn new<P: Into<PathBuf>>(directory: P, format: SinkFormat) -> Self {
+        FileSink {
+            directory: directory.into(),
+            format,
+            max_bytes: None,
+            max_duration: None,
+            partitioning: None,
+            sequence: AtomicU64::new(0),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Panics if `pattern` is not a valid strftime pattern
+    pub fn with_partitioning(mut self, pattern: impl Into<String>) -> Self {
+        let pattern = pattern.into();
+        if let Err(e) = validate_partitioning(&pattern) {
+            panic!("{}", e);
+        }
+        self.partitioning = Some(pattern);
+        self
+    }
+
+    fn partition(&self, event_timestamp: u64) -> PathBuf {
+        match &self.partitioning {
+            Some(pattern) => {
+                let time = DateTime::<Utc>::from_timestamp_millis(event_timestamp as i64).unwrap_or_default();
+                self.directory.join(time.format(pattern).to_string())
+            }
+            None => self.directory.clone(),
+        }
+    }
+
+    fn open(&self, partition: &Path, now: u64) -> io::Result<OpenFile> {
+        std::fs::create_dir_all(partition)?;
+        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
+        let (temp_path, path) = part_paths(partition, now, sequence, self.format.extension());
+        let file = File::create(&temp_path)?;
+        debug!("FileSink opened {}", temp_path.display());
+
+        Ok(OpenFile {
+            writer: BufWriter::new(file),
+            temp_path,
+            path,
+            bytes: 0,
+            opened_at: now,
+        })
+    }
+
+    fn commit(&self, file: OpenFile) {
+        match file.commit() {
+            Ok(path) => debug!("FileSink committed {}", path.display()),
+            Err(e) => error!("Failed to commit file in {}: {}", self.directory.display(), e),
+        }
+    }
+
+    fn write(&self, files: &mut HashMap<PathBuf, OpenFile>, msg: &Message<T>, now: u64) -> io::Result<()>
+    where
+        T: Serialize
+    {
+        let partition = self.partition(msg.event_timestamp);
+
+        // Encoded before a file is opened, so a message that cannot be encoded
+        // leaves no empty file behind
+        let first_in_file = files.get(&partition).is_none_or(|file| file.bytes == 0);
+        let line = self.format.encode(msg, first_in_file)?;
+
+        if !files.contains_key(&partition) {
+            let file = self.open(&partition, now)?;
+            files.insert(partition.clone(), file);
+        }
+        // A file that fails to write stays open, to be committed with the lines
+        // written before. If it cannot be cut back to those, it is discarded.
+        let file = files.get_mut(&partition).unwrap();
+        if let Err(e) = file.writer.write_all(&line) {
+            let file = files.remove(&partition).unwrap();
+            let temp_path = file.temp_path.clone();
+            match file.truncate_to_last_line() {
+                Ok(file) => {
+                    files.insert(partition, file);
+                }
+                Err(truncate_error) => {
+                    error!("Discarding {}, failed to truncate it: {}", temp_path.display(), truncate_error);
+                    let _ = std::fs::remove_file(&temp_path);
+                }
+            }
+            return Err(e);
+        }
+        file.bytes += line.len() as u64;
+
+        if self.max_bytes.is_some_and(|max_bytes| file.bytes >= max_bytes) {
+            self.commit(files.remove(&partition).unwrap());
+        }
+        Ok(())
+    }
+
+    /// Commits the files that have been open for `max_duration`
+    fn roll_expired(&self, files: &mut HashMap<PathBuf, OpenFile>, now: u64) {
+        let Some(max_duration) = self.max_duration else {
+            return;
+        };
+        let expired: Vec<PathBuf> = files.iter()
+            .filter(|(_, file)| now.saturating_sub(file.opened_at) >= max_duration.as_millis() as u64)
+            .map(|(partition, _)| partition.clone())
+            .collect();
+        for partition in expired {
+            self.commit(files.remove(&partition).unwrap());
+        }
+    }
+
+    fn flush(&self, files: &mut HashMap<PathBuf, OpenFile>) {
+        for file in files.values_mut() {
+            if let Err(e) = file.writer.flush() {
+                error!("Failed to flush {}: {}", file.temp_path.display(), e);
+            }
+        }
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> PipelineComponent for FileSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("FileSink requires a directory. Use FileSink::new() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("FileSink starting, writing to {}", self.directory.display());
+        let mut files = HashMap::new();
+
+        loop {
+            match next_or_idle(&input).await {
+                Polled::Message(msg) => {
+                    let now = context.clock().now_millis();
+                    self.roll_expired(&mut files, now);
+                    if let Err(e) = self.write(&mut files, &msg, now) {
+                        error!("Failed to write to {}: {}", self.directory.display(), e);
+                    }
+                }
+                Polled::Idle => {
+                    self.roll_expired(&mut files, context.clock().now_millis());
+                    self.flush(&mut files);
+                }
+                Polled::Closed => break,
+            }
+        }
+
+        for (_, file) in files.drain() {
+            self.commit(file);
+        }
+        debug!("FileSink completed");
+    }
+}
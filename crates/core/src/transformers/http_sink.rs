@@ -0,0 +1,380 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message, AggregateMetadata};
+use crate::pipeline::channel::{Sender, Receiver};
+use crossbeam_channel::TryRecvError;
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::marker::PhantomData;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tracing::{debug, error, warn};
+
+/// A request that failed permanently, with the payloads it carried
+#[derive(Debug, Clone)]
+pub struct FailedRequest<T> {
+    pub url: String,
+    pub payloads: Vec<T>,
+    /// The status of the last response, if the server answered at all
+    pub status: Option<u16>,
+    pub error: String,
+    pub attempts: u32,
+}
+
+/// POSTs payloads as JSON to an HTTP endpoint, one request per payload or, with
+/// `with_batch_size`, JSON arrays of up to that many payloads.
+///
+/// The URL and header values are templates in which `{source}`, `{position}`
+/// and `{event_time}` are replaced with the message's metadata, as for
+/// `MqttSink`. Only messages with the same URL and headers share a batch. A
+/// batch is sent once it is full or its first message has waited for
+/// `with_linger`.
+///
+/// Requests answered with a 5xx status or `429 Too Many Requests`, or that fail
+/// to connect or time out, are retried with exponential backoff, waiting as
+/// long as a `Retry-After` header asks instead when there is one, up to the
+/// maximum backoff. Requests that still fail after `with_max_retries` retries,
+/// are answered with any other error status or whose payloads cannot be
+/// serialized are sent to the dead letter sender if one is configured and
+/// dropped otherwise.
+pub struct HttpSink<T> {
+    url: String,
+    headers: Vec<(String, String)>,
+    batch_size: Option<usize>,
+    linger: Duration,
+    concurrency: usize,
+    retry: RetryPolicy,
+    timeout: Duration,
+    dead_letters: Option<Sender<FailedRequest<T>>>,
+    _phantom: PhantomData<T>,
+}
+
+#[derive(Clone, Copy)]
+struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl<T> HttpSink<T> {
+    pub fn new(url: impl Into<String>) -> Self {
+        HttpSink {
+            url: url.into(),
+            headers: Vec::new(),
+            batch_size: None,
+            linger: Duration::from_millis(100),
+            concurrency: 1,
+            retry: RetryPolicy {
+                max_retries: 3,
+                initial_backoff: Duration::from_millis(200),
+                max_backoff: Duration::from_secs(30),
+            },
+            timeout: Duration::from_secs(30),
+            dead_letters: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Sends a header with every request; the value may use the URL's placeholders
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sends payloads in JSON arrays of up to `batch_size`
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        assert!(batch_size > 0, "HttpSink requires a batch size of at least one");
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// How long a batch waits for more messages before it is sent anyway, 100
+    /// milliseconds by default, as measured by the component's clock
+    pub fn with_linger(mut self, linger: Duration) -> Self {
+        self.linger = linger;
+        self
+    }
+
+    /// The number of requests in flight at once, one by default
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        assert!(concurrency > 0, "HttpSink requires a concurrency of at least one");
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Retries a failed request up to `max_retries` times, 3 by default
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.retry.max_retries = max_retries;
+        self
+    }
+
+    /// The first retry waits `initial`, doubling for each retry after it up to
+    /// `max`. 200 milliseconds up to 30 seconds by default. A longer delay asked
+    /// for by `Retry-After` is cut to `max` as well.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.retry.initial_backoff = initial;
+        self.retry.max_backoff = max.max(initial);
+        self
+    }
+
+    /// How long a single attempt may take, 30 seconds by default
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Routes permanently failed requests to `dead_letters`. A request with a
+    /// single payload keeps the metadata of its message; a batch carries the
+    /// aggregate metadata of its messages.
+    pub fn with_dead_letters(mut self, dead_letters: Sender<FailedRequest<T>>) -> Self {
+        self.dead_letters = Some(dead_letters);
+        self
+    }
+}
+
+// How often the input is polled while batches wait to be sent
+const LINGER_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The rendered URL and headers of a request
+#[derive(PartialEq)]
+struct Target {
+    url: String,
+    headers: Vec<(String, String)>,
+}
+
+/// Messages waiting to be sent to the same target
+struct Batch<T> {
+    target: Target,
+    messages: Vec<Message<T>>,
+    started_at: u64,
+}
+
+/// Why a request failed
+struct Failure {
+    status: Option<u16>,
+    error: String,
+    attempts: u32,
+}
+
+/// What the request tasks share
+struct Delivery<T> {
+    client: reqwest::Client,
+    retry: RetryPolicy,
+    batched: bool,
+    dead_letters: Option<Sender<FailedRequest<T>>>,
+}
+
+/// The delay a `Retry-After` header asks for, given in seconds or as an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+}
+
+impl<T: Serialize> Delivery<T> {
+    async fn deliver(&self, batch: Batch<T>) {
+        let payloads: Vec<&T> = batch.messages.iter().map(|msg| &msg.payload).collect();
+        let body = if self.batched {
+            serde_json::to_vec(&payloads)
+        } else {
+            serde_json::to_vec(payloads[0])
+        };
+        let body = match body {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Failed to serialize payload for {}: {}", batch.target.url, e);
+                let failure = Failure { status: None, error: format!("failed to serialize payload: {}", e), attempts: 0 };
+                self.route_dead_letter(batch, failure);
+                return;
+            }
+        };
+
+        if let Err(failure) = self.send(&batch.target, body).await {
+            error!("Request to {} failed after {} attempts: {}", batch.target.url, failure.attempts, failure.error);
+            self.route_dead_letter(batch, failure);
+        }
+    }
+
+    async fn send(&self, target: &Target, body: Vec<u8>) -> Result<(), Failure> {
+        let mut backoff = self.retry.initial_backoff;
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            let mut request = self.client.post(&target.url)
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone());
+            for (name, value) in &target.headers {
+                request = request.header(name, value);
+            }
+
+            let (failure, delay) = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let delay = retry_after(response.headers());
+                    let text = response.text().await.unwrap_or_default();
+                    let failure = Failure {
+                        status: Some(status.as_u16()),
+                        error: format!("{} {}", status, text.trim()).trim_end().to_string(),
+                        attempts,
+                    };
+                    if !(status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS) {
+                        return Err(failure);
+                    }
+                    (failure, delay)
+                }
+                Err(e) => (Failure { status: None, error: e.to_string(), attempts }, None),
+            };
+
+            if attempts > self.retry.max_retries {
+                return Err(failure);
+            }
+            let delay = delay.map_or(backoff, |delay| delay.min(self.retry.max_backoff));
+            warn!("Request to {} failed: {}, retrying in {:?}", target.url, failure.error, delay);
+            tokio::time::sleep(delay).await;
+            backoff = (backoff * 2).min(self.retry.max_backoff);
+        }
+    }
+
+    fn route_dead_letter(&self, batch: Batch<T>, failure: Failure) {
+        let Some(dead_letters) = &self.dead_letters else {
+            warn!("HttpSink dropped {} payloads", batch.messages.len());
+            return;
+        };
+
+        let aggregate = (batch.messages.len() > 1).then(|| {
+            let event_time = batch.messages.iter().map(|msg| msg.event_timestamp).max().unwrap_or_default();
+            (event_time, AggregateMetadata::from_messages(&batch.messages))
+        });
+        let mut metadata = None;
+        let mut payloads = Vec::with_capacity(batch.messages.len());
+        for msg in batch.messages {
+            let (payload, parts) = msg.into_parts();
+            payloads.push(payload);
+            metadata.get_or_insert(parts);
+        }
+
+        let failed = FailedRequest {
+            url: batch.target.url,
+            payloads,
+            status: failure.status,
+            error: failure.error,
+            attempts: failure.attempts,
+        };
+        let msg = match (aggregate, metadata) {
+            (Some((event_time, aggregate)), _) => Message::with_aggregate(failed, event_time, aggregate),
+            (None, Some(metadata)) => metadata.with_new_payload(failed),
+            (None, None) => Message::new(failed),
+        };
+        if dead_letters.send(msg).is_err() {
+            warn!("HttpSink dead letter channel closed, dropping failed request");
+        }
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> PipelineComponent for HttpSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("HttpSink requires a URL. Use HttpSink::new() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("HttpSink starting, posting to {}", self.url);
+
+        let client = match reqwest::Client::builder().timeout(self.timeout).build() {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Failed to create HTTP client: {}", e);
+                return;
+            }
+        };
+        let delivery = Arc::new(Delivery {
+            client,
+            retry: self.retry,
+            batched: self.batch_size.is_some(),
+            dead_letters: self.dead_letters.clone(),
+        });
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+
+        // Receiving blocks, so it runs apart from the requests
+        let (url, headers, batch_size) = (self.url.clone(), self.headers.clone(), self.batch_size.unwrap_or(1));
+        let linger = self.linger.as_millis() as u64;
+        let runtime = tokio::runtime::Handle::current();
+        let permits = semaphore.clone();
+        let batcher = tokio::task::spawn_blocking(move || {
+            let dispatch = |batch: Batch<T>| {
+                // Waiting for a permit holds back the input while the endpoint is busy
+                let Ok(permit) = runtime.block_on(permits.clone().acquire_owned()) else {
+                    return;
+                };
+                let delivery = delivery.clone();
+                runtime.spawn(async move {
+                    delivery.deliver(batch).await;
+                    drop(permit);
+                });
+            };
+
+            let mut pending: Vec<Batch<T>> = Vec::new();
+            loop {
+                // Expired batches go out before the next message is taken, which
+                // could otherwise join them
+                let now = context.clock().now_millis();
+                let (expired, waiting) = pending.drain(..)
+                    .partition::<Vec<_>, _>(|batch| now.saturating_sub(batch.started_at) >= linger);
+                pending = waiting;
+                expired.into_iter().for_each(&dispatch);
+
+                // Polled while batches wait, so they are sent on time
+                let msg = if pending.is_empty() {
+                    match input.recv() {
+                        Ok(msg) => msg,
+                        Err(_) => break,
+                    }
+                } else {
+                    match input.try_recv() {
+                        Ok(msg) => msg,
+                        Err(TryRecvError::Empty) => {
+                            std::thread::sleep(LINGER_POLL_INTERVAL);
+                            continue;
+                        }
+                        Err(TryRecvError::Disconnected) => break,
+                    }
+                };
+
+                let target = Target {
+                    url: msg.render(&url),
+                    headers: headers.iter().map(|(name, value)| (name.clone(), msg.render(value))).collect(),
+                };
+                let index = match pending.iter().position(|batch| batch.target == target) {
+                    Some(index) => index,
+                    None => {
+                        let started_at = context.clock().now_millis();
+                        pending.push(Batch { target, messages: Vec::new(), started_at });
+                        pending.len() - 1
+                    }
+                };
+                pending[index].messages.push(msg);
+                if pending[index].messages.len() >= batch_size {
+                    dispatch(pending.remove(index));
+                }
+            }
+            pending.drain(..).for_each(&dispatch);
+        });
+        if let Err(e) = batcher.await {
+            error!("HttpSink failed: {:?}", e);
+        }
+
+        // Wait for the requests in flight before completing
+        if let Err(e) = semaphore.acquire_many(self.concurrency as u32).await {
+            error!("HttpSink semaphore closed: {:?}", e);
+        }
+        debug!("HttpSink completed");
+    }
+}
@@ -1,4 +1,4 @@
-use crate::pipeline::{PipelineComponent, ComponentContext, Message};
+use crate::pipeline::{PipelineComponent, ComponentContext};
 use crate::pipeline::channel::{Receiver, Sender};
 use tracing::{debug, error};
 use serde_json::{json, Value};
@@ -24,7 +24,7 @@ impl PipelineComponent for HuggingfaceEmbeddings {
     async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
         debug!("HuggingfaceEmbeddings starting");
         while let Ok(msg) = input.recv() {
-            let text = msg.payload.clone();
+            let (text, metadata) = msg.into_parts();
             debug!("Processing text for embeddings");
             
             // Format for feature-extraction pipeline
@@ -37,7 +37,7 @@ impl PipelineComponent for HuggingfaceEmbeddings {
 
             match self.get_embeddings(body).await {
                 Ok(embeddings) => {
-                    if let Err(e) = output.send(Message::with_new_payload(msg, embeddings)) {
+                    if let Err(e) = output.send(metadata.with_new_payload(embeddings)) {
                         error!("Failed to send embeddings: {:?}", e);
                         break;
                     }
@@ -1,8 +1,18 @@
 pub mod huggingface_embeddings;
 pub mod gemini_embeddings;
 pub mod printer_sink;
+pub mod record_tap;
+pub mod file_sink;
+pub mod parquet_sink;
+pub mod websocket_server_sink;
+pub mod http_sink;
 
 
 pub use gemini_embeddings::GeminiEmbeddings;
 pub use huggingface_embeddings::HuggingfaceEmbeddings;
-pub use printer_sink::PrinterSink;
\ No newline at end of file
+pub use printer_sink::PrinterSink;
+pub use record_tap::RecordTap;
+pub use file_sink::{FileSink, SinkFormat};
+pub use parquet_sink::ParquetSink;
+pub use websocket_server_sink::{WebSocketServerSink, SlowClientPolicy};
+pub use http_sink::{HttpSink, FailedRequest};
\ No newline at end of file
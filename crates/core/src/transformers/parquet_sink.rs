@@ -0,0 +1,282 @@
+use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::channel::{Sender, Receiver};
+use super::file_sink::{commit_part, next_or_idle, part_paths, Polled};
+use arrow_schema::{FieldRef, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+use serde_arrow::schema::{SchemaLike, TracingOptions};
+use std::fs::File;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+pub use parquet::basic::Compression;
+
+/// Writes messages to Parquet files in a directory, one row per payload.
+///
+/// Payloads are converted with serde, into the columns of `with_schema` or, by
+/// default, a schema inferred from the first row group. A field that is null
+/// in every row of that group has no type to infer, so the rows are kept and
+/// inferred again with the next group; payloads with fields that are often
+/// null need `with_schema`. Rows that do not fit the schema are logged and
+/// dropped. Rows are buffered into
+/// row groups of `with_row_group_size` rows, and files are rolled once they
+/// reach `with_max_bytes`, checked after each row group, or have been open for
+/// `with_max_duration`.
+///
+/// As with `FileSink`, files are written under a hidden `.inprogress` name and
+/// renamed once complete.
+pub struct ParquetSink<T> {
+    directory: PathBuf,
+    schema: Option<SchemaRef>,
+    row_group_size: usize,
+    compression: Compression,
+    max_bytes: Option<u64>,
+    max_duration: Option<Duration>,
+    sequence: AtomicU64,
+    _phantom: PhantomData<T>,
+}
+
+struct OpenFile {
+    writer: ArrowWriter<File>,
+    temp_path: PathBuf,
+    path: PathBuf,
+}
+
+impl OpenFile {
+    /// Writes the footer and moves the file to its final name
+    fn commit(self) -> Result<PathBuf, ParquetError> {
+        let file = self.writer.into_inner()?;
+        commit_part(&file, &self.temp_path, &self.path)?;
+        Ok(self.path)
+    }
+}
+
+// How many row groups of rows are held back while writes fail, before the
+// oldest rows are dropped
+const MAX_HELD_ROW_GROUPS: usize = 4;
+
+/// Rows waiting to be written and the file they go to
+struct State<T> {
+    fields: Option<Vec<FieldRef>>,
+    rows: Vec<T>,
+    // How many rows to buffer before writing them, raised while writes fail
+    write_at: usize,
+    file: Option<OpenFile>,
+    // When the first row of the current file arrived
+    started_at: Option<u64>,
+}
+
+impl<T> ParquetSink<T> {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Self {
+        ParquetSink {
+            directory: directory.into(),
+            schema: None,
+            row_group_size: 8192,
+            compression: Compression::SNAPPY,
+            max_bytes: None,
+            max_duration: None,
+            sequence: AtomicU64::new(0),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Writes the columns of `schema` instead of inferring them from the payloads
+    pub fn with_schema(mut self, schema: SchemaRef) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    pub fn with_row_group_size(mut self, row_group_size: usize) -> Self {
+        self.row_group_size = row_group_size.max(1);
+        self
+    }
+
+    /// Snappy by default
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    fn open(&self, fields: &[FieldRef], now: u64) -> Result<OpenFile, ParquetError> {
+        std::fs::create_dir_all(&self.directory)?;
+        let sequence = self.sequence.fetch_add(1, Ordering::SeqCst);
+        let (temp_path, path) = part_paths(&self.directory, now, sequence, "parquet");
+
+        let properties = WriterProperties::builder()
+            .set_compression(self.compression)
+            .set_max_row_group_size(self.row_group_size)
+            .build();
+        let schema = Arc::new(arrow_schema::Schema::new(fields.to_vec()));
+        let writer = ArrowWriter::try_new(File::create(&temp_path)?, schema, Some(properties))?;
+        debug!("ParquetSink opened {}", temp_path.display());
+
+        Ok(OpenFile { writer, temp_path, path })
+    }
+
+    fn commit(&self, file: OpenFile) {
+        match file.commit() {
+            Ok(path) => debug!("ParquetSink committed {}", path.display()),
+            Err(e) => error!("Failed to commit file in {}: {}", self.directory.display(), e),
+        }
+    }
+
+    /// Writes the buffered rows as a row group, rolling the file if it is full.
+    /// Rows that fail to write, for lack of a schema or a file, stay buffered
+    /// and are tried again with the next row group, up to `MAX_HELD_ROW_GROUPS`
+    /// row groups of them.
+    fn write_row_group(&self, state: &mut State<T>, now: u64) -> Result<(), ParquetError>
+    where
+        T: Serialize
+    {
+        if state.rows.is_empty() {
+            return Ok(());
+        }
+        let result = self.write_rows(state, now);
+        if result.is_err() {
+            let excess = state.rows.len().saturating_sub(self.row_group_size * MAX_HELD_ROW_GROUPS);
+            if excess > 0 {
+                error!("ParquetSink dropping {} rows held back by failed writes to {}", excess, self.directory.display());
+                state.rows.drain(..excess);
+            }
+        }
+        state.write_at = match result {
+            Ok(()) => self.row_group_size,
+            Err(_) => state.rows.len() + self.row_group_size,
+        };
+        result
+    }
+
+    fn write_rows(&self, state: &mut State<T>, now: u64) -> Result<(), ParquetError>
+    where
+        T: Serialize
+    {
+        let fields = match &state.fields {
+            Some(fields) => fields,
+            None => {
+                let options = TracingOptions::default().strings_as_large_utf8(false);
+                let fields = Vec::<FieldRef>::from_samples(&state.rows, options)
+                    .map_err(|e| ParquetError::General(format!(
+                        "cannot infer a schema from {} rows, set one with `with_schema`: {}", state.rows.len(), e
+                    )))?;
+                state.fields.insert(fields)
+            }
+        };
+        let batch = match serde_arrow::to_record_batch(fields, &state.rows) {
+            Ok(batch) => batch,
+            Err(_) => {
+                // Rows that cannot be converted would fail every row group they
+                // are in, so they are dropped and the others written
+                state.rows.retain(|row| match serde_arrow::to_record_batch(fields, &std::slice::from_ref(row)) {
+                    Ok(_) => true,
+                    Err(e) => {
+                        let row = serde_json::to_string(row).unwrap_or_else(|e| format!("<{}>", e));
+                        warn!("ParquetSink dropping a row that does not fit the schema: {}: {}", e, row);
+                        false
+                    }
+                });
+                if state.rows.is_empty() {
+                    return Ok(());
+                }
+                serde_arrow::to_record_batch(fields, &state.rows)
+                    .map_err(|e| ParquetError::External(Box::new(e)))?
+            }
+        };
+
+        let file = match state.file.as_mut() {
+            Some(file) => file,
+            None => state.file.insert(self.open(fields, now)?),
+        };
+        file.writer.write(&batch)?;
+        file.writer.flush()?;
+        state.rows.clear();
+
+        if self.max_bytes.is_some_and(|max_bytes| file.writer.bytes_written() as u64 >= max_bytes) {
+            self.roll(state);
+        }
+        Ok(())
+    }
+
+    fn roll(&self, state: &mut State<T>) {
+        if let Some(file) = state.file.take() {
+            self.commit(file);
+        }
+        state.started_at = None;
+    }
+
+    /// Writes and commits the current file once it has been open for `max_duration`
+    fn roll_expired(&self, state: &mut State<T>, now: u64)
+    where
+        T: Serialize
+    {
+        let expired = match (self.max_duration, state.started_at) {
+            (Some(max_duration), Some(started_at)) => now.saturating_sub(started_at) >= max_duration.as_millis() as u64,
+            _ => false,
+        };
+        if expired {
+            if let Err(e) = self.write_row_group(state, now) {
+                error!("Failed to write to {}: {}", self.directory.display(), e);
+            }
+            self.roll(state);
+        }
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static> PipelineComponent for ParquetSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("ParquetSink requires a directory. Use ParquetSink::new() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("ParquetSink starting, writing to {}", self.directory.display());
+        let mut state = State {
+            fields: self.schema.as_ref().map(|schema| schema.fields().iter().cloned().collect()),
+            rows: Vec::new(),
+            write_at: self.row_group_size,
+            file: None,
+            started_at: None,
+        };
+
+        loop {
+            match next_or_idle(&input).await {
+                Polled::Message(msg) => {
+                    let now = context.clock().now_millis();
+                    self.roll_expired(&mut state, now);
+                    state.started_at.get_or_insert(now);
+                    state.rows.push(msg.payload);
+                    if state.rows.len() >= state.write_at {
+                        if let Err(e) = self.write_row_group(&mut state, now) {
+                            error!("Failed to write to {}: {}", self.directory.display(), e);
+                        }
+                    }
+                }
+                Polled::Idle => self.roll_expired(&mut state, context.clock().now_millis()),
+                Polled::Closed => break,
+            }
+        }
+
+        if let Err(e) = self.write_row_group(&mut state, context.clock().now_millis()) {
+            error!("Failed to write to {}: {}", self.directory.display(), e);
+        }
+        self.roll(&mut state);
+        debug!("ParquetSink completed");
+    }
+}
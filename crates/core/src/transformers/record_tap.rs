@@ -0,0 +1,117 @@
+use crate::pipeline::{PipelineComponent, ComponentContext, Message, Codec, CborCodec};
+use crate::pipeline::channel::{Sender, Receiver};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error};
+
+/// Identifies a record log and its format version
+pub const RECORD_LOG_MAGIC: &[u8; 8] = b"FLOQREC\x02";
+
+/// Passes messages through unchanged while appending each one to a record log,
+/// which `ReplaySource` plays back.
+///
+/// The log starts with `RECORD_LOG_MAGIC`, followed by one record per message:
+/// the big-endian `u32` length of the encoded message, the big-endian `u64` time
+/// the tap received it and the message encoded with the tap's codec, CBOR unless
+/// set with `with_codec`. The whole message is kept, including its timestamps,
+/// source and aggregate metadata.
+///
+/// Failing to write the log is logged but does not stop messages from flowing.
+/// All slots of the tap append to the same log.
+pub struct RecordTap<T, C = CborCodec> {
+    path: PathBuf,
+    codec: C,
+    writer: Mutex<Option<BufWriter<File>>>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> RecordTap<T> {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        RecordTap {
+            path: path.into(),
+            codec: CborCodec,
+            writer: Mutex::new(None),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, C: Codec> RecordTap<T, C> {
+    /// Encodes messages with `codec`; the log must be replayed with the same codec
+    pub fn with_codec<D: Codec>(self, codec: D) -> RecordTap<T, D> {
+        RecordTap {
+            path: self.path,
+            codec,
+            writer: self.writer,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn open(&self) -> std::io::Result<BufWriter<File>> {
+        let mut writer = BufWriter::new(File::create(&self.path)?);
+        writer.write_all(RECORD_LOG_MAGIC)?;
+        Ok(writer)
+    }
+
+    fn record(&self, recorded_at: u64, msg: &Message<T>, flush: bool) -> std::io::Result<()>
+    where
+        T: Serialize
+    {
+        let record = self.codec.encode(msg)?;
+        let len = u32::try_from(record.len()).map_err(|_| std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("record of {} bytes does not fit the log's 32-bit length", record.len()),
+        ))?;
+
+        let mut writer = self.writer.lock().unwrap();
+        let writer = match writer.as_mut() {
+            Some(writer) => writer,
+            None => writer.insert(self.open()?),
+        };
+        writer.write_all(&len.to_be_bytes())?;
+        writer.write_all(&recorded_at.to_be_bytes())?;
+        writer.write_all(&record)?;
+        if flush {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Serialize + Send + Sync + 'static, C: Codec> PipelineComponent for RecordTap<T, C> {
+    type Input = T;
+    type Output = T;
+
+    fn new() -> Self {
+        panic!("RecordTap requires a log path. Use RecordTap::new() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, output: Sender<Self::Output>, context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("RecordTap starting, recording to {}", self.path.display());
+
+        while let Ok(msg) = input.recv() {
+            // Flushing once the queue is drained keeps the log current without
+            // a write per message under load
+            if let Err(e) = self.record(context.clock().now_millis(), &msg, input.is_empty()) {
+                error!("Failed to record message to {}: {}", self.path.display(), e);
+            }
+
+            if let Err(e) = output.send(msg) {
+                error!("Failed to forward recorded message: {:?}", e);
+                break;
+            }
+        }
+
+        if let Some(writer) = self.writer.lock().unwrap().as_mut() {
+            if let Err(e) = writer.flush() {
+                error!("Failed to flush {}: {}", self.path.display(), e);
+            }
+        }
+
+        debug!("RecordTap completed");
+    }
+}
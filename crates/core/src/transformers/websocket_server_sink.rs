@@ -0,0 +1,280 @@
+use crate::pipeline::{PipelineComponent, ComponentContext};
+use crate::pipeline::channel::{Sender, Receiver};
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, error, warn};
+
+/// What happens to a client that falls more than its buffer behind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowClientPolicy {
+    /// Skip the messages it missed and carry on with the latest ones
+    DropMessages,
+    /// Close the connection
+    Disconnect,
+}
+
+/// Serves messages to WebSocket clients, e.g. a browser dashboard.
+///
+/// Every client connected to the bound address receives each message,
+/// serialized as JSON with its timestamps and source. With `with_replay` a new
+/// client first receives the last messages sent before it connected.
+///
+/// Each client may fall up to `with_client_buffer` messages behind, after which
+/// it is handled according to `with_slow_clients`. A client that accepts no
+/// data for `with_send_timeout` is disconnected either way. Messages sent while
+/// no client is connected are dropped.
+///
+/// All slots of the sink share one server. Once the last slot completes, the
+/// sink sends the clients the messages they have not received yet and closes
+/// the connections.
+pub struct WebSocketServerSink<T> {
+    address: String,
+    replay: usize,
+    client_buffer: usize,
+    slow_clients: SlowClientPolicy,
+    send_timeout: Duration,
+    server: tokio::sync::Mutex<Option<Arc<Server>>>,
+    running: AtomicUsize,
+    _phantom: PhantomData<T>,
+}
+
+struct Server {
+    updates: broadcast::Sender<String>,
+    // The last messages for new clients, locked while subscribing so a client
+    // neither misses nor repeats a message sent at the same time
+    history: Mutex<VecDeque<String>>,
+    listener: JoinHandle<()>,
+    clients: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl<T> WebSocketServerSink<T> {
+    /// Listens on `address`, e.g. `127.0.0.1:9001`
+    pub fn bind(address: impl Into<String>) -> Self {
+        WebSocketServerSink {
+            address: address.into(),
+            replay: 0,
+            client_buffer: 1024,
+            slow_clients: SlowClientPolicy::DropMessages,
+            send_timeout: Duration::from_secs(10),
+            server: tokio::sync::Mutex::new(None),
+            running: AtomicUsize::new(0),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Sends new clients the last `count` messages
+    pub fn with_replay(mut self, count: usize) -> Self {
+        self.replay = count;
+        self
+    }
+
+    pub fn with_client_buffer(mut self, client_buffer: usize) -> Self {
+        self.client_buffer = client_buffer.max(1);
+        self
+    }
+
+    /// Drops messages for slow clients by default
+    pub fn with_slow_clients(mut self, policy: SlowClientPolicy) -> Self {
+        self.slow_clients = policy;
+        self
+    }
+
+    pub fn with_send_timeout(mut self, send_timeout: Duration) -> Self {
+        self.send_timeout = send_timeout;
+        self
+    }
+
+    /// Starts the server for the first slot, shared by the ones after it
+    async fn start(&self) -> std::io::Result<Arc<Server>> {
+        let mut server = self.server.lock().await;
+        if let Some(server) = server.as_ref() {
+            return Ok(server.clone());
+        }
+
+        let listener = TcpListener::bind(&self.address).await?;
+        debug!("WebSocketServerSink listening on {}", listener.local_addr()?);
+        let (updates, _) = broadcast::channel(self.client_buffer);
+
+        let started = Arc::new_cyclic(|weak: &std::sync::Weak<Server>| {
+            let weak = weak.clone();
+            let (policy, send_timeout) = (self.slow_clients, self.send_timeout);
+            let listener = tokio::spawn(async move {
+                loop {
+                    let (stream, peer) = match listener.accept().await {
+                        Ok(connection) => connection,
+                        Err(e) => {
+                            error!("Failed to accept connection: {}", e);
+                            continue;
+                        }
+                    };
+                    let Some(server) = weak.upgrade() else {
+                        break;
+                    };
+                    debug!("WebSocket client connected from {}", peer);
+                    // Subscribed before the handshake, so once connected a client
+                    // receives every message sent after it connected
+                    let (backlog, updates) = server.subscribe();
+                    let client = tokio::spawn(serve(stream, backlog, updates, policy, send_timeout));
+
+                    let mut clients = server.clients.lock().unwrap();
+                    clients.retain(|client| !client.is_finished());
+                    clients.push(client);
+                }
+            });
+            Server {
+                updates,
+                history: Mutex::new(VecDeque::new()),
+                listener,
+                clients: Mutex::new(Vec::new()),
+            }
+        });
+        *server = Some(started.clone());
+        Ok(started)
+    }
+}
+
+impl Server {
+    /// The messages to replay to a new client and its subscription to the ones after
+    fn subscribe(&self) -> (Vec<String>, broadcast::Receiver<String>) {
+        let history = self.history.lock().unwrap();
+        (history.iter().cloned().collect(), self.updates.subscribe())
+    }
+}
+
+type ClientWriter = SplitSink<WebSocketStream<TcpStream>, WsMessage>;
+
+/// Sends one message, failing if the client accepts nothing for `timeout`
+async fn send(write: &mut ClientWriter, text: String, timeout: Duration) -> Result<(), String> {
+    match tokio::time::timeout(timeout, write.send(WsMessage::Text(text))).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(format!("client went away: {}", e)),
+        Err(_) => Err(format!("client accepted nothing for {:?}", timeout)),
+    }
+}
+
+/// Sends the replayed and then the live messages to one client, until it
+/// disconnects or the server shuts down
+async fn serve(stream: TcpStream, backlog: Vec<String>, mut updates: broadcast::Receiver<String>, policy: SlowClientPolicy, send_timeout: Duration) {
+    let websocket = match tokio::time::timeout(send_timeout, tokio_tungstenite::accept_async(stream)).await {
+        Ok(Ok(websocket)) => websocket,
+        Ok(Err(e)) => {
+            warn!("WebSocket handshake failed: {}", e);
+            return;
+        }
+        Err(_) => {
+            warn!("WebSocket handshake timed out");
+            return;
+        }
+    };
+    let (mut write, mut read) = websocket.split();
+
+    for text in backlog {
+        if let Err(e) = send(&mut write, text, send_timeout).await {
+            debug!("Disconnecting WebSocket client: {}", e);
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(text) => {
+                    if let Err(e) = send(&mut write, text, send_timeout).await {
+                        debug!("Disconnecting WebSocket client: {}", e);
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => match policy {
+                    SlowClientPolicy::DropMessages => warn!("Slow WebSocket client missed {} messages", missed),
+                    SlowClientPolicy::Disconnect => {
+                        warn!("Disconnecting WebSocket client that fell {} messages behind", missed);
+                        break;
+                    }
+                },
+                Err(RecvError::Closed) => break,
+            },
+            // Messages from clients are ignored, but reading answers their pings
+            incoming = read.next() => match incoming {
+                Some(Ok(WsMessage::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    let mut websocket = match write.reunite(read) {
+        Ok(websocket) => websocket,
+        Err(_) => return,
+    };
+    let _ = tokio::time::timeout(send_timeout, websocket.close(None)).await;
+}
+
+impl<T: Serialize + Send + Sync + 'static> PipelineComponent for WebSocketServerSink<T> {
+    type Input = T;
+    type Output = ();
+
+    fn new() -> Self {
+        panic!("WebSocketServerSink requires an address. Use WebSocketServerSink::bind() instead.")
+    }
+
+    async fn run(&self, input: Receiver<Self::Input>, _output: Sender<Self::Output>, _context: Arc<ComponentContext<Self::Input, Self::Output>>) {
+        debug!("WebSocketServerSink starting");
+        self.running.fetch_add(1, Ordering::SeqCst);
+
+        match self.start().await {
+            Ok(server) => {
+                // Receiving blocks, so it runs apart from the client connections
+                let replay = self.replay;
+                let broadcaster = server.clone();
+                let result = tokio::task::spawn_blocking(move || {
+                    while let Ok(msg) = input.recv() {
+                        let text = match serde_json::to_string(&msg) {
+                            Ok(text) => text,
+                            Err(e) => {
+                                error!("Failed to serialize message: {}", e);
+                                continue;
+                            }
+                        };
+                        let mut history = broadcaster.history.lock().unwrap();
+                        if replay > 0 {
+                            if history.len() == replay {
+                                history.pop_front();
+                            }
+                            history.push_back(text.clone());
+                        }
+                        // Fails only while no client is connected
+                        let _ = broadcaster.updates.send(text);
+                    }
+                }).await;
+                if let Err(e) = result {
+                    error!("WebSocketServerSink failed: {:?}", e);
+                }
+            }
+            Err(e) => error!("Failed to listen on {}: {}", self.address, e),
+        }
+
+        // The last slot shuts the server down. Dropping it ends the broadcast, so
+        // the clients are sent what they have not received yet and disconnected.
+        if self.running.fetch_sub(1, Ordering::SeqCst) == 1 {
+            if let Some(server) = self.server.lock().await.take() {
+                server.listener.abort();
+                let clients = std::mem::take(&mut *server.clients.lock().unwrap());
+                drop(server);
+                for client in clients {
+                    let _ = client.await;
+                }
+            }
+        }
+        debug!("WebSocketServerSink completed");
+    }
+}
@@ -1,18 +1,30 @@
-use floq::pipeline::{PipelineTask, PipelineComponent, ComponentContext};
+use floq::pipeline::{PipelineTask, PipelineComponent, Message, AggregateMetadata, AutoscalePolicy, ScaleError};
+use floq::pipeline::{Codec, CodecError, JsonCodec, CborCodec, MessagePackCodec, ComponentContext};
+use floq::pipeline::channel::channel;
 use floq::test_utils::{
     NumberSource, NumberDoubler, NumberCollector, 
-    StringSource, StringCollector, DelayedStringSource
+    StringSource, StringCollector, MessageSource, MessageCollector,
+    DelayedStringSource, ComponentHarness
 };
 use floq::slots::round_robin_splitter::RoundRobinSplitter;
+use floq::slots::{Broadcaster, LeastLoadedSplitter, Merger};
 use floq::functions::filter::Filter;
 use floq::functions::map::Map;
+use floq::functions::{FlatMap, TryMap, AsyncMap, ParseJson, JsonPath};
 use floq::functions::reduce::Reduce;
 use floq::functions::window::Window;
-use tracing::debug;
-use std::time::Duration;
-use tokio::time::sleep;
-use crossbeam_channel::{Sender, Receiver};
+use floq::sources::{DirectorySource, ReplaySource, TailFileSource, FileSource, FileFormat, JsonLines, Csv, LengthPrefixed, HttpSource, SseSource};
+use floq::transformers::{RecordTap, FileSink, SinkFormat, ParquetSink, WebSocketServerSink, SlowClientPolicy, HttpSink};
+use floq::transformers::parquet_sink::Compression;
+use floq::remote::{RemoteSink, RemoteSource};
+use floq::sqlite::{SqliteSink, SqliteSource, Value as SqlValue};
+use floq::mqtt::{MqttSink, MqttSource, QoS};
+use floq::socket::{Framing, TcpSink, TcpSource, UdpSink, UdpSource};
+#[cfg(unix)]
+use floq::socket::{UnixSocketSink, UnixSocketSource};
+use floq::config::{ComponentRegistry, ConfigError, DynComponent, PipelineConfig, Value, ValueType};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[tokio::test]
 async fn test_simple_pipeline() {
@@ -64,7 +76,7 @@ async fn test_round_robin_splitter() {
     let results = collector.results.clone();
     let collector_task = PipelineTask::with_slots(collector, 2);
 
-    let mut splitter = RoundRobinSplitter::new();
+    let splitter = RoundRobinSplitter::new();
     let splitter_task = PipelineTask::new(splitter);
     
     // Create a pipeline with a splitter and two parallel collectors
@@ -247,3 +259,2025 @@ async fn test_count_based_window() {
     assert_eq!(*results, vec!["0,1", "2"]);
 }
 
+#[tokio::test]
+async fn test_window_preserves_metadata() {
+    let collector = MessageCollector::new();
+    let collector_results = collector.results.clone();
+
+    let source = MessageSource::from_messages(vec![
+        Message::with_event_time("a".to_string(), 1_000).with_source("mastodon"),
+        Message::with_event_time("b".to_string(), 3_000).with_source("bluesky"),
+        Message::with_event_time("c".to_string(), 2_000).with_source("mastodon"),
+    ]);
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(Window::with_count(3))
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    let results = collector_results.lock().unwrap();
+    assert_eq!(results.len(), 1);
+
+    let window = &results[0];
+    assert_eq!(window.payload, vec!["a", "b", "c"]);
+    assert_eq!(window.event_timestamp, 3_000);
+    assert_eq!(window.source_id, None);
+
+    let aggregate = window.aggregate.as_ref().unwrap();
+    assert_eq!(aggregate.window_start, 1_000);
+    assert_eq!(aggregate.window_end, 3_000);
+    assert_eq!(aggregate.count, 3);
+    assert_eq!(aggregate.sources.iter().collect::<Vec<_>>(), vec!["bluesky", "mastodon"]);
+}
+
+#[tokio::test]
+async fn test_reduce_preserves_metadata() {
+    let collector = MessageCollector::new();
+    let collector_results = collector.results.clone();
+
+    let source = MessageSource::from_messages(vec![
+        Message::with_event_time(1, 5_000).with_source("sensor"),
+        Message::with_event_time(2, 4_000).with_source("sensor"),
+    ]);
+
+    let reduce = Reduce::new(0, |acc: &mut i32, x: i32| {
+        *acc += x;
+    });
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(reduce)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    let results = collector_results.lock().unwrap();
+    assert_eq!(results.iter().map(|msg| msg.payload).collect::<Vec<_>>(), vec![1, 3]);
+
+    // Event time never moves backwards and the single source is kept
+    let last = &results[1];
+    assert_eq!(last.event_timestamp, 5_000);
+    assert_eq!(last.source_id.as_deref(), Some("sensor"));
+
+    let aggregate = last.aggregate.as_ref().unwrap();
+    assert_eq!(aggregate.window_start, 4_000);
+    assert_eq!(aggregate.window_end, 5_000);
+    assert_eq!(aggregate.count, 2);
+}
+
+#[tokio::test]
+async fn test_filter_generic_predicate() {
+    let collector = NumberCollector::new();
+    let collector_results = collector.results.clone();
+
+    // Keep even numbers from a numeric stream using the whole message
+    let filter = Filter::with_predicate(|msg: &Message<i32>| msg.payload % 2 == 0);
+
+    let pipeline = PipelineTask::new(NumberSource::new())
+        | PipelineTask::new(filter)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    assert_eq!(*collector_results.lock().unwrap(), vec![0, 2]);
+}
+
+#[tokio::test]
+async fn test_filter_combinators() {
+    let collector = MessageCollector::new();
+    let collector_results = collector.results.clone();
+
+    let source = MessageSource::from_messages(vec![
+        Message::with_event_time("rust".to_string(), 1_000).with_source("mastodon"),
+        Message::with_event_time("rust".to_string(), 2_000).with_source("bluesky"),
+        Message::with_event_time("go".to_string(), 3_000).with_source("bluesky"),
+        Message::with_event_time("python".to_string(), 4_000).with_source("bluesky"),
+    ]);
+
+    // (mentions rust and not from mastodon) or late python posts
+    let from_mastodon = Filter::with_predicate(|msg: &Message<String>| msg.source_id.as_deref() == Some("mastodon"));
+    let late = Filter::with_predicate(|msg: &Message<String>| msg.event_timestamp > 3_500);
+    let filter = Filter::with_pattern("rust").unwrap()
+        .and(!from_mastodon)
+        .or(Filter::with_lambda(|s| s.starts_with("py")).and(late));
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(filter)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    let results = collector_results.lock().unwrap();
+    let timestamps: Vec<u64> = results.iter().map(|msg| msg.event_timestamp).collect();
+    assert_eq!(timestamps, vec![2_000, 4_000]);
+}
+
+#[tokio::test]
+async fn test_flat_map() {
+    let collector = StringCollector::new();
+    let collector_results = collector.results.clone();
+
+    let source = MessageSource::from_messages(vec![
+        Message::new("hello floq".to_string()),
+        Message::new("".to_string()),
+        Message::new("streams are fun".to_string()),
+    ]);
+
+    let split = FlatMap::new(|text: String| {
+        text.split_whitespace().map(|word| word.to_string()).collect::<Vec<_>>()
+    });
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(split)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    assert_eq!(*collector_results.lock().unwrap(), vec!["hello", "floq", "streams", "are", "fun"]);
+}
+
+#[tokio::test]
+async fn test_try_map_routes_errors() {
+    let collector = NumberCollector::new();
+    let collector_results = collector.results.clone();
+    let (error_sender, error_receiver) = floq::pipeline::channel::channel();
+
+    let source = MessageSource::from_messages(vec![
+        Message::new("1".to_string()),
+        Message::with_event_time("x".to_string(), 42),
+        Message::new("3".to_string()),
+    ]);
+
+    let parse = TryMap::new(|text: String| text.parse::<i32>().map_err(|e| e.to_string()))
+        .with_errors(error_sender);
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(parse)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    assert_eq!(*collector_results.lock().unwrap(), vec![1, 3]);
+
+    let error = error_receiver.recv().unwrap();
+    assert_eq!(error.payload, "invalid digit found in string");
+    assert_eq!(error.event_timestamp, 42);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_async_map_ordered() {
+    let collector = NumberCollector::new();
+    let collector_results = collector.results.clone();
+
+    // Earlier items take longer, so completion order is the reverse of input order
+    let slow_double = AsyncMap::new(|num: i32| async move {
+        tokio::time::sleep(std::time::Duration::from_millis(30 - num as u64 * 10)).await;
+        num * 2
+    })
+    .with_concurrency(3);
+
+    let pipeline = PipelineTask::new(NumberSource::new())
+        | PipelineTask::new(slow_double)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    assert_eq!(*collector_results.lock().unwrap(), vec![0, 2, 4]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_async_map_unordered() {
+    let collector = NumberCollector::new();
+    let collector_results = collector.results.clone();
+
+    // Each item completes only once the results of the items after it have been
+    // emitted, so completion order is the reverse of input order. The gate is
+    // waited on off the runtime, as stages block their worker while receiving.
+    let gate = Arc::new((std::sync::Mutex::new(0), std::sync::Condvar::new()));
+    let turn = gate.clone();
+    let reverse_double = AsyncMap::new(move |num: i32| {
+        let turn = turn.clone();
+        async move {
+            tokio::task::spawn_blocking(move || {
+                let (emitted, changed) = &*turn;
+                let _emitted = changed.wait_while(emitted.lock().unwrap(), |emitted| *emitted != 2 - num).unwrap();
+            }).await.unwrap();
+            num * 2
+        }
+    })
+    .with_concurrency(3)
+    .with_ordered(false);
+    let count_emitted = Map::new(move |num: i32| {
+        let (emitted, changed) = &*gate;
+        *emitted.lock().unwrap() += 1;
+        changed.notify_all();
+        num
+    });
+
+    let pipeline = PipelineTask::new(NumberSource::new())
+        | PipelineTask::new(reverse_double)
+        | PipelineTask::new(count_emitted)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    assert_eq!(*collector_results.lock().unwrap(), vec![4, 2, 0]);
+}
+
+#[tokio::test]
+async fn test_async_map_emits_while_waiting_for_input() {
+    let (sender, input) = channel::<i32>();
+    let (output, results) = channel::<i32>();
+    let double = AsyncMap::new(|num: i32| async move { num * 2 }).with_ordered(false);
+    let context = Arc::new(ComponentContext::new(vec![output.clone()], vec![input.clone()]));
+    let running = tokio::spawn(async move { double.run(input, output, context).await });
+
+    // A slow source: the next item only arrives after a while, but the result
+    // of the first does not wait for it, even on a single worker
+    sender.send(Message::new(1)).unwrap();
+    let closing = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(3));
+        drop(sender);
+    });
+    let started = Instant::now();
+    let first = tokio::task::spawn_blocking(move || results.recv().unwrap().payload).await.unwrap();
+    assert_eq!(first, 2);
+    assert!(started.elapsed() < Duration::from_secs(2), "result waited {:?} for the next input", started.elapsed());
+
+    closing.join().unwrap();
+    running.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_broadcaster_shares_arc_payloads() {
+    let collector = MessageCollector::<Arc<str>>::new();
+    let collector_results = collector.results.clone();
+
+    let post: Arc<str> = Arc::from("a large post");
+    let source = MessageSource::from_messages(vec![Message::new(post.clone())]);
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(Broadcaster::new())
+        | PipelineTask::with_slots(collector, 2);
+
+    pipeline.run().await;
+
+    // Both slots receive the post without copying it
+    let results = collector_results.lock().unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|msg| Arc::ptr_eq(&msg.payload, &post)));
+}
+
+fn record_task(tasks: &std::sync::Mutex<Vec<tokio::task::Id>>) {
+    tasks.lock().unwrap().push(tokio::task::id());
+}
+
+#[tokio::test]
+async fn test_stateless_stages_are_fused() {
+    let collector = StringCollector::new();
+    let collector_results = collector.results.clone();
+    let first_tasks = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let second_tasks = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let (first, second) = (first_tasks.clone(), second_tasks.clone());
+    let pipeline = PipelineTask::new(NumberSource::new())
+        | PipelineTask::new(Map::new(move |num: i32| { record_task(&first); num * 10 }))
+        | PipelineTask::new(Filter::with_predicate(|msg: &Message<i32>| msg.payload > 0))
+        | PipelineTask::new(Map::new(move |num: i32| { record_task(&second); num.to_string() }))
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    assert_eq!(*collector_results.lock().unwrap(), vec!["10", "20"]);
+
+    // All three stateless stages ran in a single task
+    let first_tasks = first_tasks.lock().unwrap();
+    let second_tasks = second_tasks.lock().unwrap();
+    assert_eq!(first_tasks.len(), 3);
+    assert_eq!(second_tasks.len(), 2);
+    assert!(first_tasks.iter().chain(second_tasks.iter()).all(|id| *id == first_tasks[0]));
+}
+
+#[tokio::test]
+async fn test_fusion_opt_out() {
+    let collector = NumberCollector::new();
+    let collector_results = collector.results.clone();
+    let first_tasks = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let second_tasks = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let (first, second) = (first_tasks.clone(), second_tasks.clone());
+    let pipeline = PipelineTask::new(NumberSource::new())
+        | PipelineTask::new(Map::new(move |num: i32| { record_task(&first); num + 1 })).without_fusion()
+        | PipelineTask::new(Map::new(move |num: i32| { record_task(&second); num * 2 }))
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    assert_eq!(*collector_results.lock().unwrap(), vec![2, 4, 6]);
+    assert_ne!(first_tasks.lock().unwrap()[0], second_tasks.lock().unwrap()[0]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 12)]
+async fn test_rescale_running_stage() {
+    let collector = StringCollector::new();
+    let collector_results = collector.results.clone();
+    let slot_tasks = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let items = (0..30)
+        .map(|i| (i.to_string(), std::time::Duration::from_millis(10)))
+        .collect();
+
+    let tasks = slot_tasks.clone();
+    let stage = PipelineTask::with_slots(Map::new(move |text: String| { record_task(&tasks); text }), 2);
+    let scaler = stage.scaler();
+    assert_eq!(scaler.rescale(4), Err(ScaleError::NotDeployed));
+
+    let pipeline = PipelineTask::new(DelayedStringSource::new(items))
+        | PipelineTask::new(RoundRobinSplitter::new())
+        | stage
+        | PipelineTask::new(collector);
+
+    let rescale = async {
+        tokio::time::sleep(std::time::Duration::from_millis(80)).await;
+        scaler.rescale(4).unwrap();
+        assert_eq!(scaler.slots(), 4);
+        tokio::time::sleep(std::time::Duration::from_millis(80)).await;
+        assert_eq!(scaler.rescale(0), Err(ScaleError::BelowMinimum(1)));
+        scaler.rescale(1).unwrap();
+        assert_eq!(scaler.slots(), 1);
+
+        // Retired slots complete right away, while the source is still running
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        assert_eq!(scaler.running_slots(), 1);
+    };
+
+    tokio::join!(pipeline.run(), rescale);
+
+    // Nothing is lost or duplicated while slots come and go
+    let mut results: Vec<i32> = collector_results.lock().unwrap().iter().map(|s| s.parse().unwrap()).collect();
+    results.sort();
+    assert_eq!(results, (0..30).collect::<Vec<_>>());
+
+    let mut tasks = slot_tasks.lock().unwrap().clone();
+    tasks.sort();
+    tasks.dedup();
+    assert_eq!(tasks.len(), 4);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 12)]
+async fn test_autoscale_on_queue_depth() {
+    let collector = StringCollector::new();
+    let collector_results = collector.results.clone();
+    let slot_tasks = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    // Items arrive faster than two slots can handle them
+    let items = (0..60)
+        .map(|i| (i.to_string(), std::time::Duration::from_millis(2)))
+        .collect();
+
+    let tasks = slot_tasks.clone();
+    let slow_stage = PipelineTask::with_slots(Map::new(move |text: String| {
+        record_task(&tasks);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        text
+    }), 2);
+    let scaler = slow_stage.scaler();
+    let autoscaler = scaler.autoscale(AutoscalePolicy {
+        min_slots: 1,
+        max_slots: 4,
+        scale_up_depth: 2,
+        scale_down_depth: 0,
+        interval: std::time::Duration::from_millis(20),
+    });
+
+    let pipeline = PipelineTask::new(DelayedStringSource::new(items))
+        | PipelineTask::new(RoundRobinSplitter::new())
+        | slow_stage
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+    autoscaler.await.unwrap();
+
+    assert_eq!(collector_results.lock().unwrap().len(), 60);
+
+    let mut tasks = slot_tasks.lock().unwrap().clone();
+    tasks.sort();
+    tasks.dedup();
+    assert!(tasks.len() > 2, "expected the autoscaler to add slots, got {}", tasks.len());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 12)]
+async fn test_scaled_stage_is_not_fused() {
+    let collector = StringCollector::new();
+    let collector_results = collector.results.clone();
+
+    let items = (0..30)
+        .map(|i| (i.to_string(), std::time::Duration::from_millis(5)))
+        .collect();
+
+    // Both maps are stateless, but the first one must keep its own slots to be rescaled
+    let stage = PipelineTask::with_slots(Map::new(|text: String| text), 2);
+    let scaler = stage.scaler();
+    let autoscaler = scaler.autoscale(AutoscalePolicy {
+        min_slots: 2,
+        max_slots: 2,
+        interval: std::time::Duration::from_millis(20),
+        ..AutoscalePolicy::default()
+    });
+
+    let pipeline = PipelineTask::new(DelayedStringSource::new(items))
+        | PipelineTask::new(RoundRobinSplitter::new())
+        | stage
+        | PipelineTask::with_slots(Map::new(|text: String| text), 2)
+        | PipelineTask::new(collector);
+
+    let rescale = async {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let rescaler = scaler.clone();
+        tokio::task::spawn_blocking(move || rescaler.rescale(3)).await.unwrap().unwrap();
+        assert_eq!(scaler.slots(), 3);
+    };
+
+    tokio::join!(pipeline.run(), rescale);
+
+    // The autoscaler sees the stage complete instead of waiting for a deployment
+    tokio::time::timeout(std::time::Duration::from_secs(5), autoscaler).await
+        .expect("autoscaler did not stop")
+        .unwrap();
+    assert_eq!(collector_results.lock().unwrap().len(), 30);
+}
+
+// Runs 30 messages through two slots where the first message is far more
+// expensive than the rest, returning each message's latency in the slots
+async fn skewed_latencies<S>(splitter: S) -> Vec<Duration>
+where
+    S: PipelineComponent<Input = (String, Instant), Output = (String, Instant)>,
+{
+    let collector = MessageCollector::<Duration>::new();
+    let collector_results = collector.results.clone();
+
+    let items = (0..30)
+        .map(|i| (i.to_string(), Duration::from_millis(4)))
+        .collect();
+
+    let pipeline = PipelineTask::new(DelayedStringSource::new(items))
+        | PipelineTask::new(Map::new(|text: String| (text, Instant::now())))
+        | PipelineTask::new(splitter)
+        | PipelineTask::with_slots(Map::new(|(text, sent): (String, Instant)| {
+            let cost = if text == "0" { 250 } else { 2 };
+            std::thread::sleep(Duration::from_millis(cost));
+            sent.elapsed()
+        }), 2)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    let mut latencies: Vec<Duration> = collector_results.lock().unwrap()
+        .iter()
+        .map(|msg| msg.payload)
+        .collect();
+    latencies.sort();
+    latencies
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+async fn test_least_loaded_splitter_tail_latency() {
+    let round_robin = skewed_latencies(RoundRobinSplitter::new()).await;
+    let least_loaded = skewed_latencies(LeastLoadedSplitter::new()).await;
+
+    assert_eq!(round_robin.len(), 30);
+    assert_eq!(least_loaded.len(), 30);
+
+    // Round robin keeps queueing every other message behind the expensive one,
+    // while the least loaded splitter routes around the busy slot
+    let slow = Duration::from_millis(100);
+    let round_robin_slow = round_robin.iter().filter(|latency| **latency > slow).count();
+    let least_loaded_slow = least_loaded.iter().filter(|latency| **latency > slow).count();
+    assert!(round_robin_slow >= 10, "expected round robin to queue behind the slow slot, got {}", round_robin_slow);
+    assert!(least_loaded_slow <= 2, "expected at most 2 slow messages, got {}", least_loaded_slow);
+
+    let p90 = |latencies: &[Duration]| latencies[latencies.len() * 9 / 10];
+    assert!(p90(&least_loaded) < slow);
+    assert!(p90(&round_robin) > slow);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn test_merger_reads_all_inputs() {
+    let collector = MessageCollector::<i32>::new();
+    let collector_results = collector.results.clone();
+
+    let source = MessageSource::from_messages((1..=6).map(Message::new).collect());
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(RoundRobinSplitter::new())
+        | PipelineTask::with_slots(Merger::new(), 3)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    let mut results: Vec<i32> = collector_results.lock().unwrap().iter().map(|msg| msg.payload).collect();
+    results.sort();
+    assert_eq!(results, vec![1, 2, 3, 4, 5, 6]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 6)]
+async fn test_merger_event_time_order() {
+    let collector = MessageCollector::<u64>::new();
+    let collector_results = collector.results.clone();
+
+    // Split round robin, each input is in order but one runs ahead of the other
+    let source = MessageSource::from_messages(
+        [10, 1, 20, 2, 30, 3].into_iter()
+            .map(|time| Message::with_event_time(time, time))
+            .collect()
+    );
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(RoundRobinSplitter::new())
+        | PipelineTask::with_slots(Merger::new().with_ordered(true), 2)
+        | PipelineTask::new(collector);
+
+    pipeline.run().await;
+
+    let results: Vec<u64> = collector_results.lock().unwrap().iter().map(|msg| msg.event_timestamp).collect();
+    assert_eq!(results, vec![1, 2, 3, 10, 20, 30]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_merger_emits_in_order_while_inputs_are_open() {
+    let (first_sender, first) = channel::<u64>();
+    let (second_sender, second) = channel::<u64>();
+    let (output, merged) = channel::<u64>();
+
+    let merger = Merger::new().with_ordered(true);
+    let context = Arc::new(ComponentContext::new(vec![output.clone()], vec![first.clone(), second]));
+    let running = tokio::spawn(async move { merger.run(first, output, context).await });
+
+    for time in [1, 3, 5] {
+        first_sender.send(Message::with_event_time(time, time)).unwrap();
+    }
+    for time in [2, 4] {
+        second_sender.send(Message::with_event_time(time, time)).unwrap();
+    }
+
+    // Everything up to the watermark of 4 is emitted while both inputs are still open
+    let receiving = tokio::task::spawn_blocking(move || {
+        let received: Vec<u64> = (0..4).map(|_| merged.recv().unwrap().payload).collect();
+        (received, merged)
+    });
+    let (received, merged) = tokio::time::timeout(Duration::from_secs(5), receiving).await
+        .expect("merger held back messages behind the watermark")
+        .unwrap();
+    assert_eq!(received, vec![1, 2, 3, 4]);
+    assert!(merged.try_recv().is_err());
+
+    // 5 is released once the inputs complete
+    drop((first_sender, second_sender));
+    running.await.unwrap();
+    assert_eq!(merged.try_recv().unwrap().payload, 5);
+}
+
+// Registry with the built-ins plus a sink collecting into `results`
+fn registry_with_collector(results: Arc<std::sync::Mutex<Vec<String>>>) -> ComponentRegistry {
+    let mut registry = ComponentRegistry::default();
+    registry.register("collect", ValueType::Text, ValueType::None, move |_params| {
+        Ok(DynComponent::adapt(StringCollector { results: results.clone() }))
+    });
+    registry
+}
+
+fn write_temp_file(name: &str, contents: &str) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("floq_{}_{}", std::process::id(), name));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_pipeline_from_yaml_config() {
+    let results = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let input = write_temp_file("posts.txt", "Learning RUST\nCooking pasta\n<p>rust is fun</p>\n");
+
+    let config = PipelineConfig::from_yaml(&format!(r#"
+stages:
+  - type: file
+    path: {}
+  - type: strip_html
+  - type: lowercase
+  - type: filter
+    pattern: rust
+  - type: collect
+"#, input.display())).unwrap();
+
+    let pipeline = registry_with_collector(results.clone()).build(&config).unwrap();
+    pipeline.run().await;
+
+    assert_eq!(*results.lock().unwrap(), vec!["learning rust", "rust is fun"]);
+    std::fs::remove_file(input).unwrap();
+}
+
+#[tokio::test]
+async fn test_adapted_component_counts_dropped_inputs() {
+    // Text cannot be given to a component taking batches
+    let component = DynComponent::adapt(Map::new(|batch: Vec<String>| batch)).with_name("stages[1] (count)");
+    let (sender, input) = channel::<Value>();
+    let (output, results) = channel::<Value>();
+    sender.send(Message::new(Value::Text("not a batch".to_string()))).unwrap();
+    sender.send(Message::new(Value::Batch(vec!["a".to_string()]))).unwrap();
+    drop(sender);
+
+    let context = Arc::new(ComponentContext::new(vec![output.clone()], vec![input.clone()]));
+    component.run(input, output, context).await;
+    let output: Vec<Value> = std::iter::from_fn(|| results.recv().ok().map(|msg| msg.payload)).collect();
+
+    assert_eq!(output, vec![Value::Batch(vec!["a".to_string()])]);
+    assert_eq!(component.dropped(), 1);
+    assert_eq!(component.name(), "stages[1] (count)");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_pipeline_from_toml_config() {
+    let results = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let input = write_temp_file("words.txt", "a\nb\nc\nd\n");
+
+    let config = PipelineConfig::from_toml(&format!(r#"
+[[stages]]
+type = "file"
+path = "{}"
+
+[[stages]]
+type = "window"
+count = 2
+
+[[stages]]
+type = "join"
+separator = "+"
+
+[[stages]]
+type = "collect"
+"#, input.display())).unwrap();
+
+    let pipeline = registry_with_collector(results.clone()).build(&config).unwrap();
+    pipeline.run().await;
+
+    assert_eq!(*results.lock().unwrap(), vec!["a+b", "c+d"]);
+    std::fs::remove_file(input).unwrap();
+}
+
+#[test]
+fn test_config_validation() {
+    let registry = ComponentRegistry::default();
+    let validate = |yaml: &str| registry.validate(&PipelineConfig::from_yaml(yaml).unwrap());
+
+    assert_eq!(
+        validate("stages:\n  - type: bluesky\n  - type: window\n    count: 5\n  - type: round_robin\n  - type: join\n    slots: 2\n"),
+        Ok(vec![ValueType::Text, ValueType::Batch, ValueType::Batch, ValueType::Text])
+    );
+
+    // A batch cannot be printed without joining it first
+    assert_eq!(
+        validate("stages:\n  - type: bluesky\n  - type: window\n    count: 5\n  - type: printer\n"),
+        Err(ConfigError::TypeMismatch {
+            stage: 2,
+            kind: "printer".to_string(),
+            expected: ValueType::Text,
+            found: ValueType::Batch,
+        })
+    );
+
+    // Pipelines start with a source
+    assert!(matches!(
+        validate("stages:\n  - type: lowercase\n"),
+        Err(ConfigError::TypeMismatch { stage: 0, .. })
+    ));
+
+    // Nothing can follow a sink, not even another source
+    assert_eq!(
+        validate("stages:\n  - type: bluesky\n  - type: printer\n  - type: bluesky\n  - type: printer\n"),
+        Err(ConfigError::AfterSink { stage: 2, kind: "bluesky".to_string() })
+    );
+    assert_eq!(
+        validate("stages:\n  - type: bluesky\n  - type: printer\n  - type: lowercase\n"),
+        Err(ConfigError::AfterSink { stage: 2, kind: "lowercase".to_string() })
+    );
+
+    // Sources only start a pipeline
+    assert_eq!(
+        validate("stages:\n  - type: bluesky\n  - type: bluesky\n  - type: printer\n"),
+        Err(ConfigError::SourceNotFirst { stage: 1, kind: "bluesky".to_string() })
+    );
+
+    assert_eq!(
+        validate("stages:\n  - type: bluesky\n  - type: upcase\n"),
+        Err(ConfigError::UnknownComponent { stage: 1, kind: "upcase".to_string() })
+    );
+
+    // Parameters are checked by the components, before any stage is started
+    let config = PipelineConfig::from_yaml("stages:\n  - type: bluesky\n  - type: filter\n    pattern: \"(\"\n").unwrap();
+    assert!(matches!(registry.validate(&config), Err(ConfigError::InvalidParams { stage: 1, .. })));
+    assert!(matches!(registry.build(&config), Err(ConfigError::InvalidParams { stage: 1, .. })));
+}
+
+#[test]
+fn test_time_window_with_mock_clock() {
+    let harness = ComponentHarness::new(Window::<i32>::with_duration(Duration::from_secs(10)));
+
+    harness.push(1);
+    harness.advance(Duration::from_secs(4));
+    harness.push(2);
+    assert!(harness.output().is_empty());
+
+    // The window closes with the first message after ten seconds
+    harness.advance(Duration::from_secs(6));
+    harness.push(3);
+    let output = harness.output();
+    assert_eq!(output.len(), 1);
+    assert_eq!(output[0].payload, vec![1, 2, 3]);
+    let window = output[0].aggregate.as_ref().unwrap();
+    assert_eq!((window.window_start, window.window_end), (0, 10_000));
+
+    harness.advance(Duration::from_secs(1));
+    harness.push(4);
+    let rest = harness.finish();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].payload, vec![4]);
+}
+
+/// A payload that cannot be cloned
+#[derive(Debug, PartialEq)]
+struct Unique(i32);
+
+#[test]
+fn test_windows_move_payloads_without_clone() {
+    let harness = ComponentHarness::new(Window::<Unique>::with_count(2));
+    for value in 1..=3 {
+        harness.push(Unique(value));
+    }
+    let batches: Vec<Vec<Unique>> = harness.finish().into_iter().map(|msg| msg.payload).collect();
+    assert_eq!(batches, vec![vec![Unique(1), Unique(2)], vec![Unique(3)]]);
+
+    let harness = ComponentHarness::new(Window::<Unique>::with_duration(Duration::from_secs(10)));
+    harness.push(Unique(1));
+    harness.advance(Duration::from_secs(10));
+    harness.push(Unique(2));
+    let batches: Vec<Vec<Unique>> = harness.finish().into_iter().map(|msg| msg.payload).collect();
+    assert_eq!(batches, vec![vec![Unique(1), Unique(2)]]);
+}
+
+#[test]
+fn test_sliding_window_with_mock_clock() {
+    let harness = ComponentHarness::new(Window::<i32>::with_sliding_window(
+        Duration::from_secs(10),
+        Duration::from_secs(5),
+    ));
+
+    for value in 1..=4 {
+        harness.push(value);
+        harness.advance(Duration::from_secs(5));
+    }
+
+    // Slides at 5s, 10s and 15s; by 15s the first message fell out of the window
+    let payloads: Vec<Vec<i32>> = harness.output().into_iter().map(|msg| msg.payload).collect();
+    assert_eq!(payloads, vec![vec![1, 2], vec![1, 2, 3], vec![2, 3, 4]]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_record_and_replay() {
+    let log = std::env::temp_dir().join(format!("floq_{}_replay.rec", std::process::id()));
+
+    // Record three messages 200ms apart on the mock clock
+    let harness = ComponentHarness::new(RecordTap::<String>::new(&log));
+    for (i, text) in ["first", "second", "third"].into_iter().enumerate() {
+        harness.push_message(Message::with_event_time(text.to_string(), 1_000 + i as u64).with_source("bluesky"));
+        harness.advance(Duration::from_millis(200));
+    }
+    assert_eq!(harness.finish().len(), 3);
+
+    let replay = |source: ReplaySource<String>| async move {
+        let collector = MessageCollector::new();
+        let results = collector.results.clone();
+        let started = Instant::now();
+        (PipelineTask::new(source) | PipelineTask::new(collector)).run().await;
+        let messages = std::mem::take(&mut *results.lock().unwrap());
+        (messages, started.elapsed())
+    };
+
+    let (messages, _) = replay(ReplaySource::new(&log)).await;
+    let replayed: Vec<(String, u64, Option<String>)> = messages.into_iter()
+        .map(|msg| (msg.payload, msg.event_timestamp, msg.source_id))
+        .collect();
+    assert_eq!(replayed, vec![
+        ("first".to_string(), 1_000, Some("bluesky".to_string())),
+        ("second".to_string(), 1_001, Some("bluesky".to_string())),
+        ("third".to_string(), 1_002, Some("bluesky".to_string())),
+    ]);
+
+    // 400ms of recorded gaps take 200ms at twice the speed
+    let (messages, elapsed) = replay(ReplaySource::new(&log).with_speed(2.0)).await;
+    assert_eq!(messages.len(), 3);
+    assert!(elapsed >= Duration::from_millis(200), "replayed in {:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(400), "replayed in {:?}", elapsed);
+
+    // A corrupt length at the end of the log is not allocated, replay stops there
+    let mut corrupt = u32::MAX.to_be_bytes().to_vec();
+    corrupt.extend_from_slice(&2_000u64.to_be_bytes());
+    corrupt.extend_from_slice(b"cut");
+    {
+        use std::io::Write;
+        std::fs::OpenOptions::new().append(true).open(&log).unwrap().write_all(&corrupt).unwrap();
+    }
+    let (messages, _) = replay(ReplaySource::new(&log)).await;
+    assert_eq!(messages.len(), 3);
+
+    std::fs::remove_file(log).unwrap();
+}
+
+fn assert_codec_round_trip<C: Codec>(codec: C) {
+    let mut msg = Message::with_aggregate(
+        vec!["a".to_string(), "b".to_string()],
+        1_500,
+        AggregateMetadata::default().with_bounds(1_000, 2_000),
+    ).with_source("mastodon");
+    msg.aggregate.as_mut().unwrap().sources.insert("bluesky".to_string());
+
+    let decoded: Message<Vec<String>> = codec.decode(&codec.encode(&msg).unwrap()).unwrap();
+    assert_eq!(decoded.payload, msg.payload);
+    assert_eq!(decoded.event_timestamp, msg.event_timestamp);
+    assert_eq!(decoded.ingestion_timestamp, msg.ingestion_timestamp);
+    assert_eq!(decoded.source_id, msg.source_id);
+    assert_eq!(decoded.aggregate, msg.aggregate);
+
+    assert!(matches!(codec.decode::<String>(b"\xff\x00"), Err(CodecError::Decode { .. })));
+}
+
+#[test]
+fn test_codecs_round_trip_messages() {
+    assert_codec_round_trip(JsonCodec);
+    assert_codec_round_trip(CborCodec);
+    assert_codec_round_trip(MessagePackCodec);
+
+    // Messages encoded before lineage and positions were tracked still decode,
+    // also when their fields were encoded in order rather than by name
+    let msg: Message<String> = JsonCodec
+        .decode(br#"{"payload":"old","event_timestamp":1,"ingestion_timestamp":2,"source_id":null}"#)
+        .unwrap();
+    assert_eq!((msg.payload.as_str(), msg.aggregate, msg.position), ("old", None, None));
+    let positional = rmp_serde::to_vec(&("old", 1u64, 2u64, None::<String>)).unwrap();
+    let msg: Message<String> = MessagePackCodec.decode(&positional).unwrap();
+    assert_eq!((msg.payload.as_str(), msg.aggregate, msg.position), ("old", None, None));
+}
+
+// The sending process of `test_remote_channel_between_processes`, which starts
+// this test binary again to run it
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[ignore]
+async fn remote_sender_process() {
+    let Ok(address) = std::env::var("FLOQ_REMOTE_ADDRESS") else {
+        return;
+    };
+    let messages = (0..500).map(|i| Message::with_event_time(i, i as u64).with_source("sender")).collect();
+    let pipeline = PipelineTask::new(MessageSource::from_messages(messages))
+        | PipelineTask::new(RemoteSink::<i32>::connect(address).with_max_backoff(Duration::from_millis(100)));
+    pipeline.run().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_remote_channel_between_processes() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let mut sender = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["remote_sender_process", "--exact", "--ignored", "--quiet"])
+        .env("FLOQ_REMOTE_ADDRESS", &address)
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // The sender is started first and waits in the backlog until the receiver accepts
+    let collector = MessageCollector::new();
+    let results = collector.results.clone();
+    // A small window makes the sender wait for credits many times
+    let pipeline = PipelineTask::new(RemoteSource::<i32>::from_listener(listener).unwrap().with_window(16))
+        | PipelineTask::new(collector);
+    pipeline.run().await;
+
+    assert!(sender.wait().unwrap().success());
+    let received: Vec<(i32, u64, Option<String>)> = results.lock().unwrap().iter()
+        .map(|msg| (msg.payload, msg.event_timestamp, msg.source_id.clone()))
+        .collect();
+    let expected: Vec<(i32, u64, Option<String>)> = (0..500)
+        .map(|i| (i, i as u64, Some("sender".to_string())))
+        .collect();
+    assert_eq!(received, expected);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_remote_sink_drops_items_too_large_for_a_frame() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let collector = MessageCollector::new();
+    let results = collector.results.clone();
+    let source = RemoteSource::<String>::from_listener(listener).unwrap();
+    let receiver = tokio::spawn(async move {
+        let pipeline = PipelineTask::new(source) | PipelineTask::new(collector);
+        pipeline.run().await;
+    });
+
+    // The receiver would refuse the large frame and drop the connection, over
+    // and over if the sink kept resending it
+    let texts = vec!["before".to_string(), "x".repeat(65 * 1024 * 1024), "after".to_string()];
+    let sender = PipelineTask::new(MessageSource::from_messages(texts.into_iter().map(Message::new).collect()))
+        | PipelineTask::new(RemoteSink::<String>::connect(address).with_max_backoff(Duration::from_millis(100)));
+    tokio::time::timeout(Duration::from_secs(20), sender.run()).await.unwrap();
+
+    receiver.await.unwrap();
+    let received: Vec<String> = results.lock().unwrap().iter().map(|msg| msg.payload.clone()).collect();
+    assert_eq!(received, vec!["before", "after"]);
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+struct Post {
+    user: String,
+    tag: Option<String>,
+}
+
+#[tokio::test]
+async fn test_parse_json_projects_fields() {
+    let collector = MessageCollector::new();
+    let results = collector.results.clone();
+    let (error_sender, error_receiver) = floq::pipeline::channel::channel();
+
+    let source = MessageSource::from_messages(vec![
+        Message::new(r#"{"account": {"username": "ana"}, "tags": [{"name": "rust"}]}"#.to_string()),
+        Message::with_event_time("{not json".to_string(), 42),
+        Message::new(r#"{"account": {"username": "bo"}, "tags": []}"#.to_string()),
+        Message::new(r#"{"account": {}}"#.to_string()),
+    ]);
+
+    let parse = ParseJson::<Post>::new()
+        .with_field("user", "$.account.username")
+        .with_field("tag", "$.tags[0].name")
+        .with_errors(error_sender);
+
+    let pipeline = PipelineTask::new(source)
+        | PipelineTask::new(parse)
+        | PipelineTask::new(collector);
+    pipeline.run().await;
+
+    let posts: Vec<Post> = results.lock().unwrap().iter().map(|msg| msg.payload.clone()).collect();
+    assert_eq!(posts, vec![
+        Post { user: "ana".to_string(), tag: Some("rust".to_string()) },
+        Post { user: "bo".to_string(), tag: None },
+    ]);
+
+    // Invalid JSON and documents not matching `Post` are routed with their line
+    let errors: Vec<_> = std::iter::from_fn(|| error_receiver.try_recv().ok()).collect();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].payload.line, "{not json");
+    assert_eq!(errors[0].event_timestamp, 42);
+    assert_eq!(errors[1].payload.line, r#"{"account": {}}"#);
+
+    let document = serde_json::json!({"a": {"b c": [1, {"d": true}]}});
+    assert_eq!(JsonPath::parse("$.a['b c'][1].d").unwrap().get(&document), Some(&serde_json::json!(true)));
+    assert_eq!(JsonPath::parse("a").unwrap().get(&document), document.get("a"));
+    assert!(JsonPath::parse("$.a[*]").is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_json_stage_in_config() {
+    let results = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let input = write_temp_file("events.jsonl", "{\"kind\": \"post\", \"author\": {\"handle\": \"ana\"}}\nnot json\n");
+
+    let config = PipelineConfig::from_yaml(&format!(r#"
+stages:
+  - type: file
+    path: {}
+  - type: json
+    fields:
+      handle: $.author.handle
+      kind: kind
+  - type: collect
+"#, input.display())).unwrap();
+
+    let pipeline = registry_with_collector(results.clone()).build(&config).unwrap();
+    pipeline.run().await;
+
+    assert_eq!(*results.lock().unwrap(), vec![r#"{"handle":"ana","kind":"post"}"#]);
+    std::fs::remove_file(input).unwrap();
+}
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("floq_{}_{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn append(path: &std::path::Path, text: &str) {
+    use std::io::Write;
+    std::fs::OpenOptions::new().append(true).create(true).open(path).unwrap()
+        .write_all(text.as_bytes()).unwrap();
+}
+
+async fn collect_lines<S: PipelineComponent<Input = (), Output = String>>(source: S) -> Vec<Message<String>> {
+    let collector = MessageCollector::new();
+    let results = collector.results.clone();
+    (PipelineTask::new(source) | PipelineTask::new(collector)).run().await;
+    let lines = results.lock().unwrap().clone();
+    lines
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_tail_file_source_follows_and_resumes() {
+    let dir = temp_dir("tail");
+    let log = dir.join("app.log");
+    let offsets = dir.join("offsets.json");
+    std::fs::write(&log, "a\nb\n").unwrap();
+
+    let tail = || TailFileSource::new(&log)
+        .with_offsets(&offsets)
+        .with_poll_interval(Duration::from_millis(10))
+        .with_idle_timeout(Duration::from_millis(300));
+    let payloads = |lines: Vec<Message<String>>| lines.into_iter().map(|msg| msg.payload).collect::<Vec<_>>();
+
+    // Appends, a line written in two parts and a rotation while tailing
+    let writer_log = log.clone();
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        append(&writer_log, "c\npart");
+        std::thread::sleep(Duration::from_millis(50));
+        append(&writer_log, "ial\n");
+        std::thread::sleep(Duration::from_millis(50));
+        std::fs::rename(&writer_log, writer_log.with_extension("log.1")).unwrap();
+        std::fs::write(&writer_log, "d\n").unwrap();
+    });
+    assert_eq!(payloads(collect_lines(tail()).await), vec!["a", "b", "c", "partial", "d"]);
+    writer.join().unwrap();
+
+    // A restarted source continues after the last line emitted
+    append(&log, "e\n");
+    assert_eq!(payloads(collect_lines(tail()).await), vec!["e"]);
+
+    // A truncated file is read from its start
+    std::fs::write(&log, "f\n").unwrap();
+    assert_eq!(payloads(collect_lines(tail()).await), vec!["f"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_directory_source_picks_up_new_files() {
+    let dir = temp_dir("directory");
+    let offsets = dir.join("offsets.json");
+    std::fs::write(dir.join("a.log"), "1\n").unwrap();
+    std::fs::write(dir.join("notes.txt"), "skipped\n").unwrap();
+
+    let source = || DirectorySource::new(&dir, "*.log")
+        .with_offsets(&offsets)
+        .with_poll_interval(Duration::from_millis(10))
+        .with_idle_timeout(Duration::from_millis(300));
+
+    let writer_dir = dir.clone();
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        append(&writer_dir.join("a.log"), "2\n");
+        std::fs::write(writer_dir.join("b.log"), "3\n").unwrap();
+    });
+    let lines: Vec<(String, Option<String>)> = collect_lines(source()).await.into_iter()
+        .map(|msg| (msg.payload, msg.source_id))
+        .collect();
+    writer.join().unwrap();
+    assert_eq!(lines, vec![
+        ("1".to_string(), Some("a.log".to_string())),
+        ("2".to_string(), Some("a.log".to_string())),
+        ("3".to_string(), Some("b.log".to_string())),
+    ]);
+
+    append(&dir.join("b.log"), "4\n");
+    let lines: Vec<String> = collect_lines(source()).await.into_iter().map(|msg| msg.payload).collect();
+    assert_eq!(lines, vec!["4"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_directory_source_follows_renamed_files() {
+    let dir = temp_dir("rename");
+    let offsets = dir.join("offsets.json");
+    std::fs::write(dir.join("app.log"), "1\n2\n").unwrap();
+
+    let source = || DirectorySource::new(&dir, "app.log*")
+        .with_offsets(&offsets)
+        .with_poll_interval(Duration::from_millis(10))
+        .with_idle_timeout(Duration::from_millis(300));
+
+    // Rotate: the writer keeps appending to the renamed file before reopening
+    let writer_dir = dir.clone();
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(100));
+        std::fs::rename(writer_dir.join("app.log"), writer_dir.join("app.log.1")).unwrap();
+        append(&writer_dir.join("app.log.1"), "3\n");
+        std::fs::write(writer_dir.join("app.log"), "4\n").unwrap();
+    });
+    let mut lines: Vec<String> = collect_lines(source()).await.into_iter().map(|msg| msg.payload).collect();
+    writer.join().unwrap();
+
+    // Depending on when the rename is noticed, 3 is read through the old or the
+    // new name, but every line is emitted exactly once
+    lines.sort();
+    assert_eq!(lines, vec!["1", "2", "3", "4"]);
+
+    // Files renamed while the source is stopped resume from their saved offsets
+    std::fs::rename(dir.join("app.log.1"), dir.join("app.log.2")).unwrap();
+    std::fs::rename(dir.join("app.log"), dir.join("app.log.1")).unwrap();
+    append(&dir.join("app.log.1"), "5\n");
+    let lines: Vec<String> = collect_lines(source()).await.into_iter().map(|msg| msg.payload).collect();
+    assert_eq!(lines, vec!["5"]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+async fn read_file<F: FileFormat>(source: FileSource<F>) -> Vec<Message<F::Record>> {
+    let collector = MessageCollector::new();
+    let results = collector.results.clone();
+    (PipelineTask::new(source) | PipelineTask::new(collector)).run().await;
+    let records = std::mem::take(&mut *results.lock().unwrap());
+    records
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Trade {
+    symbol: String,
+    price: f64,
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_file_source_formats() {
+    use std::io::Write;
+    let dir = temp_dir("formats");
+
+    // Compression is detected from the content, not the file name
+    let gzip = dir.join("lines.gz");
+    let mut encoder = flate2::write::GzEncoder::new(std::fs::File::create(&gzip).unwrap(), flate2::Compression::default());
+    encoder.write_all(b"first\r\nsecond\n").unwrap();
+    encoder.finish().unwrap();
+    let lines = read_file(FileSource::new(&gzip)).await;
+    let lines: Vec<_> = lines.into_iter().map(|msg| (msg.payload, msg.source_id.unwrap(), msg.position.unwrap())).collect();
+    assert_eq!(lines, vec![
+        ("first".to_string(), "lines.gz".to_string(), 1),
+        ("second".to_string(), "lines.gz".to_string(), 2),
+    ]);
+
+    let jsonl = dir.join("events.jsonl.zst");
+    std::fs::write(&jsonl, zstd::encode_all(&b"{\"n\": 1}\n\nnot json\n{\"n\": 2}\n"[..], 0).unwrap()).unwrap();
+    let events = read_file(FileSource::new(&jsonl).with_format(JsonLines::<serde_json::Value>::new())).await;
+    let events: Vec<_> = events.into_iter().map(|msg| (msg.payload["n"].as_i64().unwrap(), msg.position.unwrap())).collect();
+    assert_eq!(events, vec![(1, 1), (2, 4)]);
+
+    let csv = dir.join("trades.csv");
+    std::fs::write(&csv, "price;symbol\n1.5;ABC\nbad;XYZ\n2;DEF\n").unwrap();
+    let trades = read_file(FileSource::new(&csv).with_format(Csv::<Trade>::new().with_delimiter(b';'))).await;
+    let trades: Vec<_> = trades.into_iter().map(|msg| (msg.payload, msg.position.unwrap())).collect();
+    assert_eq!(trades, vec![
+        (Trade { symbol: "ABC".to_string(), price: 1.5 }, 1),
+        (Trade { symbol: "DEF".to_string(), price: 2.0 }, 3),
+    ]);
+
+    let binary = dir.join("records.bin");
+    std::fs::write(&binary, [&[0, 0, 0, 2, 0xff, 0x00][..], &[0, 0, 0, 0][..], &[0, 0, 0, 1, 7][..]].concat()).unwrap();
+    let records = read_file(FileSource::new(&binary).with_format(LengthPrefixed)).await;
+    let records: Vec<Vec<u8>> = records.into_iter().map(|msg| msg.payload).collect();
+    assert_eq!(records, vec![vec![0xff, 0x00], vec![], vec![7]]);
+
+    // A corrupt length fails the file instead of allocating 4 GiB
+    let corrupt = [&[0, 0, 0, 1, 7][..], &u32::MAX.to_be_bytes()[..], b"abc"].concat();
+    let mut read = Vec::new();
+    let result = LengthPrefixed.read(&mut &corrupt[..], &mut |_, record| { read.push(record); true });
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    assert_eq!(read, vec![vec![7]]);
+
+    // So does a header cut short by the end of the file
+    let truncated = [&[0, 0, 0, 1, 7][..], &[0, 0][..]].concat();
+    let mut read = Vec::new();
+    let result = LengthPrefixed.read(&mut &truncated[..], &mut |_, record| { read.push(record); true });
+    assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    assert_eq!(read, vec![vec![7]]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_csv_file_in_config() {
+    let results = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let input = write_temp_file("people.csv", "name,age\nana,31\n");
+
+    let config = PipelineConfig::from_yaml(&format!(
+        "stages:\n  - type: file\n    path: {}\n    format: csv\n  - type: collect\n",
+        input.display()
+    )).unwrap();
+    registry_with_collector(results.clone()).build(&config).unwrap().run().await;
+
+    assert_eq!(*results.lock().unwrap(), vec![r#"{"age":31,"name":"ana"}"#]);
+    std::fs::remove_file(input).unwrap();
+}
+
+/// Files under `dir` that readers would see, relative to it and in path order
+fn list_files(dir: &std::path::Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in std::fs::read_dir(current).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if !path.file_name().unwrap().to_string_lossy().starts_with('.') {
+                files.push(path.strip_prefix(dir).unwrap().to_string_lossy().into_owned());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn test_file_sink_rolls_and_partitions() {
+    let dir = temp_dir("file_sink");
+    let at = |time: &str| chrono::DateTime::parse_from_rfc3339(time).unwrap().timestamp_millis() as u64;
+    let trade = |symbol: &str, price: f64| Trade { symbol: symbol.to_string(), price };
+
+    let harness = ComponentHarness::new(
+        FileSink::<Trade>::new(&dir, SinkFormat::Csv)
+            .with_partitioning("date=%Y-%m-%d/hour=%H")
+            .with_max_duration(Duration::from_secs(60))
+    );
+    harness.push_message(Message::with_event_time(trade("ABC", 1.5), at("2026-10-18T13:05:00Z")));
+    harness.push_message(Message::with_event_time(trade("DEF", 2.0), at("2026-10-18T13:10:00Z")));
+    harness.push_message(Message::with_event_time(trade("ABC", 1.6), at("2026-10-18T14:01:00Z")));
+    assert!(list_files(&dir).is_empty(), "files in progress are hidden");
+
+    // Both open files have expired by the time the next message arrives
+    harness.advance(Duration::from_secs(60));
+    harness.push_message(Message::with_event_time(trade("DEF", 2.1), at("2026-10-18T14:02:00Z")));
+    assert_eq!(list_files(&dir), vec![
+        "date=2026-10-18/hour=13/part-0-00000.csv",
+        "date=2026-10-18/hour=14/part-0-00001.csv",
+    ]);
+    harness.finish();
+
+    let files = list_files(&dir);
+    assert_eq!(files, vec![
+        "date=2026-10-18/hour=13/part-0-00000.csv",
+        "date=2026-10-18/hour=14/part-0-00001.csv",
+        "date=2026-10-18/hour=14/part-60000-00002.csv",
+    ]);
+    let read = |file: &str| std::fs::read_to_string(dir.join(file)).unwrap();
+    assert_eq!(read(&files[0]), "symbol,price\nABC,1.5\nDEF,2.0\n");
+    assert_eq!(read(&files[2]), "symbol,price\nDEF,2.1\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Files roll once they reach the size limit
+    let harness = ComponentHarness::new(FileSink::<String>::new(&dir, SinkFormat::Text).with_max_bytes(10));
+    for text in ["hello", "world", "again"] {
+        harness.push(text.to_string());
+    }
+    harness.finish();
+    let contents: Vec<String> = list_files(&dir).iter().map(|file| std::fs::read_to_string(dir.join(file)).unwrap()).collect();
+    assert_eq!(contents, vec!["hello\nworld\n", "again\n"]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // JSON lines hold whole messages, readable with the JSON codec
+    let harness = ComponentHarness::new(FileSink::<Trade>::new(&dir, SinkFormat::JsonLines));
+    harness.push_message(Message::with_event_time(trade("ABC", 1.5), at("2026-10-18T13:05:00Z")));
+    harness.finish();
+    let files = list_files(&dir);
+    let decoded: Message<Trade> = JsonCodec.decode(read(&files[0]).trim_end().as_bytes()).unwrap();
+    assert_eq!(decoded.payload, trade("ABC", 1.5));
+    assert_eq!(decoded.event_timestamp, at("2026-10-18T13:05:00Z"));
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Reads back the rows of each Parquet file under `dir` by row group, with the
+/// compression of the file's first column
+fn read_parquet(dir: &std::path::Path) -> Vec<(Vec<Vec<Trade>>, String)> {
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    let open = |file: &str| ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(dir.join(file)).unwrap()).unwrap();
+
+    list_files(dir).iter().map(|file| {
+        let metadata = open(file).metadata().clone();
+        let row_groups = (0..metadata.num_row_groups()).map(|index| {
+            open(file).with_row_groups(vec![index]).build().unwrap()
+                .flat_map(|batch| serde_arrow::from_record_batch::<Vec<Trade>>(&batch.unwrap()).unwrap())
+                .collect()
+        }).collect();
+        (row_groups, metadata.row_group(0).column(0).compression().to_string())
+    }).collect()
+}
+
+#[test]
+fn test_parquet_sink_writes_row_groups() {
+    let dir = temp_dir("parquet_sink");
+    let trades: Vec<Trade> = (0..5).map(|i| Trade { symbol: format!("S{}", i), price: i as f64 * 1.5 }).collect();
+
+    let harness = ComponentHarness::new(
+        ParquetSink::<Trade>::new(&dir)
+            .with_row_group_size(2)
+            .with_compression(Compression::ZSTD(Default::default()))
+    );
+    for trade in &trades {
+        harness.push(trade.clone());
+    }
+    assert!(list_files(&dir).is_empty(), "files in progress are hidden");
+    harness.finish();
+
+    let files = read_parquet(&dir);
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].0, vec![trades[0..2].to_vec(), trades[2..4].to_vec(), trades[4..].to_vec()]);
+    assert!(files[0].1.starts_with("ZSTD"), "compressed with {}", files[0].1);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // An explicit schema, and files rolled by size after each row group and by time
+    let schema = Arc::new(arrow_schema::Schema::new(vec![
+        arrow_schema::Field::new("symbol", arrow_schema::DataType::Utf8, false),
+        arrow_schema::Field::new("price", arrow_schema::DataType::Float64, false),
+    ]));
+    let harness = ComponentHarness::new(
+        ParquetSink::<Trade>::new(&dir)
+            .with_schema(schema)
+            .with_row_group_size(2)
+            .with_max_bytes(1)
+            .with_max_duration(Duration::from_secs(60))
+    );
+    for trade in &trades[0..3] {
+        harness.push(trade.clone());
+    }
+    assert_eq!(list_files(&dir).len(), 1);
+    harness.advance(Duration::from_secs(60));
+    harness.push(trades[3].clone());
+    assert_eq!(list_files(&dir).len(), 2);
+    harness.finish();
+
+    let files: Vec<Vec<Vec<Trade>>> = read_parquet(&dir).into_iter().map(|(row_groups, compression)| {
+        assert_eq!(compression, "SNAPPY");
+        row_groups
+    }).collect();
+    assert_eq!(files, vec![
+        vec![trades[0..2].to_vec()],
+        vec![trades[2..3].to_vec()],
+        vec![trades[3..4].to_vec()],
+    ]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+struct Reading {
+    sensor: String,
+    value: Option<f64>,
+}
+
+#[test]
+fn test_parquet_sink_keeps_rows_without_an_inferred_schema() {
+    let dir = temp_dir("parquet_sink_nulls");
+    let reading = |sensor: &str, value: Option<f64>| Reading { sensor: sensor.to_string(), value };
+    let readings = vec![reading("a", None), reading("b", None), reading("a", Some(1.5)), reading("b", None)];
+
+    // The first row group has no value to infer the type of `value` from, so
+    // its rows are written with the next one
+    let harness = ComponentHarness::new(ParquetSink::<Reading>::new(&dir).with_row_group_size(2));
+    for reading in &readings[0..2] {
+        harness.push(reading.clone());
+    }
+    harness.advance(Duration::from_secs(1));
+    assert!(list_files(&dir).is_empty());
+    for reading in &readings[2..] {
+        harness.push(reading.clone());
+    }
+    harness.finish();
+
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    let files = list_files(&dir);
+    assert_eq!(files.len(), 1);
+    let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(dir.join(&files[0])).unwrap()).unwrap().build().unwrap();
+    let rows: Vec<Reading> = reader
+        .flat_map(|batch| serde_arrow::from_record_batch::<Vec<Reading>>(&batch.unwrap()).unwrap())
+        .collect();
+    assert_eq!(rows, readings);
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_parquet_sink_drops_rows_it_cannot_write() {
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    let dir = temp_dir("parquet_sink_drops");
+    let reading = |sensor: &str, value: Option<f64>| Reading { sensor: sensor.to_string(), value };
+    let read_rows = |dir: &std::path::Path| -> Vec<Reading> {
+        list_files(dir).iter().flat_map(|file| {
+            let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(dir.join(file)).unwrap()).unwrap().build().unwrap();
+            reader.flat_map(|batch| serde_arrow::from_record_batch::<Vec<Reading>>(&batch.unwrap()).unwrap()).collect::<Vec<_>>()
+        }).collect()
+    };
+
+    // A row that does not fit the schema is dropped, not the row group with it
+    let schema = Arc::new(arrow_schema::Schema::new(vec![
+        arrow_schema::Field::new("sensor", arrow_schema::DataType::Utf8, false),
+        arrow_schema::Field::new("value", arrow_schema::DataType::Float64, false),
+    ]));
+    let harness = ComponentHarness::new(ParquetSink::<Reading>::new(&dir).with_schema(schema).with_row_group_size(2));
+    for reading in [reading("a", Some(1.0)), reading("b", None), reading("c", Some(2.0)), reading("d", Some(3.0))] {
+        harness.push(reading);
+    }
+    harness.finish();
+    assert_eq!(read_rows(&dir), vec![reading("a", Some(1.0)), reading("c", Some(2.0)), reading("d", Some(3.0))]);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // Rows held back without a schema are capped at four row groups, oldest dropped first
+    let harness = ComponentHarness::new(ParquetSink::<Reading>::new(&dir).with_row_group_size(2));
+    for index in 0..10 {
+        harness.push(reading(&index.to_string(), None));
+    }
+    harness.push(reading("10", Some(1.5)));
+    harness.finish();
+    let sensors: Vec<String> = read_rows(&dir).into_iter().map(|reading| reading.sensor).collect();
+    assert_eq!(sensors, (2..=10).map(|index| index.to_string()).collect::<Vec<_>>());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_sqlite_sink_and_source() {
+    let dir = temp_dir("sqlite");
+    let database = dir.join("trades.db");
+    let trade = |symbol: &str, price: f64| Trade { symbol: symbol.to_string(), price };
+
+    // Later prices replace earlier ones for the same symbol
+    let sink = SqliteSink::new(&database, "prices", &["symbol", "price"], |trade: &Trade| {
+        vec![SqlValue::Text(trade.symbol.clone()), SqlValue::Real(trade.price)]
+    })
+        .with_setup("CREATE TABLE IF NOT EXISTS prices (id INTEGER PRIMARY KEY, symbol TEXT UNIQUE, price REAL)")
+        .with_upsert(&["symbol"])
+        .with_batch_size(2);
+    let harness = ComponentHarness::new(sink);
+    harness.push(trade("ABC", 1.0));
+    harness.push(trade("DEF", 2.0));
+    harness.push(trade("ABC", 1.5));
+    harness.finish();
+
+    let read = |after: i64| {
+        let source = SqliteSource::new(&database, "prices", "id", |row| {
+            Ok(Trade { symbol: row.get("symbol")?, price: row.get("price")? })
+        })
+            .with_start(after)
+            .with_batch_size(1)
+            .with_poll_interval(Duration::from_millis(20))
+            .with_idle_timeout(Duration::from_millis(200));
+        async move {
+            let collector = MessageCollector::new();
+            let results = collector.results.clone();
+            (PipelineTask::new(source) | PipelineTask::new(collector)).run().await;
+            let rows: Vec<(Trade, u64)> = results.lock().unwrap().iter()
+                .map(|msg| (msg.payload.clone(), msg.position.unwrap()))
+                .collect();
+            rows
+        }
+    };
+    assert_eq!(read(i64::MIN).await, vec![(trade("ABC", 1.5), 1), (trade("DEF", 2.0), 2)]);
+
+    // Resuming after the last position only emits rows added since
+    let harness = ComponentHarness::new(SqliteSink::new(&database, "prices", &["symbol", "price"], |trade: &Trade| {
+        vec![SqlValue::Text(trade.symbol.clone()), SqlValue::Real(trade.price)]
+    }));
+    harness.push(trade("GHI", 3.0));
+    harness.finish();
+    assert_eq!(read(2).await, vec![(trade("GHI", 3.0), 3)]);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Starts an embedded MQTT broker accepting the user `floq`, returning its URL
+fn start_mqtt_broker() -> String {
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let config: rumqttd::Config = toml::from_str(&format!(r#"
+        id = 0
+        [router]
+        max_connections = 100
+        max_outgoing_packet_count = 200
+        max_segment_size = 1048576
+        max_segment_count = 10
+        [v4.1]
+        name = "v4-1"
+        listen = "127.0.0.1:{}"
+        next_connection_delay_ms = 1
+        [v4.1.connections]
+        connection_timeout_ms = 5000
+        max_payload_size = 20480
+        max_inflight_count = 100
+        dynamic_filters = true
+        auth = {{ floq = "secret" }}
+    "#, port)).unwrap();
+    std::thread::spawn(move || rumqttd::Broker::new(config).start().unwrap());
+    while std::net::TcpStream::connect(("127.0.0.1", port)).is_err() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    format!("mqtt://127.0.0.1:{}", port)
+}
+
+fn publish_mqtt(url: &str, topic: &str, readings: &[(&str, &str)]) {
+    let harness = ComponentHarness::new(MqttSink::<String>::new(url, "publisher", topic).with_credentials("floq", "secret"));
+    for (source, reading) in readings {
+        harness.push_message(Message::new(reading.to_string()).with_source(*source));
+    }
+    harness.finish();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_mqtt_sink_and_source() {
+    let url = start_mqtt_broker();
+    let subscribe = |url: &str| {
+        let source = MqttSource::new(url, "subscriber")
+            .with_topic("sensors/+/temperature", QoS::AtLeastOnce)
+            .with_topic("alerts/#", QoS::AtMostOnce)
+            .with_credentials("floq", "secret")
+            .with_clean_session(false)
+            .with_idle_timeout(Duration::from_millis(1500));
+        let collector = MessageCollector::new();
+        let results = collector.results.clone();
+        let task = tokio::spawn(async move {
+            (PipelineTask::new(source) | PipelineTask::new(collector)).run().await;
+        });
+        async move {
+            task.await.unwrap();
+            let mut received: Vec<(String, String)> = results.lock().unwrap().iter()
+                .map(|msg| (msg.source_id.clone().unwrap(), msg.payload.clone()))
+                .collect();
+            received.sort();
+            received
+        }
+    };
+
+    // Topics are filled in from the message source; only matching ones arrive
+    let received = subscribe(&url);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let publisher = url.clone();
+    tokio::task::spawn_blocking(move || {
+        publish_mqtt(&publisher, "sensors/{source}/temperature", &[("kitchen", "21.5"), ("garage", "12.0")]);
+        publish_mqtt(&publisher, "alerts/{source}/smoke", &[("kitchen", "on")]);
+        publish_mqtt(&publisher, "sensors/{source}/humidity", &[("kitchen", "40")]);
+    }).await.unwrap();
+    assert_eq!(received.await, vec![
+        ("alerts/kitchen/smoke".to_string(), "on".to_string()),
+        ("sensors/garage/temperature".to_string(), "12.0".to_string()),
+        ("sensors/kitchen/temperature".to_string(), "21.5".to_string()),
+    ]);
+
+    // The persistent session queues QoS 1 messages published while disconnected
+    let publisher = url.clone();
+    tokio::task::spawn_blocking(move || {
+        publish_mqtt(&publisher, "sensors/{source}/temperature", &[("attic", "30.5")]);
+    }).await.unwrap();
+    assert_eq!(subscribe(&url).await, vec![
+        ("sensors/attic/temperature".to_string(), "30.5".to_string()),
+    ]);
+
+    // Wrong credentials are refused, so nothing is received
+    let source = MqttSource::new(&url, "intruder")
+        .with_topic("#", QoS::AtMostOnce)
+        .with_credentials("floq", "wrong")
+        .with_idle_timeout(Duration::from_millis(500));
+    assert!(collect_lines(source).await.is_empty());
+}
+
+/// Connects to a WebSocket server, returning a task that collects the payloads
+/// it receives, once `read_after` completes, until the connection ends
+async fn websocket_client<F>(address: &str, read_after: F) -> tokio::task::JoinHandle<Vec<String>>
+where
+    F: std::future::Future + Send + 'static,
+{
+    use futures_util::StreamExt;
+    let (mut websocket, _) = tokio_tungstenite::connect_async(format!("ws://{}", address)).await.unwrap();
+    tokio::spawn(async move {
+        read_after.await;
+        let mut payloads = Vec::new();
+        while let Some(Ok(message)) = websocket.next().await {
+            if let Ok(text) = message.into_text() {
+                if !text.is_empty() {
+                    let msg: Message<String> = serde_json::from_str(&text).unwrap();
+                    payloads.push(msg.payload);
+                }
+            }
+        }
+        payloads
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_websocket_server_sink_broadcasts() {
+    let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let sink = WebSocketServerSink::<String>::bind(&address)
+        .with_replay(2)
+        .with_client_buffer(16)
+        .with_slow_clients(SlowClientPolicy::Disconnect)
+        .with_send_timeout(Duration::from_millis(200));
+    let harness = tokio::task::spawn_blocking(move || {
+        let harness = ComponentHarness::new(sink);
+        for text in ["one", "two", "three"] {
+            harness.push(text.to_string());
+        }
+        harness
+    }).await.unwrap();
+
+    // New clients first receive the last two messages
+    // The slow client only starts reading once everything has been pushed
+    let (pushed_all, all_pushed) = tokio::sync::oneshot::channel::<()>();
+    let fast = websocket_client(&address, std::future::ready(())).await;
+    let slow = websocket_client(&address, all_pushed).await;
+
+    // Large messages fill the socket buffers of the client that is not reading
+    let large = "x".repeat(128 * 1024);
+    let pushed = 100;
+    tokio::task::spawn_blocking(move || {
+        for _ in 0..pushed {
+            harness.push(large.clone());
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        harness.push("last".to_string());
+        harness.finish();
+    }).await.unwrap();
+    pushed_all.send(()).unwrap();
+
+    let fast = fast.await.unwrap();
+    assert_eq!(&fast[..2], ["two", "three"]);
+    assert_eq!(fast.len(), 2 + pushed + 1);
+    assert_eq!(fast.last().unwrap(), "last");
+
+    let slow = slow.await.unwrap();
+    assert_eq!(&slow[..2], ["two", "three"]);
+    assert!(slow.len() < 2 + pushed, "slow client received {} messages", slow.len());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_http_source_accepts_webhooks() {
+    let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let source = HttpSource::<Trade>::bind(&address)
+        .with_path("/hooks/trades")
+        .with_token("s3cret")
+        .with_idle_timeout(Duration::from_millis(500));
+    let collector = MessageCollector::new();
+    let results = collector.results.clone();
+    let pipeline = tokio::spawn(async move {
+        (PipelineTask::new(source) | PipelineTask::new(collector)).run().await;
+    });
+
+    let url = format!("http://{}/hooks/trades", address);
+    let client = reqwest::Client::new();
+    let post = |url: String, body: &'static str, token: Option<&'static str>| {
+        let mut request = client.post(url).body(body);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        async move {
+            let response = request.send().await.unwrap();
+            (response.status().as_u16(), response.text().await.unwrap())
+        }
+    };
+
+    // Wait for the server to listen
+    while tokio::net::TcpStream::connect(&address).await.is_err() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(post(url.clone(), r#"{"symbol":"ABC","price":1.5}"#, Some("s3cret")).await, (202, r#"{"accepted":1}"#.to_string()));
+    assert_eq!(post(url.clone(), r#"[{"symbol":"DEF","price":2},{"symbol":"GHI","price":3}]"#, Some("s3cret")).await.0, 202);
+    assert_eq!(post(url.clone(), r#"{"symbol":"ABC","price":1.5}"#, None).await.0, 401);
+    assert_eq!(post(url.clone(), r#"{"symbol":"ABC","price":1.5}"#, Some("wrong")).await.0, 401);
+    assert_eq!(post(format!("http://{}/other", address), "{}", Some("s3cret")).await.0, 404);
+    // A batch is rejected as a whole if any payload does not deserialize
+    assert_eq!(post(url.clone(), r#"[{"symbol":"JKL","price":4},{"symbol":"MNO"}]"#, Some("s3cret")).await.0, 400);
+    assert_eq!(post(url.clone(), "not json", Some("s3cret")).await.0, 400);
+    assert_eq!(client.get(&url).bearer_auth("s3cret").send().await.unwrap().status().as_u16(), 405);
+
+    pipeline.await.unwrap();
+    let symbols: Vec<String> = results.lock().unwrap().iter().map(|msg| msg.payload.symbol.clone()).collect();
+    assert_eq!(symbols, vec!["ABC", "DEF", "GHI"]);
+}
+
+/// Reads an HTTP request, returning its `Last-Event-ID` header
+async fn read_last_event_id(stream: &mut tokio::net::TcpStream) -> Option<String> {
+    use tokio::io::AsyncReadExt;
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await.unwrap();
+        request.extend_from_slice(&buffer[..read]);
+    }
+    String::from_utf8(request).unwrap().lines()
+        .find_map(|line| line.to_lowercase().starts_with("last-event-id:").then(|| line[14..].trim().to_string()))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_sse_source_resumes_after_last_event() {
+    use tokio::io::AsyncWriteExt;
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/events", listener.local_addr().unwrap());
+    const HEADER: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n";
+
+    let server = tokio::spawn(async move {
+        // The first stream ends partway through an event, whose id is not the
+        // last event id as the event was never received
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let first_id = read_last_event_id(&mut stream).await;
+        stream.write_all(HEADER).await.unwrap();
+        stream.write_all(b": comment\nretry: 50\nid: 1\ndata: first\n\nevent: update\nid: 2\ndata: second\n").await.unwrap();
+        stream.write_all(b"data: line\n\nid: 3\ndata: partial").await.unwrap();
+        drop(stream);
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let resumed_id = read_last_event_id(&mut stream).await;
+        stream.write_all(HEADER).await.unwrap();
+        stream.write_all(b"id: 3\r\ndata: third\r\n\r\n").await.unwrap();
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        (first_id, resumed_id)
+    });
+
+    let events = collect_lines(SseSource::new(&url).with_last_event_id("0").with_idle_timeout(Duration::from_millis(500))).await;
+    let events: Vec<(String, String)> = events.into_iter().map(|msg| (msg.source_id.unwrap(), msg.payload)).collect();
+    assert_eq!(events, vec![
+        ("message".to_string(), "first".to_string()),
+        ("update".to_string(), "second\nline".to_string()),
+        ("message".to_string(), "third".to_string()),
+    ]);
+    assert_eq!(server.await.unwrap(), (Some("0".to_string()), Some("2".to_string())));
+}
+
+/// Requests received by `start_mock_endpoint`: the path, `X-Source` header and body
+type ReceivedRequests = Arc<std::sync::Mutex<Vec<(String, String, serde_json::Value)>>>;
+
+/// Serves `/ok` with 200, `/flaky` with 503 and then 429 before 200, `/gone`
+/// with 400, `/busy` with 503 asking to retry in an hour and everything else
+/// with 500
+async fn start_mock_endpoint() -> (String, ReceivedRequests) {
+    use hyper::service::{make_service_fn, service_fn};
+    use hyper::{Body, Request, Response};
+
+    let received: ReceivedRequests = Arc::default();
+    let flaky_attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let requests = received.clone();
+    let make_service = make_service_fn(move |_| {
+        let (requests, flaky_attempts) = (requests.clone(), flaky_attempts.clone());
+        async move {
+            Ok::<_, std::convert::Infallible>(service_fn(move |request: Request<Body>| {
+                let (requests, flaky_attempts) = (requests.clone(), flaky_attempts.clone());
+                async move {
+                    let path = request.uri().path().to_string();
+                    let source = request.headers().get("X-Source").map(|value| value.to_str().unwrap().to_string()).unwrap_or_default();
+                    let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                    requests.lock().unwrap().push((path.clone(), source, serde_json::from_slice(&body).unwrap()));
+
+                    let response = Response::builder();
+                    let response = match path.as_str() {
+                        "/ok" => response.status(200),
+                        "/flaky" => match flaky_attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst) {
+                            0 => response.status(503).header("Retry-After", "0"),
+                            1 => response.status(429),
+                            _ => response.status(200),
+                        },
+                        "/gone" => response.status(400),
+                        "/busy" => response.status(503).header("Retry-After", "3600"),
+                        _ => response.status(500),
+                    };
+                    Ok::<_, std::convert::Infallible>(response.body(Body::from("nope")).unwrap())
+                }
+            }))
+        }
+    });
+    let server = hyper::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+    let address = format!("http://{}", server.local_addr());
+    tokio::spawn(server);
+    (address, received)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_http_sink_batches_retries_and_dead_letters() {
+    let (address, received) = start_mock_endpoint().await;
+    let trade = |symbol: &str, source: &str| Message::new(Trade { symbol: symbol.to_string(), price: 1.0 }).with_source(source);
+    let source = MessageSource::from_messages(vec![
+        trade("ABC", "ok"), trade("DEF", "flaky"), trade("GHI", "ok"), trade("JKL", "gone"),
+        trade("MNO", "ok"), trade("PQR", "down"), trade("STU", "down"),
+    ]);
+
+    let (dead_letter_sender, dead_letters) = floq::pipeline::channel::channel();
+    let sink = HttpSink::<Trade>::new(format!("{}/{{source}}", address))
+        .with_header("X-Source", "{source}")
+        .with_batch_size(2)
+        .with_concurrency(3)
+        .with_max_retries(2)
+        .with_backoff(Duration::from_millis(10), Duration::from_millis(20))
+        .with_dead_letters(dead_letter_sender);
+    (PipelineTask::new(source) | PipelineTask::new(sink)).run().await;
+
+    // Batches are arrays of at most two payloads, each sent to its own source's URL
+    let received = received.lock().unwrap().clone();
+    let mut delivered = Vec::new();
+    for (path, source, body) in &received {
+        assert_eq!(path, &format!("/{}", source));
+        let batch = body.as_array().unwrap();
+        assert!(!batch.is_empty() && batch.len() <= 2);
+        if path == "/ok" {
+            delivered.extend(batch.iter().map(|trade| trade["symbol"].as_str().unwrap().to_string()));
+        }
+    }
+    delivered.sort();
+    assert_eq!(delivered, vec!["ABC", "GHI", "MNO"]);
+    // Retried after the 503 and the 429 before succeeding
+    assert_eq!(received.iter().filter(|(path, _, _)| path == "/flaky").count(), 3);
+
+    let mut failed = Vec::new();
+    while let Ok(msg) = dead_letters.try_recv() {
+        assert_eq!(msg.source_id.as_deref(), Some(msg.payload.url.rsplit('/').next().unwrap()));
+        for trade in &msg.payload.payloads {
+            failed.push((trade.symbol.clone(), msg.payload.status, msg.payload.attempts));
+        }
+    }
+    failed.sort_by(|a, b| a.0.cmp(&b.0));
+    // Client errors are not retried, server errors are until the retries run out
+    assert_eq!(failed, vec![
+        ("JKL".to_string(), Some(400), 1),
+        ("PQR".to_string(), Some(500), 3),
+        ("STU".to_string(), Some(500), 3),
+    ]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_http_sink_dead_letters_without_waiting_on_the_server() {
+    let (address, received) = start_mock_endpoint().await;
+
+    // Retry-After cannot hold a request back longer than the maximum backoff
+    let source = MessageSource::from_messages(vec![Message::new(Trade { symbol: "ABC".to_string(), price: 1.0 })]);
+    let (dead_letter_sender, dead_letters) = floq::pipeline::channel::channel();
+    let sink = HttpSink::<Trade>::new(format!("{}/busy", address))
+        .with_max_retries(1)
+        .with_backoff(Duration::from_millis(10), Duration::from_millis(20))
+        .with_dead_letters(dead_letter_sender);
+    tokio::time::timeout(Duration::from_secs(10), (PipelineTask::new(source) | PipelineTask::new(sink)).run()).await.unwrap();
+    assert_eq!(received.lock().unwrap().len(), 2);
+    let failed = dead_letters.try_recv().unwrap().payload;
+    assert_eq!((failed.status, failed.attempts), (Some(503), 2));
+
+    // Payloads that cannot be serialized are not sent
+    let unserializable = std::collections::HashMap::from([(vec![1u8], 1u8)]);
+    let source = MessageSource::from_messages(vec![Message::new(unserializable)]);
+    let (dead_letter_sender, dead_letters) = floq::pipeline::channel::channel();
+    let sink = HttpSink::new(format!("{}/ok", address)).with_dead_letters(dead_letter_sender);
+    (PipelineTask::new(source) | PipelineTask::new(sink)).run().await;
+    assert_eq!(received.lock().unwrap().len(), 2);
+    let failed = dead_letters.try_recv().unwrap().payload;
+    assert_eq!((failed.status, failed.attempts, failed.payloads.len()), (None, 0, 1));
+    assert!(failed.error.starts_with("failed to serialize payload"), "{}", failed.error);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_http_sink_batches_until_the_linger_expires() {
+    let (address, received) = start_mock_endpoint().await;
+    let trade = |symbol: &str| Trade { symbol: symbol.to_string(), price: 1.0 };
+
+    // A batch waits for more messages while the input is idle, until it is full
+    // or has waited for the linger
+    let harness = ComponentHarness::new(
+        HttpSink::<Trade>::new(format!("{}/ok", address))
+            .with_batch_size(3)
+            .with_linger(Duration::from_secs(1))
+    );
+    for symbol in ["ABC", "DEF", "GHI", "JKL"] {
+        harness.push(trade(symbol));
+    }
+    harness.advance(Duration::from_secs(1));
+    harness.push(trade("MNO"));
+    harness.finish();
+
+    let batches: Vec<Vec<String>> = received.lock().unwrap().iter()
+        .map(|(_, _, body)| body.as_array().unwrap().iter().map(|trade| trade["symbol"].as_str().unwrap().to_string()).collect())
+        .collect();
+    assert_eq!(batches, vec![vec!["ABC", "DEF", "GHI"], vec!["JKL"], vec!["MNO"]]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_http_sink_renders_templates_once() {
+    let (address, received) = start_mock_endpoint().await;
+    let trade = Trade { symbol: "ABC".to_string(), price: 1.0 };
+    let source = MessageSource::from_messages(vec![Message::new(trade).with_source("{position}").with_position(7)]);
+
+    // A placeholder in the metadata is not filled in again
+    let sink = HttpSink::<Trade>::new(format!("{}/ok", address)).with_header("X-Source", "{source}@{position}");
+    (PipelineTask::new(source) | PipelineTask::new(sink)).run().await;
+
+    let received = received.lock().unwrap().clone();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].1, "{position}@7");
+}
+
+/// Runs a source into a `MessageCollector` in the background
+fn collect_in_background<C>(source: C) -> tokio::task::JoinHandle<Vec<Message<String>>>
+where
+    C: PipelineComponent<Input = (), Output = String> + Send + Sync + 'static,
+{
+    let collector = MessageCollector::new();
+    let results = collector.results.clone();
+    tokio::spawn(async move {
+        (PipelineTask::new(source) | PipelineTask::new(collector)).run().await;
+        let results = results.lock().unwrap().clone();
+        results
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_tcp_sink_and_source() {
+    use tokio::io::AsyncWriteExt;
+
+    // The sink keeps reconnecting until the source listens, while its input is
+    // open, and drops a payload that would be split into two lines
+    let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let source = DelayedStringSource::new(vec![
+        ("alpha".to_string(), Duration::ZERO),
+        ("two\nlines".to_string(), Duration::ZERO),
+        ("beta".to_string(), Duration::from_millis(600)),
+    ]);
+    let sink = TcpSink::<String>::connect(&address).with_max_backoff(Duration::from_millis(50));
+    let sender = tokio::spawn(async move {
+        (PipelineTask::new(source) | PipelineTask::new(sink)).run().await;
+    });
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let received = collect_in_background(TcpSource::bind(&address).with_idle_timeout(Duration::from_millis(700)));
+    sender.await.unwrap();
+
+    // A line without a newline is emitted once the peer disconnects
+    let mut client = tokio::net::TcpStream::connect(&address).await.unwrap();
+    client.write_all(b"one\r\ntwo\nthree").await.unwrap();
+    let client_address = client.local_addr().unwrap().to_string();
+    drop(client);
+
+    let received = received.await.unwrap();
+    let from = |peer: &str| -> Vec<(String, u64)> {
+        received.iter()
+            .filter(|msg| msg.source_id.as_deref() == Some(peer))
+            .map(|msg| (msg.payload.clone(), msg.position.unwrap()))
+            .collect()
+    };
+    assert_eq!(from(&client_address), vec![("one".to_string(), 1), ("two".to_string(), 2), ("three".to_string(), 3)]);
+    assert_eq!(received.len(), 5);
+    let others: Vec<String> = received.iter().filter(|msg| msg.source_id.as_deref() != Some(client_address.as_str())).map(|msg| msg.payload.clone()).collect();
+    assert_eq!(others, vec!["alpha", "beta"]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_socket_sinks_stop_retrying_once_the_input_completes() {
+    // Nothing listens, and the UDP sink's address never resolves
+    let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let messages = || MessageSource::from_messages(vec![Message::new("alpha".to_string()), Message::new("beta".to_string())]);
+    let tcp = TcpSink::<String>::connect(&address).with_max_backoff(Duration::from_millis(50));
+    let udp = UdpSink::<String>::connect("127.0.0.1:none").with_max_backoff(Duration::from_millis(50));
+
+    let run = async {
+        (PipelineTask::new(messages()) | PipelineTask::new(tcp)).run().await;
+        (PipelineTask::new(messages()) | PipelineTask::new(udp)).run().await;
+    };
+    tokio::time::timeout(Duration::from_secs(10), run).await.expect("the sinks kept retrying");
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_unix_socket_sink_and_source() {
+    // Length-prefixed frames may hold line breaks, and a stale socket file is replaced
+    let path = std::env::temp_dir().join(format!("floq_{}_socket.sock", std::process::id()));
+    drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+    let received = collect_in_background(UnixSocketSource::bind(&path)
+        .with_framing(Framing::LengthPrefixed)
+        .with_idle_timeout(Duration::from_millis(500)));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let trades = vec![
+        Message::new(Trade { symbol: "ABC".to_string(), price: 1.5 }),
+        Message::new(Trade { symbol: "multi\nline".to_string(), price: 2.0 }),
+    ];
+    let sink = UnixSocketSink::<Trade>::connect(&path).with_framing(Framing::LengthPrefixed);
+    (PipelineTask::new(MessageSource::from_messages(trades)) | PipelineTask::new(sink)).run().await;
+
+    let received = received.await.unwrap();
+    let trades: Vec<Trade> = received.iter().map(|msg| serde_json::from_str(&msg.payload).unwrap()).collect();
+    assert_eq!(trades.iter().map(|trade| trade.symbol.as_str()).collect::<Vec<_>>(), vec!["ABC", "multi\nline"]);
+    assert!(received.iter().all(|msg| msg.source_id.as_deref() == Some(path.to_str().unwrap())));
+    assert!(!path.exists());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_udp_sink_and_source() {
+    let address = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+    let received = collect_in_background(UdpSource::bind(&address).with_idle_timeout(Duration::from_millis(500)));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let source = MessageSource::from_messages(vec![
+        Message::new("<34>1 2024-01-01T00:00:00Z host app - - - disk full\n".to_string()),
+        Message::new("second".to_string()),
+    ]);
+    (PipelineTask::new(source) | PipelineTask::new(UdpSink::<String>::connect(&address))).run().await;
+
+    let received = received.await.unwrap();
+    let payloads: Vec<&str> = received.iter().map(|msg| msg.payload.as_str()).collect();
+    assert_eq!(payloads, vec!["<34>1 2024-01-01T00:00:00Z host app - - - disk full", "second"]);
+    // Both datagrams came from the sink's socket
+    let sender = received[0].source_id.clone().unwrap();
+    assert!(sender.starts_with("127.0.0.1:"));
+    assert_eq!(received[1].source_id.as_deref(), Some(sender.as_str()));
+}
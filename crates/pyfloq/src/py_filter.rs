@@ -9,12 +9,12 @@ use floq::functions::Filter;
 #[pyclass(name = "Filter")]
 #[derive(Clone)]
 pub struct PyFilter {
-    filter: Filter,
-    task: Arc<PipelineTask<Filter>>,
+    filter: Filter<String>,
+    task: Arc<PipelineTask<Filter<String>>>,
 }
 
 impl PyPipelineWrapper<String, String> for PyFilter {
-    type Component = Filter;
+    type Component = Filter<String>;
     
     fn get_task(&self) -> Arc<PipelineTask<Self::Component>> {
         self.task.clone()